@@ -69,6 +69,12 @@ impl ArrayContainer {
         self.array.shrink_to_fit();
     }
 
+    /// The number of bytes allocated by the array
+    #[inline]
+    pub fn mem_size(&self) -> usize {
+        self.array.capacity() * mem::size_of::<u16>()
+    }
+
     /// Reserve space for `additional` elements
     #[inline]
     pub fn reserve(&mut self, additional: usize) {
@@ -93,15 +99,63 @@ impl ArrayContainer {
         self.array.push(value);
     }
 
+    /// Push a value onto the end of the array without checking ordering
+    /// 
+    /// # Remarks
+    /// The value must be greater than every element in the array, this isn't checked even in 
+    /// debug builds. Violating this breaks the sorted invariant that searches and set operations rely on
+    #[inline]
+    pub(crate) fn push_unchecked(&mut self, value: u16) {
+        self.array.push(value);
+    }
+
+    /// Append a slice of strictly increasing values that are all greater than the array's maximum
+    /// 
+    /// # Remarks
+    /// The slice is copied in one go once it's been validated. The array isn't promoted, it may 
+    /// grow past `DEFAULT_MAX_SIZE` and it's up to the caller to choose a representation afterwards
+    /// 
+    /// # Panics
+    /// Panics if the values are not strictly increasing or don't follow the current maximum
+    pub fn extend_sorted(&mut self, values: &[u16]) {
+        if values.is_empty() {
+            return;
+        }
+
+        if let Some(max) = self.max() {
+            assert!(max < values[0], "Values must be greater than the array maximum");
+        }
+
+        assert!(
+            values.windows(2).all(|w| w[0] < w[1]),
+            "Values must be strictly increasing"
+        );
+
+        self.array.extend_from_slice(values);
+    }
+
     /// Add a value to the array
+    /// 
+    /// # Returns
+    /// Returns false if the value is absent and the array already holds `DEFAULT_MAX_SIZE` values
+    #[cfg(test)]
+    #[inline]
     pub fn add(&mut self, value: u16) -> bool {
+        self.add_bounded(value, DEFAULT_MAX_SIZE)
+    }
+
+    /// Add a value to the array as long as it holds fewer than `max_size` values
+    /// 
+    /// # Returns
+    /// Returns false if the value is absent and the array already holds `max_size` values
+    pub fn add_bounded(&mut self, value: u16, max_size: usize) -> bool {
         let can_append = {
             let is_max_value = match self.max() {
                 Some(max) => max < value,
                 None => true
             };
 
-            is_max_value && self.cardinality() < DEFAULT_MAX_SIZE
+            is_max_value && self.cardinality() < max_size
         };
 
         if can_append {
@@ -114,7 +168,7 @@ impl ArrayContainer {
                 true
             },
             Err(index) => {
-                if self.cardinality() < DEFAULT_MAX_SIZE {
+                if self.cardinality() < max_size {
                     self.array.insert(index, value);
 
                     true
@@ -130,20 +184,18 @@ impl ArrayContainer {
     pub fn add_range(&mut self, range: Range<u32>) {
         debug_assert!(is_valid_range(range.clone()));
 
+        // Nothing to add. `range.start` may be `1 << 16` here so it can't be truncated and added
         if range.is_empty() {
-            self.add(range.start as u16);
-        }
-        else {
-            // Resize to fit all new elements
-            self.reserve(range.len());
-
-            // Append new elements
-            for i in range {
-                // This is technically valid since we only store the lower 16 bits
-                // inside containers. The upper 16 are stored as keys in the roaring bitmap
-                self.array.push(i as u16);
-            }
+            return;
         }
+
+        // Replace any existing values within the range with the full range
+        let start = array_ops::count_less(&self.array, range.start as u16);
+        let end = self.len() - array_ops::count_greater(&self.array, (range.end - 1) as u16);
+
+        // This is technically valid since we only store the lower 16 bits
+        // inside containers. The upper 16 are stored as keys in the roaring bitmap
+        self.array.splice(start..end, range.map(|i| i as u16));
     }
 
     /// Remove a specified value from the array
@@ -190,23 +242,64 @@ impl ArrayContainer {
     pub fn contains_range(&self, range: Range<u32>) -> bool {
         debug_assert!(is_valid_range(range.clone()));
 
+        // We always contain the empty set
+        if range.is_empty() {
+            return true;
+        }
+
         let rs = range.start as u16;
         let re = (range.end - 1) as u16;
 
         let min = array_ops::advance_until(&self.array, 0, rs);
         let max = array_ops::advance_until(&self.array, min, re);
 
-        if  min < self.len() && max < self.len() {
-            return max - min == (re - rs) as usize && self.array[min] == rs && self.array[max] == re;
+        // Either endpoint is past the end of the array, so it can't be present
+        if min >= self.len() || max >= self.len() {
+            return false;
+        }
+
+        max - min == (re - rs) as usize && self.array[min] == rs && self.array[max] == re
+    }
+
+    /// Get the number of values within [min-max)
+    pub fn cardinality_range(&self, range: Range<u32>) -> usize {
+        debug_assert!(is_valid_range(range.clone()));
+
+        if range.is_empty() {
+            return 0;
+        }
+
+        let num_less = array_ops::count_less(&self.array, range.start as u16);
+        let num_greater = array_ops::count_greater(&self.array, (range.end - 1) as u16);
+
+        self.len() - num_less - num_greater
+    }
+
+    /// The length of the longest run of consecutive values in the array
+    pub fn longest_run(&self) -> usize {
+        if self.array.is_empty() {
+            return 0;
         }
 
-        false
+        let mut longest = 1;
+        let mut current = 1;
+        for pair in self.array.windows(2) {
+            if pair[0] + 1 == pair[1] {
+                current += 1;
+                longest = longest.max(current);
+            }
+            else {
+                current = 1;
+            }
+        }
+
+        longest
     }
 
-    /// Check if the array is full
+    /// Check if the array holds every value in the container's range
     #[inline]
     pub fn is_full(&self) -> bool {
-        self.array.len() == DEFAULT_MAX_SIZE
+        self.array.len() == 1 << 16
     }
 
     /// Find the element of a given rank from `start_rank`. 
@@ -247,12 +340,45 @@ impl ArrayContainer {
         }
     }
 
+    /// The smallest value not in the array. Returns `None` if every value is present
+    pub fn min_excluded(&self) -> Option<u16> {
+        self.min_excluded_from(0)
+    }
+
+    /// The smallest value not in the array that is at least `from`. Returns `None` if every such value is present
+    pub fn min_excluded_from(&self, from: u16) -> Option<u16> {
+        // Values are sorted and unique so `array[i] == from + (i - start)` holds for a prefix 
+        // of the values from `start`, binary search for the end of that prefix
+        let start = self.array.partition_point(|value| *value < from);
+        let mut lower = start;
+        let mut upper = self.array.len();
+
+        while lower < upper {
+            let mid = (lower + upper) / 2;
+
+            if self.array[mid] as usize == from as usize + (mid - start) {
+                lower = mid + 1;
+            }
+            else {
+                upper = mid;
+            }
+        }
+
+        let excluded = from as usize + (lower - start);
+        if excluded < (1 << 16) {
+            Some(excluded as u16)
+        }
+        else {
+            None
+        }
+    }
+
     /// Return the number of values equal to or smaller than `value`
     #[inline]
     pub fn rank(&self, value: u16) -> usize {
         match self.array.binary_search(&value) {
             Ok(index) => index + 1,
-            Err(index) => if index > 0 { index - 1 } else { 0 }
+            Err(index) => index
         }
     }
 
@@ -262,10 +388,10 @@ impl ArrayContainer {
             return 0;
         }
 
-        let mut num_runs = 0; // Always at least one run
+        let mut num_runs = 1; // Always at least one run
         let mut previous = self.array[0];
 
-        for value in self.array.iter() {
+        for value in self.array[1..].iter() {
             if *value != previous + 1 {
                 num_runs += 1;
             }
@@ -323,13 +449,7 @@ impl ArrayContainer {
             let num_bytes = mem::size_of::<u16>() * cardinality;
             let bytes_slice = slice::from_raw_parts_mut(ptr, num_bytes);
 
-            let num_read = buf.read(bytes_slice)?;
-            if num_read != num_bytes {
-                return Err(
-                    io::Error::new(io::ErrorKind::UnexpectedEof, 
-                    "Unexpected end of stream"
-                ));
-            }
+            buf.read_exact(bytes_slice)?;
 
             result.set_cardinality(cardinality);
 
@@ -432,6 +552,11 @@ impl SetOr<Self> for ArrayContainer {
         Container::Array(result)
     }
     
+    #[inline]
+    fn or_cardinality(&self, other: &Self) -> usize {
+        self.cardinality() + other.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_or(mut self, other: &Self) -> Container {
         let max_cardinality = self.len() + other.len();
         
@@ -495,6 +620,11 @@ impl SetOr<BitsetContainer> for ArrayContainer {
         Container::Bitset(result)
     }
     
+    #[inline]
+    fn or_cardinality(&self, other: &BitsetContainer) -> usize {
+        self.cardinality() + other.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     // TODO: Find a way to do this inplace
     #[inline]
     fn inplace_or(self, other: &BitsetContainer) -> Container {
@@ -508,6 +638,11 @@ impl SetOr<RunContainer> for ArrayContainer {
         SetOr::or(other, self)
     }
     
+    #[inline]
+    fn or_cardinality(&self, other: &RunContainer) -> usize {
+        self.cardinality() + other.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     // TODO: Find a way to do this inplace
     #[inline]
     fn inplace_or(self, other: &RunContainer) -> Container {
@@ -634,6 +769,11 @@ impl SetAndNot<Self> for ArrayContainer {
         Container::Array(result)
     }
     
+    #[inline]
+    fn and_not_cardinality(&self, other: &Self) -> usize {
+        self.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_and_not(mut self, other: &Self) -> Container {
         unsafe {
             // Shift the elements of self over to accomodate new contents
@@ -670,6 +810,12 @@ impl SetAndNot<BitsetContainer> for ArrayContainer {
         Container::Array(result)
     }
     
+    fn and_not_cardinality(&self, other: &BitsetContainer) -> usize {
+        self.array.iter()
+            .filter(|value| !other.contains(**value))
+            .count()
+    }
+
     // TODO: Find a way to do this inplace
     fn inplace_and_not(self, other: &BitsetContainer) -> Container {
         SetAndNot::and_not(&self, other)
@@ -678,59 +824,28 @@ impl SetAndNot<BitsetContainer> for ArrayContainer {
 
 impl SetAndNot<RunContainer> for ArrayContainer {
     fn and_not(&self, other: &RunContainer) -> Container {
-        if other.is_empty() {
-            return Container::Array(self.clone());
-        }
+        SetAndNot::inplace_and_not(self.clone(), other)
+    }
+    
+    #[inline]
+    fn and_not_cardinality(&self, other: &RunContainer) -> usize {
+        self.cardinality() - SetAnd::and_cardinality(self, other)
+    }
 
-        let mut result = ArrayContainer::with_capacity(self.cardinality());
- 
-        let runs = other.deref();
-        let run = runs[0];
-        let mut run_start: usize = usize::from(run.value);
-        let mut run_end: usize = usize::from(run.end());
-        let mut which_run = 0;
-
-        let mut i: isize = 0;
-        while (i as usize) < self.cardinality() {
-            let value = usize::from(self.array[i as usize]);
-
-            if value < run_start {
-                result.push(value as u16);
-            } else if value <= run_end {
-                ()
-            }
-            else {
-                loop {
-                    if which_run + 1 < runs.len() {
-                        which_run += 1;
-
-                        let run = runs[which_run];
-                        run_start = usize::from(run.value);
-                        run_end = usize::from(run.end());
-                    }
-                    else {
-                        run_end = (1 << 16) + 1;
-                        run_start = run_end;
-                    }
-
-                    if value <= run_end {
-                        break;
-                    }
-                }
+    fn inplace_and_not(mut self, other: &RunContainer) -> Container {
+        // Merge the values against the runs, skipping past runs that end before each value
+        let mut runs = other.iter_runs().peekable();
 
-                i -= 1;
-            }
+        self.array.retain(|value| {
+            while runs.next_if(|run| run.end() < *value).is_some() {}
 
-            i += 1;
-        }
+            match runs.peek() {
+                Some(run) => *value < run.value,
+                None => true
+            }
+        });
 
-        Container::Array(result)
-    }
-    
-    // TODO: Find a way to do this inplace
-    #[inline]
-    fn inplace_and_not(self, other: &RunContainer) -> Container {
-        SetAndNot::and_not(&self, other)
+        Container::Array(self)
     }
 }
 
@@ -748,6 +863,11 @@ impl SetXor<Self> for ArrayContainer {
         }
     }
     
+    #[inline]
+    fn xor_cardinality(&self, other: &Self) -> usize {
+        self.cardinality() + other.cardinality() - 2 * SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_xor(mut self, other: &Self) -> Container {
         // TODO: See if this wouldn't be more optimal using a scalar approach to avoid realloc
         unsafe {
@@ -788,6 +908,11 @@ impl SetXor<BitsetContainer> for ArrayContainer {
         }
     }
     
+    #[inline]
+    fn xor_cardinality(&self, other: &BitsetContainer) -> usize {
+        self.cardinality() + other.cardinality() - 2 * SetAnd::and_cardinality(self, other)
+    }
+
     // TODO: Find a way to do this inplace
     #[inline]
     fn inplace_xor(self, other: &BitsetContainer) -> Container {
@@ -812,6 +937,11 @@ impl SetXor<RunContainer> for ArrayContainer {
         }
     }
     
+    #[inline]
+    fn xor_cardinality(&self, other: &RunContainer) -> usize {
+        self.cardinality() + other.cardinality() - 2 * SetAnd::and_cardinality(self, other)
+    }
+
     // TODO: Find a way to do this inplace
     #[inline]
     fn inplace_xor(self, other: &RunContainer) -> Container {
@@ -895,14 +1025,22 @@ impl SetNot for ArrayContainer {
     fn not(&self, range: Range<u32>) -> Container {
         debug_assert!(is_valid_range(range.clone()));
 
-        let start_index = self.rank(range.start as u16).saturating_sub(1);
-        let end_index = self.rank(range.end.saturating_sub(1) as u16);
+        // Values in [start-end) of the array lie within the range and are removed by the flip,
+        // the rest of the range is added
+        let count_below = |bound: u32| {
+            if bound > u32::from(u16::MAX) {
+                self.array.len()
+            }
+            else {
+                array_ops::count_less(&self.array, bound as u16)
+            }
+        };
 
-        let current_values = end_index.saturating_sub(start_index).saturating_sub(1);
-        let span = range.len();
-        let new_values = span - current_values;
-        let change = (new_values as isize) - (current_values as isize);
-        let new_card = ((self.array.len() as isize) + change) as usize;
+        let start = count_below(range.start);
+        let end = count_below(range.end);
+
+        let removed = end - start;
+        let new_card = self.array.len() - removed + (range.len() - removed);
 
         // Result is going to be a bitset
         if new_card > DEFAULT_MAX_SIZE {
@@ -914,22 +1052,21 @@ impl SetNot for ArrayContainer {
         // Result is going to be an array
         else {
             let mut result = ArrayContainer::with_capacity(new_card);
-            result.array.extend_from_slice(&self.array[..start_index]);
+            result.array.extend_from_slice(&self.array[..start]);
 
-            let mut i = start_index;
-            let mut value = self.array[start_index];
-            while u32::from(value) < range.end && i <= end_index {
-                if value != self.array[i] {
-                    result.push(value);
+            let mut removed = self.array[start..end].iter().peekable();
+            for value in range {
+                let value = value as u16;
+
+                if removed.peek() == Some(&&value) {
+                    removed.next();
                 }
                 else {
-                    i += 1;
+                    result.push(value);
                 }
-
-                value += 1;
             }
 
-            result.array.extend_from_slice(&self.array[(end_index + 1)..]);
+            result.array.extend_from_slice(&self.array[end..]);
 
             Container::Array(result)
         }
@@ -1008,6 +1145,22 @@ mod test {
         }
     }
 
+    #[test]
+    fn load_range_bounds() {
+        // An empty range at the top of the container must not wrap around to 0
+        let mut array = ArrayContainer::new();
+        array.add_range((1 << 16)..(1 << 16));
+        array.add_range(10..10);
+        assert!(array.is_empty());
+
+        // Every value including the maximum is added over existing values
+        array.add_range(65_530..(1 << 16));
+        array.add_range(0..(1 << 16));
+        assert_eq!(array.cardinality(), 1 << 16);
+        assert_eq!(array.max(), Some(u16::MAX));
+        assert!(array.iter().cloned().eq(0..=u16::MAX));
+    }
+
     #[test]
     fn remove() {
         let range = 0..10;
@@ -1081,6 +1234,124 @@ mod test {
         assert!(array.contains_range(10..20));
     }
 
+    #[test]
+    fn extend_sorted() {
+        let data = generate_data(0..65535, 3_000);
+        let (head, tail) = data.split_at(1_000);
+
+        let mut expected = ArrayContainer::new();
+        for value in data.iter() {
+            expected.add(*value);
+        }
+
+        let mut found = ArrayContainer::new();
+        found.extend_sorted(head);
+        found.extend_sorted(&[]);
+        found.extend_sorted(tail);
+
+        assert_eq!(found.cardinality(), expected.cardinality());
+        assert!(found.iter().eq(expected.iter()));
+
+        // The array isn't promoted past the default threshold
+        let values: Vec<u16> = (0..(DEFAULT_MAX_SIZE as u16 + 100)).collect();
+        let mut large = ArrayContainer::new();
+        large.extend_sorted(&values);
+        assert_eq!(large.cardinality(), DEFAULT_MAX_SIZE + 100);
+        assert!(large.iter().eq(values.iter()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn extend_sorted_below_max() {
+        let mut array = ArrayContainer::new();
+        array.extend_sorted(&[10, 20]);
+        array.extend_sorted(&[15, 30]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn extend_sorted_unordered() {
+        let mut array = ArrayContainer::new();
+        array.extend_sorted(&[10, 30, 20]);
+    }
+
+    #[test]
+    fn array_run_and_not_brute_force() {
+        let mut seed = 0u8;
+        let run_sets: [&[(u32, u32)]; 6] = [
+            &[],
+            &[(0, 1)],
+            &[(0, 1 << 16)],
+            &[(65_535, 1 << 16)],
+            &[(5, 10), (11, 12), (20, 40), (1_000, 30_000), (65_000, 65_536)],
+            &[(100, 101), (102, 103), (104, 105), (40_000, 40_100)],
+        ];
+
+        for runs in run_sets.iter() {
+            for len in [0, 1, 10, 500, 4_000].iter() {
+                let array = ArrayContainer::from_data(&generate_seeded_data(0..65_535, *len, seed));
+                seed = seed.wrapping_add(1);
+
+                let mut run = RunContainer::new();
+                for (start, end) in runs.iter() {
+                    run.add_range(*start..*end);
+                }
+
+                // Also test random runs generated from the seeded data
+                let mut random = RunContainer::new();
+                for value in generate_seeded_data(0..65_000u16, 200, seed).iter() {
+                    let value = u32::from(*value);
+                    random.add_range(value..(value + (value % 97)));
+                }
+
+                for other in [&run, &random].iter() {
+                    let expected: Vec<u16> = array.iter()
+                        .cloned()
+                        .filter(|value| !other.contains(*value))
+                        .collect();
+
+                    let found = SetAndNot::and_not(&array, *other);
+                    assert!(found.iter().eq(expected.iter().cloned()), "Failed for {:?}", runs);
+
+                    let found = SetAndNot::inplace_and_not(array.clone(), *other);
+                    assert!(found.iter().eq(expected.iter().cloned()), "Failed inplace for {:?}", runs);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn contains_range_bounds() {
+        let mut array = ArrayContainer::new();
+        array.add_range(0..10);
+        array.add_range(20..30);
+        array.add_range(65530..65536);
+
+        // Endpoints present
+        assert!(array.contains_range(0..10));
+        assert!(array.contains_range(20..30));
+        assert!(array.contains_range(22..25));
+        assert!(!array.contains_range(0..30));
+
+        // Endpoints absent
+        assert!(!array.contains_range(10..20));
+        assert!(!array.contains_range(5..15));
+        assert!(!array.contains_range(15..25));
+        assert!(!array.contains_range(30..40));
+
+        // Array boundaries
+        assert!(array.contains_range(0..1));
+        assert!(array.contains_range(65535..65536));
+        assert!(array.contains_range(65530..65536));
+        assert!(!array.contains_range(65529..65536));
+        assert!(!array.contains_range(0..65536));
+
+        // Empty ranges are always contained
+        assert!(array.contains_range(40..40));
+        assert!(ArrayContainer::new().contains_range(0..0));
+        assert!(!ArrayContainer::new().contains_range(0..1));
+    }
+
     #[test]
     fn select() {
         let range = 0..30;
@@ -1126,6 +1397,19 @@ mod test {
         assert_eq!(rank, 21);
     }
 
+    #[test]
+    fn rank_absent() {
+        let mut array = ArrayContainer::new();
+        for value in (0..100).step_by(2) {
+            array.add(value);
+        }
+
+        assert_eq!(array.rank(0), 1);
+        assert_eq!(array.rank(1), 1);
+        assert_eq!(array.rank(51), 26);
+        assert_eq!(array.rank(65535), 50);
+    }
+
     #[test]
     fn from_bitset() {
         let data = generate_data(0..65535, 3_000);
@@ -1148,6 +1432,38 @@ mod test {
         }
     }
     
+    #[test]
+    fn num_runs() {
+        let mut a = ArrayContainer::new();
+        a.add_range(10..20);
+        a.add(30);
+        a.add(65535);
+
+        assert_eq!(a.num_runs(), 3);
+
+        let mut b = ArrayContainer::new();
+        b.add(65535);
+
+        assert_eq!(b.num_runs(), 1);
+    }
+
+    #[test]
+    fn longest_run() {
+        let mut a = ArrayContainer::new();
+        assert_eq!(a.longest_run(), 0);
+
+        a.add(7);
+        assert_eq!(a.longest_run(), 1);
+
+        a.add_range(10..20);
+        a.add_range(30..33);
+        a.add_range(65_530..(1 << 16));
+        assert_eq!(a.longest_run(), 10);
+
+        a.add_range(20..30);
+        assert_eq!(a.longest_run(), 23);
+    }
+
     #[test]
     fn round_trip_serialize() {
         let data = generate_data(0..65535, 2_000);
@@ -1194,6 +1510,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_array_or_cardinality() {
+        op_card_test::<ArrayContainer, ArrayContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_array_and() {
         op_test::<ArrayContainer, ArrayContainer, u16, _, Container>(
@@ -1215,6 +1543,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_array_and_not_cardinality() {
+        op_card_test::<ArrayContainer, ArrayContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_array_xor() {
         op_test::<ArrayContainer, ArrayContainer, u16, _, Container>(
@@ -1222,6 +1562,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_array_xor_cardinality() {
+        op_card_test::<ArrayContainer, ArrayContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_array_subset_of() {
         op_subset_test::<ArrayContainer, ArrayContainer, u16>();
@@ -1284,6 +1636,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_bitset_or_cardinality() {
+        op_card_test::<ArrayContainer, BitsetContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_bitset_and() {
         op_test::<ArrayContainer, BitsetContainer, u16, _, Container>(
@@ -1305,6 +1669,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_bitset_and_not_cardinality() {
+        op_card_test::<ArrayContainer, BitsetContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_bitset_xor() {
         op_test::<ArrayContainer, BitsetContainer, u16, _, Container>(
@@ -1312,6 +1688,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_bitset_xor_cardinality() {
+        op_card_test::<ArrayContainer, BitsetContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_bitset_subset_of() {
         op_subset_test::<ArrayContainer, BitsetContainer, u16>();
@@ -1352,6 +1740,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_run_or_cardinality() {
+        op_card_test::<ArrayContainer, RunContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_run_and() {
         op_test::<ArrayContainer, RunContainer, u16, _, Container>(
@@ -1373,6 +1773,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_run_and_not_cardinality() {
+        op_card_test::<ArrayContainer, RunContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_run_xor() {
         op_test::<ArrayContainer, RunContainer, u16, _, Container>(
@@ -1380,6 +1792,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn array_run_xor_cardinality() {
+        op_card_test::<ArrayContainer, RunContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn array_run_inplace_or() {
         op_test::<ArrayContainer, RunContainer, u16, _, Container>(
@@ -0,0 +1,600 @@
+// Required since code is conditionally compiled out in this module
+#![allow(dead_code)]
+
+#[cfg(target_arch = "x86_64")] use std::ptr;
+#[cfg(target_arch = "x86_64")] use std::arch::x86_64::{
+    __m128i,
+    __m256i,
+
+    _mm_alignr_epi8,
+    _mm_lddqu_si128,
+    _mm_or_si128,
+    _mm_packs_epi16,
+    _mm_setzero_si128,
+    _mm_movemask_epi8,
+    _mm_shuffle_epi8,
+    _mm_storeu_si128,
+
+    _mm256_broadcastsi128_si256,
+    _mm256_set_m128i,
+    _mm256_set1_epi16,
+    _mm256_setzero_si256,
+    _mm256_cmpeq_epi16,
+    _mm256_or_si256,
+    _mm256_extract_epi16,
+    _mm256_castsi256_si128,
+    _mm256_extracti128_si256,
+    _mm256_lddqu_si256,
+    _mm256_alignr_epi8,
+    _mm256_permute2x128_si256,
+    _mm256_min_epu16,
+    _mm256_max_epu16,
+    _mm256_packs_epi16,
+    _mm256_movemask_epi8,
+};
+
+#[cfg(target_arch = "x86_64")] use super::scalar;
+#[cfg(target_arch = "x86_64")] use super::vector::UNIQUE_SHUFFLE;
+
+/// Number of 16 bit elements processed per block
+const BLOCK_SIZE: usize = 8;
+
+/// Number of 16 bit elements held in a 256 bit register
+const WIDE_BLOCK_SIZE: usize = 16;
+
+/// Compute the union between `a` and `b` and append the result into `out`
+/// 
+/// # Remarks
+/// This is the vectorized merge used by the SSE kernel widened to 16 values per register.
+/// Blocks are merged with a rotate/min/max network, with the smaller half written out 
+/// after removing duplicates and the larger half carried into the next merge
+/// 
+/// # Returns
+/// Returns the number of elements appended to `out`
+/// 
+/// # Safety
+/// - Assumes `out` contains enough space to hold the output
+/// - Requires that the executing CPU supports AVX2
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn or(a: &[u16], b: &[u16], out: *mut u16) -> usize {
+    if a.len() < WIDE_BLOCK_SIZE || b.len() < WIDE_BLOCK_SIZE {
+        return scalar::or(a, b, out);
+    }
+
+    let ptr_a = a.as_ptr();
+    let ptr_b = b.as_ptr();
+
+    let simd_len_a = a.len() / WIDE_BLOCK_SIZE;
+    let simd_len_b = b.len() / WIDE_BLOCK_SIZE;
+
+    let mut i_a = 1;
+    let mut i_b = 1;
+    let mut count = 0;
+
+    let v_a = _mm256_lddqu_si256(ptr_a as *const __m256i);
+    let v_b = _mm256_lddqu_si256(ptr_b as *const __m256i);
+
+    let mut min = _mm256_setzero_si256();
+    let mut max = _mm256_setzero_si256();
+    let mut last_store = _mm256_set1_epi16(-1);
+
+    merge(v_a, v_b, &mut min, &mut max);
+
+    count += store_or(last_store, min, out.add(count));
+    last_store = min;
+
+    if i_a < simd_len_a && i_b < simd_len_b {
+        let mut v;
+        let mut s_a = *(ptr_a.add(i_a * WIDE_BLOCK_SIZE));
+        let mut s_b = *(ptr_b.add(i_b * WIDE_BLOCK_SIZE));
+
+        loop {
+            if s_a <= s_b {
+                v = _mm256_lddqu_si256((ptr_a as *const __m256i).add(i_a));
+
+                i_a += 1;
+                if i_a < simd_len_a {
+                    s_a = *(ptr_a.add(i_a * WIDE_BLOCK_SIZE));
+                }
+                else {
+                    break;
+                }
+            }
+            else {
+                v = _mm256_lddqu_si256((ptr_b as *const __m256i).add(i_b));
+
+                i_b += 1;
+                if i_b < simd_len_b {
+                    s_b = *(ptr_b.add(i_b * WIDE_BLOCK_SIZE));
+                }
+                else {
+                    break;
+                }
+            }
+
+            merge(v, max, &mut min, &mut max);
+            count += store_or(last_store, min, out.add(count));
+            last_store = min;
+        }
+
+        merge(v, max, &mut min, &mut max);
+        count += store_or(last_store, min, out.add(count));
+        last_store = min;
+    }
+
+    // The carried maximums plus less than a block of leftovers from the exhausted slice
+    let mut buffer: [u16; 2 * WIDE_BLOCK_SIZE] = Default::default();
+    let mut buf_len = store_or(last_store, max, buffer.as_mut_ptr());
+
+    let mut finish = |s0: &[u16], pos_0: usize, s1: &[u16], pos_1: usize| {
+        let last_end = pos_0 * WIDE_BLOCK_SIZE;
+        let rem = s0.len() - last_end;
+
+        let ptr_buf = buffer.as_mut_ptr();
+        ptr::copy_nonoverlapping(s0.as_ptr().add(last_end), ptr_buf.add(buf_len), rem);
+
+        buf_len += rem;
+        buffer[..buf_len].sort_unstable();
+
+        let mut unique = 1;
+        for i in 1..buf_len {
+            if buffer[i] != buffer[unique - 1] {
+                buffer[unique] = buffer[i];
+                unique += 1;
+            }
+        }
+
+        count += scalar::or(
+            &buffer[..unique],
+            &s1[(pos_1 * WIDE_BLOCK_SIZE)..],
+            out.add(count)
+        );
+    };
+
+    if i_a == simd_len_a {
+        finish(a, i_a, b, i_b);
+    }
+    else {
+        finish(b, i_b, a, i_a);
+    }
+
+    count
+}
+
+/// Compute the symmetric difference between `a` and `b` and append the result into `out`
+/// 
+/// # Remarks
+/// Uses the same merge network as [`or`], values are written out if they differ from both neighbours
+/// since a value present in both slices shows up as an adjacent pair. This delays the output by one value
+/// 
+/// # Returns
+/// Returns the number of elements appended to `out`
+/// 
+/// # Safety
+/// - Assumes `out` contains enough space to hold `a.len() + b.len()` elements
+/// - Requires that the executing CPU supports AVX2
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn xor(a: &[u16], b: &[u16], out: *mut u16) -> usize {
+    if a.len() < WIDE_BLOCK_SIZE || b.len() < WIDE_BLOCK_SIZE {
+        return scalar::xor(a, b, out);
+    }
+
+    let ptr_a = a.as_ptr();
+    let ptr_b = b.as_ptr();
+
+    let simd_len_a = a.len() / WIDE_BLOCK_SIZE;
+    let simd_len_b = b.len() / WIDE_BLOCK_SIZE;
+
+    let mut i_a = 1;
+    let mut i_b = 1;
+    let mut count = 0;
+
+    let v_a = _mm256_lddqu_si256(ptr_a as *const __m256i);
+    let v_b = _mm256_lddqu_si256(ptr_b as *const __m256i);
+
+    let mut min = _mm256_setzero_si256();
+    let mut max = _mm256_setzero_si256();
+    let mut last_store = _mm256_set1_epi16(-1);
+
+    merge(v_a, v_b, &mut min, &mut max);
+
+    count += store_xor(last_store, min, out.add(count));
+    last_store = min;
+
+    if i_a < simd_len_a && i_b < simd_len_b {
+        let mut v;
+        let mut s_a = *(ptr_a.add(i_a * WIDE_BLOCK_SIZE));
+        let mut s_b = *(ptr_b.add(i_b * WIDE_BLOCK_SIZE));
+
+        loop {
+            if s_a <= s_b {
+                v = _mm256_lddqu_si256((ptr_a as *const __m256i).add(i_a));
+
+                i_a += 1;
+                if i_a < simd_len_a {
+                    s_a = *(ptr_a.add(i_a * WIDE_BLOCK_SIZE));
+                }
+                else {
+                    break;
+                }
+            }
+            else {
+                v = _mm256_lddqu_si256((ptr_b as *const __m256i).add(i_b));
+
+                i_b += 1;
+                if i_b < simd_len_b {
+                    s_b = *(ptr_b.add(i_b * WIDE_BLOCK_SIZE));
+                }
+                else {
+                    break;
+                }
+            }
+
+            merge(v, max, &mut min, &mut max);
+            count += store_xor(last_store, min, out.add(count));
+            last_store = min;
+        }
+
+        merge(v, max, &mut min, &mut max);
+        count += store_xor(last_store, min, out.add(count));
+        last_store = min;
+    }
+
+    // The carried maximums, the delayed last maximum and less than a block of leftovers
+    let mut buffer: [u16; 2 * WIDE_BLOCK_SIZE] = Default::default();
+    let mut buf_len = store_xor(last_store, max, buffer.as_mut_ptr());
+
+    let last = _mm256_extract_epi16(max, 15) as u16;
+    if last != _mm256_extract_epi16(max, 14) as u16 {
+        buffer[buf_len] = last;
+        buf_len += 1;
+    }
+
+    let mut finish = |s0: &[u16], pos_0: usize, s1: &[u16], pos_1: usize| {
+        let last_end = pos_0 * WIDE_BLOCK_SIZE;
+        let rem = s0.len() - last_end;
+
+        let ptr_buf = buffer.as_mut_ptr();
+        ptr::copy_nonoverlapping(s0.as_ptr().add(last_end), ptr_buf.add(buf_len), rem);
+
+        buf_len += rem;
+        buffer[..buf_len].sort_unstable();
+
+        // Drop the pairs of values present in both slices
+        let mut unique = 0;
+        let mut i = 0;
+        while i < buf_len {
+            if i + 1 < buf_len && buffer[i] == buffer[i + 1] {
+                i += 2;
+            }
+            else {
+                buffer[unique] = buffer[i];
+                unique += 1;
+                i += 1;
+            }
+        }
+
+        count += scalar::xor(
+            &buffer[..unique],
+            &s1[(pos_1 * WIDE_BLOCK_SIZE)..],
+            out.add(count)
+        );
+    };
+
+    if i_a == simd_len_a {
+        finish(a, i_a, b, i_b);
+    }
+    else {
+        finish(b, i_b, a, i_a);
+    }
+
+    count
+}
+
+/// Find the cardinality of the intersection between `a` and `b`
+///
+/// # Remarks
+/// Blocks of 8 values from each slice are compared all-against-all using 4 256 bit comparisons,
+/// advancing whichever block has the smaller maximum similar to a scalar merge
+///
+/// # Safety
+/// - Requires that the executing CPU supports AVX2
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn and_cardinality(a: &[u16], b: &[u16]) -> usize {
+    // The length of one list is shorter than the vector size, fallback to a scalar algorithm
+    if a.len() < BLOCK_SIZE || b.len() < BLOCK_SIZE {
+        return scalar::and_cardinality(a, b);
+    }
+
+    let ptr_a = a.as_ptr();
+    let ptr_b = b.as_ptr();
+    let stop_a = (a.len() / BLOCK_SIZE) * BLOCK_SIZE;
+    let stop_b = (b.len() / BLOCK_SIZE) * BLOCK_SIZE;
+
+    let mut i_a = 0;
+    let mut i_b = 0;
+    let mut count = 0;
+
+    let mut va = load_broadcast(ptr_a);
+    let mut vb = load_rotations(ptr_b);
+
+    loop {
+        count += match_count(va, &vb);
+
+        let max_a = *ptr_a.add(i_a + BLOCK_SIZE - 1);
+        let max_b = *ptr_b.add(i_b + BLOCK_SIZE - 1);
+
+        if max_a <= max_b {
+            i_a += BLOCK_SIZE;
+
+            if i_a == stop_a {
+                break;
+            }
+
+            va = load_broadcast(ptr_a.add(i_a));
+        }
+
+        if max_b <= max_a {
+            i_b += BLOCK_SIZE;
+
+            if i_b == stop_b {
+                break;
+            }
+
+            vb = load_rotations(ptr_b.add(i_b));
+        }
+    }
+
+    // Finish the remainder with a scalar merge
+    count + scalar::and_cardinality(&a[i_a..], &b[i_b..])
+}
+
+/// Compute the difference between `a` and `b` and append the result into `out`
+///
+/// # Remarks
+/// Blocks are compared in the same way as `and_cardinality`. The matches for a block of `a` are
+/// accumulated across every overlapping block of `b` and the unmatched values are written out
+/// once the block of `a` has been passed
+///
+/// # Returns
+/// Returns the number of elements appended to `out`
+///
+/// # Safety
+/// - Assumes `out` contains enough space to hold `a.len()` elements
+/// - Requires that the executing CPU supports AVX2
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+pub unsafe fn and_not(a: &[u16], b: &[u16], out: *mut u16) -> usize {
+    // The length of one list is shorter than the vector size, fallback to a scalar algorithm
+    if a.len() < BLOCK_SIZE || b.len() < BLOCK_SIZE {
+        return scalar::and_not(a, b, out);
+    }
+
+    let ptr_a = a.as_ptr();
+    let ptr_b = b.as_ptr();
+    let stop_a = (a.len() / BLOCK_SIZE) * BLOCK_SIZE;
+    let stop_b = (b.len() / BLOCK_SIZE) * BLOCK_SIZE;
+
+    let mut i_a = 0;
+    let mut i_b = 0;
+    let mut count = 0;
+    let mut mask = 0;
+
+    let mut va = load_broadcast(ptr_a);
+    let mut vb = load_rotations(ptr_b);
+
+    loop {
+        mask |= match_mask(va, &vb);
+
+        let max_a = *ptr_a.add(i_a + BLOCK_SIZE - 1);
+        let max_b = *ptr_b.add(i_b + BLOCK_SIZE - 1);
+
+        if max_a <= max_b {
+            // No later block of `b` can match this block, write out the values that weren't found
+            count += store_and_not(va, mask, out.add(count));
+            i_a += BLOCK_SIZE;
+            mask = 0;
+
+            if i_a == stop_a {
+                break;
+            }
+
+            va = load_broadcast(ptr_a.add(i_a));
+        }
+
+        if max_b <= max_a {
+            i_b += BLOCK_SIZE;
+
+            if i_b == stop_b {
+                break;
+            }
+
+            vb = load_rotations(ptr_b.add(i_b));
+        }
+    }
+
+    // `b` ran out of blocks part way through a block of `a`, 
+    // the unmatched values of that block still need comparing against the tail of `b`
+    if i_a < stop_a {
+        let mut buffer = [0u16; BLOCK_SIZE];
+        let len = store_and_not(va, mask, buffer.as_mut_ptr());
+
+        count += scalar::and_not(&buffer[..len], &b[i_b..], out.add(count));
+        i_a += BLOCK_SIZE;
+    }
+
+    // Finish the remainder with a scalar merge
+    count + scalar::and_not(&a[i_a..], &b[i_b..], out.add(count))
+}
+
+/// Rotate the 16 values of a register down by one, moving the first value to the end
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn rotate(v: __m256i) -> __m256i {
+    _mm256_alignr_epi8(_mm256_permute2x128_si256(v, v, 0x01), v, 2)
+}
+
+/// Merge the sorted registers `a` and `b` placing the smallest 16 values in `min` and the largest in `max`
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn merge(a: __m256i, b: __m256i, min: &mut __m256i, max: &mut __m256i) {
+    let mut temp = _mm256_min_epu16(a, b);
+    *max = _mm256_max_epu16(a, b);
+
+    for _i in 1..WIDE_BLOCK_SIZE {
+        temp = rotate(temp);
+        *min = _mm256_min_epu16(temp, *max);
+        *max = _mm256_max_epu16(temp, *max);
+        temp = *min;
+    }
+
+    *min = rotate(*min);
+}
+
+/// Write the values of `new` that differ from their predecessor into `output`,
+/// the predecessor of the first value is the last value of `old`
+/// 
+/// # Returns
+/// The number of values written
+/// 
+/// # Safety
+/// - Up to 16 values are stored regardless of how many are unique
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn store_or(old: __m256i, new: __m256i, output: *mut u16) -> usize {
+    // Shift the last value of `old` in ahead of `new`
+    let prev = _mm256_alignr_epi8(new, _mm256_permute2x128_si256(old, new, 0x21), 14);
+    let packed = _mm256_packs_epi16(_mm256_cmpeq_epi16(prev, new), _mm256_setzero_si256());
+    let mask = _mm256_movemask_epi8(packed) as u32;
+
+    // Compact each lane separately using the 8 value shuffle table
+    let mask_lo = (mask & 0xFF) as usize;
+    let mask_hi = ((mask >> 16) & 0xFF) as usize;
+
+    let table = UNIQUE_SHUFFLE.as_ptr() as *const __m128i;
+    let lo = _mm_shuffle_epi8(_mm256_castsi256_si128(new), _mm_lddqu_si128(table.add(mask_lo)));
+    let hi = _mm_shuffle_epi8(_mm256_extracti128_si256(new, 1), _mm_lddqu_si128(table.add(mask_hi)));
+
+    let len_lo = 8 - mask_lo.count_ones() as usize;
+    let len_hi = 8 - mask_hi.count_ones() as usize;
+
+    _mm_storeu_si128(output as *mut __m128i, lo);
+    _mm_storeu_si128(output.add(len_lo) as *mut __m128i, hi);
+
+    len_lo + len_hi
+}
+
+/// Write the values preceding each value of `new` that differ from both of their neighbours into `output`,
+/// the values preceding the start of `new` are the last two values of `old`
+/// 
+/// # Returns
+/// The number of values written
+/// 
+/// # Safety
+/// - Up to 16 values are stored regardless of how many are unique
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn store_xor(old: __m256i, new: __m256i, output: *mut u16) -> usize {
+    let carry = _mm256_permute2x128_si256(old, new, 0x21);
+    let prev_2 = _mm256_alignr_epi8(new, carry, 12);
+    let prev_1 = _mm256_alignr_epi8(new, carry, 14);
+
+    let eq_l = _mm256_cmpeq_epi16(prev_1, prev_2);
+    let eq_r = _mm256_cmpeq_epi16(prev_1, new);
+    let packed = _mm256_packs_epi16(_mm256_or_si256(eq_l, eq_r), _mm256_setzero_si256());
+    let mask = _mm256_movemask_epi8(packed) as u32;
+
+    // Compact each lane separately using the 8 value shuffle table
+    let mask_lo = (mask & 0xFF) as usize;
+    let mask_hi = ((mask >> 16) & 0xFF) as usize;
+
+    let table = UNIQUE_SHUFFLE.as_ptr() as *const __m128i;
+    let lo = _mm_shuffle_epi8(_mm256_castsi256_si128(prev_1), _mm_lddqu_si128(table.add(mask_lo)));
+    let hi = _mm_shuffle_epi8(_mm256_extracti128_si256(prev_1, 1), _mm_lddqu_si128(table.add(mask_hi)));
+
+    let len_lo = 8 - mask_lo.count_ones() as usize;
+    let len_hi = 8 - mask_hi.count_ones() as usize;
+
+    _mm_storeu_si128(output as *mut __m128i, lo);
+    _mm_storeu_si128(output.add(len_lo) as *mut __m128i, hi);
+
+    len_lo + len_hi
+}
+
+/// Write the values of the broadcast block `a` that are not flagged in `mask` into `output`
+///
+/// # Returns
+/// The number of values written
+///
+/// # Safety
+/// - Up to 8 values are stored regardless of how many are unflagged
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn store_and_not(a: __m256i, mask: usize, output: *mut u16) -> usize {
+    let table = UNIQUE_SHUFFLE.as_ptr() as *const __m128i;
+    let values = _mm_shuffle_epi8(_mm256_castsi256_si128(a), _mm_lddqu_si128(table.add(mask)));
+
+    _mm_storeu_si128(output as *mut __m128i, values);
+
+    8 - mask.count_ones() as usize
+}
+
+/// Load 8 values into both 128 bit lanes of a 256 bit register
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn load_broadcast(ptr: *const u16) -> __m256i {
+    _mm256_broadcastsi128_si256(_mm_lddqu_si128(ptr as *const __m128i))
+}
+
+/// Load 8 values and produce all 8 rotations of them packed two per 256 bit register
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn load_rotations(ptr: *const u16) -> [__m256i; 4] {
+    let v = _mm_lddqu_si128(ptr as *const __m128i);
+
+    [
+        _mm256_set_m128i(_mm_alignr_epi8(v, v, 2), v),
+        _mm256_set_m128i(_mm_alignr_epi8(v, v, 6), _mm_alignr_epi8(v, v, 4)),
+        _mm256_set_m128i(_mm_alignr_epi8(v, v, 10), _mm_alignr_epi8(v, v, 8)),
+        _mm256_set_m128i(_mm_alignr_epi8(v, v, 14), _mm_alignr_epi8(v, v, 12)),
+    ]
+}
+
+/// Count the number of values in `a` matching any rotation in `b`
+///
+/// # Remarks
+/// Assumes that the values in each block are unique so that every value matches at most once
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn match_count(a: __m256i, b: &[__m256i; 4]) -> usize {
+    let r0 = _mm256_or_si256(_mm256_cmpeq_epi16(a, b[0]), _mm256_cmpeq_epi16(a, b[1]));
+    let r1 = _mm256_or_si256(_mm256_cmpeq_epi16(a, b[2]), _mm256_cmpeq_epi16(a, b[3]));
+    let r = _mm256_or_si256(r0, r1);
+
+    // Fold the lanes together so each value in `a` is represented by a single 16 bit mask
+    let folded = _mm_or_si128(_mm256_castsi256_si128(r), _mm256_extracti128_si256(r, 1));
+
+    // Every 16 bit match produces 2 bits in the byte mask
+    (_mm_movemask_epi8(folded).count_ones() / 2) as usize
+}
+
+/// Find which values in `a` match any rotation in `b`
+///
+/// # Returns
+/// An 8 bit mask with bit `i` set if the `i`th value of `a` was matched
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn match_mask(a: __m256i, b: &[__m256i; 4]) -> usize {
+    let r0 = _mm256_or_si256(_mm256_cmpeq_epi16(a, b[0]), _mm256_cmpeq_epi16(a, b[1]));
+    let r1 = _mm256_or_si256(_mm256_cmpeq_epi16(a, b[2]), _mm256_cmpeq_epi16(a, b[3]));
+    let r = _mm256_or_si256(r0, r1);
+
+    // Fold the lanes together and narrow each 16 bit match down to a single byte
+    let folded = _mm_or_si128(_mm256_castsi256_si128(r), _mm256_extracti128_si256(r, 1));
+    let packed = _mm_packs_epi16(folded, _mm_setzero_si128());
+
+    _mm_movemask_epi8(packed) as usize
+}
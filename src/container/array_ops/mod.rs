@@ -1,20 +1,91 @@
 //! This module provides a unified interface for SIMD accelerated operations for array containers.
-//! If compiled without vector extensions then these will fall back to a scalar approach
+//! The fastest kernel supported by the executing CPU is selected at runtime, falling back to a scalar approach
 
 mod vector;
+mod avx2;
 mod scalar;
 
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether SIMD kernels may be selected at runtime
+static SIMD_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// The best kernel supported by the executing CPU, detected on first use
+static DETECTED_KERNEL: OnceLock<Kernel> = OnceLock::new();
+
+/// The family of kernels used to implement the array operations
+/// 
+/// # Remarks
+/// Variants are ordered such that each one implies support for all lesser variants
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Kernel {
+    Scalar,
+    Sse42,
+    Avx2
+}
+
+/// Enable or disable the use of SIMD accelerated kernels at runtime
+/// 
+/// # Remarks
+/// This is intended for comparing scalar and SIMD performance within a single binary. 
+/// Results are identical regardless of the setting
+pub fn set_simd_enabled(enabled: bool) {
+    SIMD_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Check whether SIMD accelerated kernels may be used
+#[inline]
+pub fn simd_enabled() -> bool {
+    SIMD_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Find the best kernel supported by the executing CPU
+/// 
+/// # Remarks
+/// Feature detection is only performed once and cached for the lifetime of the process
+fn detected_kernel() -> Kernel {
+    *DETECTED_KERNEL.get_or_init(|| {
+        #[cfg(target_arch = "x86_64")]
+        {
+            let sse42 = is_x86_feature_detected!("sse4.2") && is_x86_feature_detected!("popcnt");
+
+            if sse42 && is_x86_feature_detected!("avx2") {
+                return Kernel::Avx2;
+            }
+
+            if sse42 {
+                return Kernel::Sse42;
+            }
+        }
+
+        Kernel::Scalar
+    })
+}
+
+/// Select the kernel to use for the next operation
+#[inline]
+fn kernel() -> Kernel {
+    select_kernel(simd_enabled())
+}
+
+/// Select the kernel to use depending on whether SIMD kernels are enabled
+#[inline]
+fn select_kernel(simd_enabled: bool) -> Kernel {
+    if simd_enabled {
+        detected_kernel()
+    }
+    else {
+        Kernel::Scalar
+    }
+}
+
 /// Perform the set union operation between `a` and `b` outputting the results into `out`
 /// 
 /// # Safety
 ///  - Assumes that `out` has enough space to contain the full result
 pub unsafe fn or(a: &[u16], b: &[u16], out: *mut u16) -> usize {
-    // Conditionally compile in/out the optimial version of the algorithm
-    #[cfg(target_feature = "sse4.2")]
-    { vector::or(a, b, out) }
-    
-    #[cfg(not(target_feature = "sse4.2"))]
-    { scalar::or(a, b, out) }
+    or_with(kernel(), a, b, out)
 }
 
 /// Perform the set intersection operation between `a` and `b` outputting the results into `out`
@@ -22,21 +93,13 @@ pub unsafe fn or(a: &[u16], b: &[u16], out: *mut u16) -> usize {
 /// # Safety
 ///  - Assumes that `out` has enough space to contain the full result
 pub unsafe fn and(a: &[u16], b: &[u16], out: *mut u16) -> usize {
-    // Conditionally compile in/out the optimial version of the algorithm
-    #[cfg(target_feature = "sse4.2")]
-    { vector::and(a, b, out) }
-    
-    #[cfg(not(target_feature = "sse4.2"))]
-    { scalar::and(a, b, out) }
+    and_with(kernel(), a, b, out)
 }
 
+/// Find the cardinality of the intersection between `a` and `b`
 pub fn and_cardinality(a: &[u16], b: &[u16]) -> usize {
-    // Conditionally compile in/out the optimial version of the algorithm
-   #[cfg(target_feature = "sse4.2")]
-    { vector::and_cardinality(a, b) }
-    
-    #[cfg(not(target_feature = "sse4.2"))]
-    { scalar::and_cardinality(a, b) }
+    // Safe since `kernel` only selects kernels supported by the executing CPU
+    unsafe { and_cardinality_with(kernel(), a, b) }
 }
 
 /// Perform the set difference operation between `a` and `b` outputting the results into `out`
@@ -44,12 +107,7 @@ pub fn and_cardinality(a: &[u16], b: &[u16]) -> usize {
 /// # Safety
 ///  - Assumes that `out` has enough space to contain the full result
 pub unsafe fn and_not(a: &[u16], b: &[u16], out: *mut u16) -> usize {
-    // Conditionally compile in/out the optimial version of the algorithm
-    #[cfg(target_feature = "sse4.2")]
-    { vector::and_not(a, b, out) }
-    
-    #[cfg(not(target_feature = "sse4.2"))]
-    { scalar::and_not(a, b, out) }
+    and_not_with(kernel(), a, b, out)
 }
 
 /// Perform the set symmetric difference operation between `a` and `b` outputting the results into `out`
@@ -57,12 +115,79 @@ pub unsafe fn and_not(a: &[u16], b: &[u16], out: *mut u16) -> usize {
 /// # Safety
 ///  - Assumes that `out` has enough space to contain the full result
 pub unsafe fn xor(a: &[u16], b: &[u16], out: *mut u16) -> usize {
-    // Conditionally compile in/out the optimial version of the algorithm
-    #[cfg(target_feature = "sse4.2")]
-    { vector::xor(a, b, out) }
-    
-    #[cfg(not(target_feature = "sse4.2"))]
-    { scalar::xor(a, b, out) }
+    xor_with(kernel(), a, b, out)
+}
+
+/// Perform the set union operation between `a` and `b` using the specified kernel
+/// 
+/// # Safety
+///  - Assumes that `out` has enough space to contain the full result
+///  - Assumes that the executing CPU supports `kernel`
+unsafe fn or_with(kernel: Kernel, a: &[u16], b: &[u16], out: *mut u16) -> usize {
+    match kernel {
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Avx2 => avx2::or(a, b, out),
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Sse42 => vector::or(a, b, out),
+        _ => scalar::or(a, b, out)
+    }
+}
+
+/// Perform the set intersection operation between `a` and `b` using the specified kernel
+/// 
+/// # Safety
+///  - Assumes that `out` has enough space to contain the full result
+///  - Assumes that the executing CPU supports `kernel`
+unsafe fn and_with(kernel: Kernel, a: &[u16], b: &[u16], out: *mut u16) -> usize {
+    match kernel {
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Sse42 | Kernel::Avx2 => vector::and(a, b, out),
+        _ => scalar::and(a, b, out)
+    }
+}
+
+/// Find the cardinality of the intersection between `a` and `b` using the specified kernel
+/// 
+/// # Safety
+///  - Assumes that the executing CPU supports `kernel`
+unsafe fn and_cardinality_with(kernel: Kernel, a: &[u16], b: &[u16]) -> usize {
+    match kernel {
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Avx2 => avx2::and_cardinality(a, b),
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Sse42 => vector::and_cardinality(a, b),
+        _ => scalar::and_cardinality(a, b)
+    }
+}
+
+/// Perform the set difference operation between `a` and `b` using the specified kernel
+/// 
+/// # Safety
+///  - Assumes that `out` has enough space to contain the full result
+///  - Assumes that the executing CPU supports `kernel`
+unsafe fn and_not_with(kernel: Kernel, a: &[u16], b: &[u16], out: *mut u16) -> usize {
+    match kernel {
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Avx2 => avx2::and_not(a, b, out),
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Sse42 => vector::and_not(a, b, out),
+        _ => scalar::and_not(a, b, out)
+    }
+}
+
+/// Perform the set symmetric difference operation between `a` and `b` using the specified kernel
+/// 
+/// # Safety
+///  - Assumes that `out` has enough space to contain the full result
+///  - Assumes that the executing CPU supports `kernel`
+unsafe fn xor_with(kernel: Kernel, a: &[u16], b: &[u16], out: *mut u16) -> usize {
+    match kernel {
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Avx2 => avx2::xor(a, b, out),
+        #[cfg(target_arch = "x86_64")]
+        Kernel::Sse42 => vector::xor(a, b, out),
+        _ => scalar::xor(a, b, out)
+    }
 }
 
 // TODO: Clean this mess up
@@ -153,8 +278,16 @@ mod test {
     use crate::test::*;
     use super::scalar;
 
-    #[cfg(target_feature = "sse4.2")]
-    use super::vector;
+    use super::Kernel;
+
+    #[cfg(target_arch = "x86_64")]
+    use super::{vector, avx2};
+
+    /// Check whether the SSE4.2 kernels can be run on this CPU
+    #[cfg(target_arch = "x86_64")]
+    fn has_sse42() -> bool {
+        is_x86_feature_detected!("sse4.2") && is_x86_feature_detected!("popcnt")
+    }
 
     fn run_test<F>(op: OpType, f: F) 
         where F: Fn(&[u16], &[u16], *mut u16) -> usize 
@@ -191,6 +324,53 @@ mod test {
         }
     }
 
+    fn run_card_test<F>(f: F) 
+        where F: Fn(&[u16], &[u16]) -> usize 
+    {
+        let sizes = [(0, 100), (7, 9), (8, 8), (100, 3_000), (4_095, 4_096), (4_096, 4_096)];
+
+        for (seed, (len_a, len_b)) in sizes.iter().enumerate() {
+            let seed = seed as u8;
+            let a = generate_seeded_data(0..65535, *len_a, seed);
+            let b = generate_seeded_data(0..65535, *len_b, seed + 32);
+            let expected = compute_result(&a, &b, OpType::And).len();
+
+            assert_eq!(f(&a, &b), expected);
+            assert_eq!(f(&b, &a), expected);
+        }
+
+        // Identical and disjoint inputs
+        let a: Vec<u16> = (0..1_000).collect();
+        let b: Vec<u16> = (1_000..2_000).collect();
+        assert_eq!(f(&a, &a), a.len());
+        assert_eq!(f(&a, &b), 0);
+    }
+
+    #[test]
+    fn and_cardinality_scalar() {
+        run_card_test(scalar::and_cardinality);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn and_cardinality_vector() {
+        if !has_sse42() {
+            return;
+        }
+
+        run_card_test(|a, b| unsafe { vector::and_cardinality(a, b) });
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn and_cardinality_avx2() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        run_card_test(|a, b| unsafe { avx2::and_cardinality(a, b) });
+    }
+
     #[test]
     fn or_scalar() {
         run_test(OpType::Or, |a, b, out| unsafe { scalar::or(a, b, out) } );
@@ -212,29 +392,289 @@ mod test {
     }
 
     #[test]
-    #[cfg(target_feature = "sse4.2")]
+    #[cfg(target_arch = "x86_64")]
     fn or_vector() {
+        if !has_sse42() {
+            return;
+        }
+
         run_test(OpType::Or, |a, b, out| unsafe { vector::or(a, b, out) } );
     }
 
     #[test]
-    #[cfg(target_feature = "sse4.2")]
+    #[cfg(target_arch = "x86_64")]
+    fn or_avx2() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        run_test(OpType::Or, |a, b, out| unsafe { avx2::or(a, b, out) } );
+
+        // Lengths around the register width and the array container limit
+        let sizes = [(0, 100), (15, 16), (16, 16), (17, 40), (100, 3_000), (4_095, 4_096), (4_096, 4_096)];
+        for (seed, (len_a, len_b)) in sizes.iter().enumerate() {
+            let seed = seed as u8;
+            let a = generate_seeded_data(0..65535, *len_a, seed);
+            let b = generate_seeded_data(0..65535, *len_b, seed + 32);
+
+            for (x, y) in [(&a, &b), (&b, &a), (&a, &a)].iter() {
+                let mut expected = vec![0; x.len() + y.len()];
+                let mut found = vec![0; x.len() + y.len()];
+
+                let expected_len = unsafe { scalar::or(x, y, expected.as_mut_ptr()) };
+                let found_len = unsafe { avx2::or(x, y, found.as_mut_ptr()) };
+
+                assert_eq!(&found[..found_len], &expected[..expected_len], "Failed for {} and {}", x.len(), y.len());
+                assert!(found[..found_len].windows(2).all(|w| w[0] < w[1]));
+            }
+        }
+
+        // Interleaved and disjoint blocks stress the carried maximums
+        let evens: Vec<u16> = (0..4_000).map(|v| v * 2).collect();
+        let odds: Vec<u16> = (0..4_000).map(|v| v * 2 + 1).collect();
+        let high: Vec<u16> = (60_000..65_535).collect();
+        for (x, y) in [(&evens, &odds), (&evens, &high), (&high, &odds)].iter() {
+            let mut expected = vec![0; x.len() + y.len()];
+            let mut found = vec![0; x.len() + y.len()];
+
+            let expected_len = unsafe { scalar::or(x, y, expected.as_mut_ptr()) };
+            let found_len = unsafe { avx2::or(x, y, found.as_mut_ptr()) };
+
+            assert_eq!(&found[..found_len], &expected[..expected_len]);
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
     fn and_vector() {
+        if !has_sse42() {
+            return;
+        }
+
         run_test(OpType::And, |a, b, out| unsafe { vector::and(a, b, out) } );
     }
 
     #[test]
-    #[cfg(target_feature = "sse4.2")]
+    #[cfg(target_arch = "x86_64")]
     fn and_not_vector() {
+        if !has_sse42() {
+            return;
+        }
+
         run_test(OpType::AndNot, |a, b, out| unsafe { vector::and_not(a, b, out) } );
     }
 
     #[test]
-    #[cfg(target_feature = "sse4.2")]
+    #[cfg(target_arch = "x86_64")]
+    fn and_not_avx2() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        run_test(OpType::AndNot, |a, b, out| unsafe { avx2::and_not(a, b, out) } );
+
+        // Lengths around the block width and the array container limit
+        let empty = Vec::new();
+        let sizes = [(0, 100), (7, 8), (8, 8), (9, 40), (100, 3_000), (4_095, 4_096), (4_096, 4_096)];
+        for (seed, (len_a, len_b)) in sizes.iter().enumerate() {
+            let seed = seed as u8;
+            let a = generate_seeded_data(0..65535, *len_a, seed);
+            let b = generate_seeded_data(0..65535, *len_b, seed + 32);
+
+            for (x, y) in [(&a, &b), (&b, &a), (&a, &a), (&a, &empty), (&empty, &a)].iter() {
+                let expected = compute_result(x, y, OpType::AndNot);
+                let mut found = vec![0; x.len()];
+
+                let found_len = unsafe { avx2::and_not(x, y, found.as_mut_ptr()) };
+
+                assert_eq!(&found[..found_len], &expected[..], "Failed for {} and {}", x.len(), y.len());
+            }
+        }
+
+        // Disjoint and interleaved inputs, with `b` ending part way through a block of `a`
+        let low: Vec<u16> = (0..4_000).collect();
+        let high: Vec<u16> = (4_000..8_000).collect();
+        let evens: Vec<u16> = (0..4_000).map(|v| v * 2).collect();
+        let thirds: Vec<u16> = (0..4_000).map(|v| v * 3).collect();
+        let short: Vec<u16> = (0..21).map(|v| v * 5).collect();
+        let pairs = [(&low, &high), (&high, &low), (&evens, &thirds), (&thirds, &evens), (&evens, &short), (&low, &short)];
+        for (x, y) in pairs.iter() {
+            let expected = compute_result(x, y, OpType::AndNot);
+            let mut found = vec![0; x.len()];
+
+            let found_len = unsafe { avx2::and_not(x, y, found.as_mut_ptr()) };
+
+            assert_eq!(&found[..found_len], &expected[..]);
+        }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
     fn xor_vector() {
+        if !has_sse42() {
+            return;
+        }
+
         run_test(OpType::Xor, |a, b, out| unsafe { vector::xor(a, b, out) } );
     }
 
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn xor_avx2() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+
+        run_test(OpType::Xor, |a, b, out| unsafe { avx2::xor(a, b, out) } );
+
+        // Lengths around the register width and the array container limit
+        let sizes = [(0, 100), (15, 16), (16, 16), (17, 40), (100, 3_000), (4_095, 4_096), (4_096, 4_096)];
+        for (seed, (len_a, len_b)) in sizes.iter().enumerate() {
+            let seed = seed as u8;
+            let a = generate_seeded_data(0..65535, *len_a, seed);
+            let b = generate_seeded_data(0..65535, *len_b, seed + 32);
+
+            for (x, y) in [(&a, &b), (&b, &a), (&a, &a)].iter() {
+                let expected = compute_result(x, y, OpType::Xor);
+                let mut found = vec![0; x.len() + y.len()];
+
+                let found_len = unsafe { avx2::xor(x, y, found.as_mut_ptr()) };
+
+                assert_eq!(&found[..found_len], &expected[..], "Failed for {} and {}", x.len(), y.len());
+            }
+        }
+
+        // Interleaved blocks and values shared at the block edges stress the delayed output
+        let evens: Vec<u16> = (0..4_000).map(|v| v * 2).collect();
+        let odds: Vec<u16> = (0..4_000).map(|v| v * 2 + 1).collect();
+        let thirds: Vec<u16> = (0..4_000).map(|v| v * 3).collect();
+        for (x, y) in [(&evens, &odds), (&evens, &thirds), (&thirds, &odds)].iter() {
+            let expected = compute_result(x, y, OpType::Xor);
+            let mut found = vec![0; x.len() + y.len()];
+
+            let found_len = unsafe { avx2::xor(x, y, found.as_mut_ptr()) };
+
+            assert_eq!(&found[..found_len], &expected[..]);
+        }
+    }
+
+    #[test]
+    fn xor_capacity() {
+        // Disjoint inputs produce the largest possible output, every kernel must stay within
+        // `a.len() + b.len()` elements. A guard region after the output catches any overrun
+        const GUARD: usize = 32;
+        const SENTINEL: u16 = 0xABCD;
+
+        let low: Vec<u16> = (0..4_096).collect();
+        let high: Vec<u16> = (4_096..8_192).collect();
+        let evens: Vec<u16> = (0..4_096).map(|v| v * 2).collect();
+        let odds: Vec<u16> = (0..4_096).map(|v| v * 2 + 1).collect();
+        let short: Vec<u16> = (60_000..60_017).collect();
+
+        let pairs = [(&low, &high), (&high, &low), (&evens, &odds), (&odds, &short), (&short, &evens)];
+        let detected = super::detected_kernel();
+
+        for kernel in [Kernel::Scalar, Kernel::Sse42, Kernel::Avx2].iter().filter(|k| **k <= detected) {
+            for (a, b) in pairs.iter() {
+                for (len_a, len_b) in [(a.len(), b.len()), (a.len() - 1, b.len() - 3), (17, 16)].iter() {
+                    let (a, b) = (&a[..*len_a], &b[..*len_b]);
+                    let cap = a.len() + b.len();
+                    let mut found = vec![SENTINEL; cap + GUARD];
+
+                    let len = unsafe { super::xor_with(*kernel, a, b, found.as_mut_ptr()) };
+
+                    assert_eq!(len, cap, "{:?}", kernel);
+                    assert_eq!(&found[..len], &compute_result(a, b, OpType::Xor)[..], "{:?}", kernel);
+                    assert!(found[cap..].iter().all(|v| *v == SENTINEL), "{:?} wrote past the output", kernel);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn dispatch_paths() {
+        // Results must be identical with and without SIMD kernels. The kernels selected for
+        // either setting are run directly so the global flag is left alone for concurrent tests
+        assert_eq!(super::select_kernel(false), Kernel::Scalar);
+        assert_eq!(super::select_kernel(true), super::detected_kernel());
+
+        for enabled in [true, false].iter() {
+            let kernel = super::select_kernel(*enabled);
+
+            run_test(OpType::Or, |a, b, out| unsafe { super::or_with(kernel, a, b, out) } );
+            run_test(OpType::And, |a, b, out| unsafe { super::and_with(kernel, a, b, out) } );
+            run_test(OpType::AndNot, |a, b, out| unsafe { super::and_not_with(kernel, a, b, out) } );
+            run_test(OpType::Xor, |a, b, out| unsafe { super::xor_with(kernel, a, b, out) } );
+            run_card_test(|a, b| unsafe { super::and_cardinality_with(kernel, a, b) });
+        }
+
+        // The public entry points dispatch through the same selection
+        run_test(OpType::Or, |a, b, out| unsafe { super::or(a, b, out) } );
+        run_card_test(super::and_cardinality);
+    }
+
+    #[test]
+    fn kernel_paths() {
+        // Force every kernel supported by this CPU and ensure they all agree
+        let kernels = [Kernel::Scalar, Kernel::Sse42, Kernel::Avx2];
+        let detected = super::detected_kernel();
+
+        for kernel in kernels.iter().filter(|k| **k <= detected) {
+            let kernel = *kernel;
+
+            run_test(OpType::Or, |a, b, out| unsafe { super::or_with(kernel, a, b, out) } );
+            run_test(OpType::And, |a, b, out| unsafe { super::and_with(kernel, a, b, out) } );
+            run_test(OpType::AndNot, |a, b, out| unsafe { super::and_not_with(kernel, a, b, out) } );
+            run_test(OpType::Xor, |a, b, out| unsafe { super::xor_with(kernel, a, b, out) } );
+            run_card_test(|a, b| unsafe { super::and_cardinality_with(kernel, a, b) });
+        }
+    }
+
+    #[test]
+    fn kernel_paths_random() {
+        use rand::prelude::*;
+
+        // Small arrays with leading zeros and lengths around the block size exercise the edge cases
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(1);
+        let detected = super::detected_kernel();
+
+        for _i in 0..2_000 {
+            let span = rng.gen_range(1, 200);
+            let gen = |rng: &mut rand::rngs::SmallRng| {
+                let len = rng.gen_range(0, 48);
+                let mut v: Vec<u16> = (0..len).map(|_| rng.gen_range(0, span)).collect();
+                v.sort();
+                v.dedup();
+                v
+            };
+
+            let a = gen(&mut rng);
+            let b = gen(&mut rng);
+
+            for kernel in [Kernel::Sse42, Kernel::Avx2].iter().filter(|k| **k <= detected) {
+                for op in [OpType::Or, OpType::And, OpType::AndNot, OpType::Xor].iter() {
+                    let expected = compute_result(&a, &b, *op);
+                    let mut found = vec![0; a.len() + b.len() + 8];
+
+                    let len = unsafe {
+                        let out = found.as_mut_ptr();
+                        match op {
+                            OpType::Or => super::or_with(*kernel, &a, &b, out),
+                            OpType::And => super::and_with(*kernel, &a, &b, out),
+                            OpType::AndNot => super::and_not_with(*kernel, &a, &b, out),
+                            OpType::Xor => super::xor_with(*kernel, &a, &b, out)
+                        }
+                    };
+
+                    assert_eq!(&found[..len], &expected[..], "{:?} a={:?} b={:?}", kernel, a, b);
+                }
+
+                let card = unsafe { super::and_cardinality_with(*kernel, &a, &b) };
+                assert_eq!(card, compute_result(&a, &b, OpType::And).len());
+            }
+        }
+    }
+
     #[test]
     fn advance_until() {
         let values: Vec<u16> = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
@@ -244,4 +684,4 @@ mod test {
         assert_eq!(max_index, 9);
         assert_eq!(mid_index, 5);
     }
-}
\ No newline at end of file
+}
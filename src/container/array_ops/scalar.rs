@@ -190,10 +190,11 @@ pub unsafe fn and_not(a: &[u16], b: &[u16], out: *mut u16) -> usize {
     count
 }
 
-/// Calculate the difference between two slices using a scalar algorithm and return the number of elements in the result
+/// Calculate the symmetric difference between two slices using a scalar algorithm and return the number of elements in the result
 ///
 /// # Safety
-/// - Assumes that `out` has enough space for the full contents
+/// - Assumes that `out` has enough space for `a.len() + b.len()` elements, 
+///   only values present in exactly one of the slices are written so at most that many are written
 /// 
 /// # Remarks
 /// - Assumes that `a` and `b` are sorted. The result is undefined if violated
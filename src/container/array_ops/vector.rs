@@ -2,8 +2,8 @@
 #![allow(dead_code)]
 
 use std::ops::Deref;
-#[cfg(target_feature = "sse4.2")] use std::ptr;
-#[cfg(target_feature = "sse4.2")] use std::arch::x86_64::{
+#[cfg(target_arch = "x86_64")] use std::ptr;
+#[cfg(target_arch = "x86_64")] use std::arch::x86_64::{
     _SIDD_BIT_MASK,
     _SIDD_CMP_EQUAL_ANY,
     _SIDD_UWORD_OPS,
@@ -30,9 +30,9 @@ use std::ops::Deref;
     _mm_or_si128,
 };
 
-#[cfg(target_feature = "sse4.2")] use super::scalar;
+#[cfg(target_arch = "x86_64")] use super::scalar;
 
-#[cfg(target_feature = "sse4.2")]
+#[cfg(target_arch = "x86_64")]
 const CMPISTRM_ARGS: i32 = _SIDD_UWORD_OPS | _SIDD_CMP_EQUAL_ANY | _SIDD_BIT_MASK;
 
 /// Compute the union between `a` and `b` and append the result into `out`
@@ -42,7 +42,8 @@ const CMPISTRM_ARGS: i32 = _SIDD_UWORD_OPS | _SIDD_CMP_EQUAL_ANY | _SIDD_BIT_MAS
 /// 
 /// # Safety
 /// - Assumes `out` contains enough space to hold the output
-#[cfg(target_feature = "sse4.2")]
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2,popcnt")]
 pub unsafe fn or(a: &[u16], b: &[u16], out: *mut u16) -> usize {
     if a.len() < 8 || b.len() < 8 {
         return scalar::or(a, b, out);
@@ -123,7 +124,7 @@ pub unsafe fn or(a: &[u16], b: &[u16], out: *mut u16) -> usize {
         ptr::copy_nonoverlapping(ptr_src, ptr_dst, rem);
         
         buf_len += rem;
-        &buffer[..buf_len].sort();
+        buffer[..buf_len].sort();
         
         buf_len = unique_or(ptr_buf, buf_len);
 
@@ -151,10 +152,12 @@ pub unsafe fn or(a: &[u16], b: &[u16], out: *mut u16) -> usize {
 /// 
 /// # Safety
 /// - Assumes `out` contains enough space to hold the output
-#[cfg(target_feature = "sse4.2")]
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2,popcnt")]
+#[allow(clippy::while_immutable_condition)] // Zeros can only appear first, the loop exits by breaking
 pub unsafe fn and(a: &[u16], b: &[u16], out: *mut u16) -> usize {
     // Both sets are empty, there's no possible intersection
-    if a.len() == 0 || b.len() == 0 {
+    if a.is_empty() || b.is_empty() {
         return 0;
     }
 
@@ -272,10 +275,12 @@ pub unsafe fn and(a: &[u16], b: &[u16], out: *mut u16) -> usize {
 }
 
 /// Find the cardinality of the intersection between `a` and `b`
-#[cfg(target_feature = "sse4.2")]
-pub fn and_cardinality(a: &[u16], b: &[u16]) -> usize {
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2,popcnt")]
+#[allow(clippy::while_immutable_condition)] // Zeros can only appear first, the loop exits by breaking
+pub unsafe fn and_cardinality(a: &[u16], b: &[u16]) -> usize {
     // Both sets are empty, there's no possible intersection
-    if a.len() == 0 || b.len() == 0 {
+    if a.is_empty() || b.is_empty() {
         return 0;
     }
 
@@ -393,15 +398,16 @@ pub fn and_cardinality(a: &[u16], b: &[u16]) -> usize {
 /// 
 /// # Safety
 /// - Assumes `out` contains enough space to hold the output
-#[cfg(target_feature = "sse4.2")]
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2,popcnt")]
 pub unsafe fn and_not(a: &[u16], b: &[u16], out: *mut u16) -> usize {
     // A is the empty set therefore there are no elements in A not in B
-    if a.len() == 0 {
+    if a.is_empty() {
         return 0;
     }
     
     // All elements of A are not in B, copy A into out
-    if b.len() == 0 {
+    if b.is_empty() {
         let ptr = a.as_ptr();
         let len = a.len();
 
@@ -441,8 +447,9 @@ pub unsafe fn and_not(a: &[u16], b: &[u16], out: *mut u16) -> usize {
         }
     }
 
-    let stop_a = (len_a / 8) * 8;
-    let stop_b = (len_b / 8) * 8;
+    // Stop indices are absolute so they must account for any skipped leading 0s
+    let stop_a = i_a + (len_a / 8) * 8;
+    let stop_b = i_b + (len_b / 8) * 8;
 
     if i_a < stop_a && i_b < stop_b {
         let mut v_a = _mm_lddqu_si128(ptr_a.add(i_a) as *const __m128i);
@@ -545,7 +552,8 @@ pub unsafe fn and_not(a: &[u16], b: &[u16], out: *mut u16) -> usize {
 /// 
 /// # Safety
 /// - Assumes `out` contains enough space to hold the output
-#[cfg(target_feature = "sse4.2")]
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2,popcnt")]
 pub unsafe fn xor(a: &[u16], b: &[u16], out: *mut u16) -> usize {
     // Use a scalar algorithm if the length of the two vectors is too short to use simd
     if a.len() < 8 || b.len() < 8 {
@@ -644,7 +652,7 @@ pub unsafe fn xor(a: &[u16], b: &[u16], out: *mut u16) -> usize {
             count += num;
         }
         else {
-            &buffer[..buf_len].sort();
+            buffer[..buf_len].sort();
             buf_len = unique_xor(ptr_buf, buf_len);
             count += scalar::xor(
                 &buffer[..buf_len],
@@ -696,7 +704,8 @@ unsafe fn unique_xor(out: *mut u16, len: usize) -> usize {
     count
 }
 
-#[cfg(target_feature = "sse4.2")]
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2,popcnt")]
 unsafe fn store_or(old: __m128i, new: __m128i, output: *mut u16) -> usize {
     let temp = _mm_alignr_epi8(new, old, 16 - 2);
     let packed = _mm_packs_epi16(_mm_cmpeq_epi16(temp, new), _mm_setzero_si128());
@@ -709,7 +718,8 @@ unsafe fn store_or(old: __m128i, new: __m128i, output: *mut u16) -> usize {
     (8 - _popcnt32(mask)) as usize
 }
 
-#[cfg(target_feature = "sse4.2")]
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2,popcnt")]
 unsafe fn store_xor(old: __m128i, new: __m128i, output: *mut u16) -> usize {
     let temp_0 = _mm_alignr_epi8(new, old, 16 - 4);
     let temp_1 = _mm_alignr_epi8(new, old, 16 - 2);
@@ -729,7 +739,8 @@ unsafe fn store_xor(old: __m128i, new: __m128i, output: *mut u16) -> usize {
     (8 - _popcnt32(mask)) as usize
 }
 
-#[cfg(target_feature = "sse4.2")]
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "sse4.2,popcnt")]
 unsafe fn merge(a: __m128i, b: __m128i, min: &mut __m128i, max: &mut __m128i) {
     let mut temp = _mm_min_epu16(a, b);
     *max = _mm_max_epu16(a, b);
@@ -758,7 +769,7 @@ unsafe fn merge(a: __m128i, b: __m128i, min: &mut __m128i, max: &mut __m128i) {
 }
 
 #[repr(align(16))]
-struct Aligned<T>(T);
+pub(super) struct Aligned<T>(T);
 
 impl<T> Deref for Aligned<T> {
     type Target = T;
@@ -768,7 +779,7 @@ impl<T> Deref for Aligned<T> {
     }
 }
 
-const UNIQUE_SHUFFLE: Aligned<[u8; 4096]> = Aligned([
+pub(super) const UNIQUE_SHUFFLE: Aligned<[u8; 4096]> = Aligned([
     0x0,  0x1,  0x2,  0x3,  0x4,  0x5,  0x6,  0x7,  0x8,  0x9,  0xa,  0xb,
     0xc,  0xd,  0xe,  0xf,  0x2,  0x3,  0x4,  0x5,  0x6,  0x7,  0x8,  0x9,
     0xa,  0xb,  0xc,  0xd,  0xe,  0xf,  0xFF, 0xFF, 0x0,  0x1,  0x4,  0x5,
@@ -1,7 +1,6 @@
 use std::ops::{Deref, DerefMut};
 use std::ptr;
 
-use crate::IntoBound;
 use crate::container::*;
 
 /// The size of the bitset in 64bit words
@@ -101,7 +100,7 @@ impl BitsetContainer {
 
     /// Unset all the bits between [min-max)
     pub fn unset_range(&mut self, range: Range<u32>) {
-        let (min, max) = range.into_bound();
+        let (min, max) = (range.start, range.end);
 
         if min == max {
             return;
@@ -186,7 +185,8 @@ impl BitsetContainer {
             return false;
         }
 
-        if self.bitset[last_word] & w1 != w1 {
+        // The last word is past the end of the range if it ends on a word boundary
+        if w1 != 0 && self.bitset[last_word] & w1 != w1 {
             return false;
         }
 
@@ -200,22 +200,27 @@ impl BitsetContainer {
     }
 
     /// Flip a specific bit in the bitset
+    #[inline]
     pub fn flip(&mut self, index: u16) {
         let word_index = index / 64;
         let bit_index = index % 64;
 
-        self.bitset[word_index as usize] ^= !(1 << bit_index);
+        self.bitset[word_index as usize] ^= 1 << bit_index;
     }
 
     /// Flip all bits in the range [min-max)
     pub fn flip_range(&mut self, range: Range<u32>) {
+        debug_assert!(is_valid_range(range.clone()));
+
         if range.start == range.end {
-            return self.flip(range.start as u16);
+            return;
         }
 
         let first_word = (range.start / 64) as usize;
         let last_word = ((range.end - 1) / 64) as usize;
         
+        // Pre-flip the bits below `start` in the first word so that the full word
+        // flip below restores them, leaving only the bits within the range flipped
         self.bitset[first_word] ^= !((!0) << (range.start % 64));
         
         for i in first_word..last_word {
@@ -228,11 +233,7 @@ impl BitsetContainer {
     /// Flip all bits contained in `list`
     pub fn flip_list(&mut self, list: &[u16]) {
         for value in list.iter() {
-            let word_index = (*value >> 6) as usize;
-            let index = *value % 64;
-            let load = self.bitset[word_index];
-            let store = load ^ (1_u64 << index);
-            self.bitset[word_index] = store;
+            self.flip(*value);
         }
     }
 
@@ -253,6 +254,12 @@ impl BitsetContainer {
         self.cardinality() == BITSET_SIZE_IN_WORDS * 64
     }
 
+    /// The number of bytes allocated by the bitset
+    #[inline]
+    pub fn mem_size(&self) -> usize {
+        self.bitset.capacity() * mem::size_of::<u64>()
+    }
+
     /// The cardinality of the bitset
     #[inline]
     pub fn cardinality(&self) -> usize {
@@ -264,13 +271,22 @@ impl BitsetContainer {
         count as usize
     }
 
+    /// Check whether no bits are set
+    /// 
+    /// # Remarks
+    /// Shadows `[u64]::is_empty` reached through `Deref`, which only checks the number of words
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bitset.iter().all(|word| *word == 0)
+    }
+
     /// Get the cardinality of the range [min-max)
     pub fn cardinality_range(&self, range: Range<u32>) -> usize {
         let min = range.start;
         let max = range.end;
 
         if min == max {
-            return self.get(min as u16) as usize;
+            return 0;
         }
 
         let start = min as usize;
@@ -291,7 +307,7 @@ impl BitsetContainer {
             result += self.bitset[i].count_ones();
         }
 
-        result += (self.bitset[last_word] & (!0 >> (((!start + 1) - len_minus_one - 1) % 64)))
+        result += (self.bitset[last_word] & (!0 >> ((64 - (max % 64)) % 64)))
             .count_ones();
 
         result as usize
@@ -323,22 +339,42 @@ impl BitsetContainer {
         None
     }
 
+    /// Get the smallest value not in the bitset. Returns `None` if the bitset is full
+    pub fn min_excluded(&self) -> Option<u16> {
+        self.min_excluded_from(0)
+    }
+
+    /// Get the smallest value not in the bitset that is at least `from`. Returns `None` if every such value is present
+    pub fn min_excluded_from(&self, from: u16) -> Option<u16> {
+        let first = (from / 64) as usize;
+
+        // Treat the bits below `from` in the first word as present so they're skipped
+        let below = !(u64::MAX << (from % 64));
+
+        for (i, word) in (*self.bitset).iter().enumerate().skip(first) {
+            let word = if i == first { *word | below } else { *word };
+
+            if word != u64::MAX {
+                let r = word.trailing_ones() as u16;
+
+                return Some(r + i as u16 * 64);
+            }
+        }
+
+        None
+    }
+
     /// Find the number of values equal to or smaller than `value`
     pub fn rank(&self, value: u16) -> usize {
         let end = (value / 64) as usize;
         let mut sum = 0;
 
-        let iter = self.bitset[0..end].iter()
-            .enumerate();
-
-        let mut last = 0;
-        for (i, word) in iter {
+        for word in self.bitset[0..end].iter() {
             sum += word.count_ones();
-            last = i;
         }
 
-        let rem = (value as usize) - (last * 64) + 1;
-        let rem_word = self.bitset[last] << ((64 - rem) & 63);
+        // Only count the bits up to and including `value` in the last word
+        let rem_word = self.bitset[end] << (63 - (value % 64));
         sum += rem_word.count_ones();
 
         sum as usize
@@ -389,12 +425,44 @@ impl BitsetContainer {
     /// # Remarks
     /// If already in the most efficient representation then no change is made
     pub fn into_efficient_container(self) -> Container {
-        if self.cardinality() <= DEFAULT_MAX_SIZE {
-            Container::Array(self.into())
+        match choose_representation(self.cardinality(), self.num_runs()) {
+            ContainerKind::Array => Container::Array(self.into()),
+            ContainerKind::Bitset => Container::Bitset(self),
+            ContainerKind::Run => Container::Run(self.into())
         }
-        else {
-            Container::Bitset(self)
+    }
+
+    /// The length of the longest run of consecutive values in the bitset
+    /// 
+    /// # Remarks
+    /// Runs are measured a word at a time, a run crossing into the next word 
+    /// is carried over as the leading ones of the word it started in
+    pub fn longest_run(&self) -> usize {
+        let mut longest = 0;
+        let mut current = 0;
+
+        for word in self.bitset.iter() {
+            if *word == u64::MAX {
+                current += 64;
+                continue;
+            }
+
+            // Close the run carried in from the previous word
+            longest = longest.max(current + word.trailing_ones() as usize);
+
+            // Each step shortens every run in the word by one, counting the steps gives the longest
+            let mut w = *word;
+            let mut inner = 0;
+            while w != 0 {
+                w &= w << 1;
+                inner += 1;
+            }
+
+            longest = longest.max(inner);
+            current = word.leading_ones() as usize;
         }
+
+        longest.max(current)
     }
 
     /// Get the number of runs in the bitset
@@ -417,12 +485,8 @@ impl BitsetContainer {
             num_runs += count_runs(word, next_word);
         }
 
-        let word = next_word;
-        num_runs += count_runs(word, next_word);
-
-        if word & (1 << 31) != 0 {
-            num_runs += 1;
-        }
+        // The last word has no successor, a run touching the top bit ends here
+        num_runs += count_runs(next_word, 0);
 
         num_runs as usize
     }
@@ -447,7 +511,7 @@ impl BitsetContainer {
             words: &self.bitset,
             word_index: index,
             word: first_word,
-            base: (index * 64) as u32// TODO: Fixme
+            base: (index * 64) as u32,// TODO: Fixme
         }
     }
 
@@ -491,10 +555,7 @@ impl BitsetContainer {
             let num_bytes = BITSET_SIZE_IN_WORDS * mem::size_of::<u64>();
             let bytes_slice = slice::from_raw_parts_mut(ptr, num_bytes);
 
-            let num_read = buf.read(bytes_slice)?;
-            if num_read != num_bytes {
-                return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
-            }
+            buf.read_exact(bytes_slice)?;
 
             Ok(result)
         }
@@ -543,7 +604,7 @@ impl<'a> From<&'a RunContainer> for BitsetContainer {
         let mut bitset = BitsetContainer::new();
         for run in container.iter_runs() {
             let min = u32::from(run.value);
-            let max = u32::from(run.end() + 1);
+            let max = u32::from(run.end()) + 1;
 
             bitset.set_range(min..max);
         }
@@ -591,6 +652,18 @@ impl SetOr<Self> for BitsetContainer {
         Container::Bitset(result)
     }
 
+    fn or_cardinality(&self, other: &Self) -> usize {
+        let mut count = 0;
+        let pass = self.iter_words()
+            .zip(other.iter_words());
+
+        for (a, b) in pass {
+            count += (a | b).count_ones();
+        }
+
+        count as usize
+    }
+
     fn inplace_or(mut self, other: &Self) -> Container {
         // See the notes in inplace operations at the top of this module for details
         unsafe {
@@ -616,6 +689,11 @@ impl SetOr<ArrayContainer> for BitsetContainer {
         Container::Bitset(result)
     }
 
+    #[inline]
+    fn or_cardinality(&self, other: &ArrayContainer) -> usize {
+        self.cardinality() + other.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_or(mut self, other: &ArrayContainer) -> Container {
         self.set_list(&other);
         
@@ -638,6 +716,11 @@ impl SetOr<RunContainer> for BitsetContainer {
         }
     }
 
+    #[inline]
+    fn or_cardinality(&self, other: &RunContainer) -> usize {
+        self.cardinality() + other.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_or(mut self, other: &RunContainer) -> Container {
         if other.is_full() {
             Container::Run(other.clone())
@@ -778,6 +861,18 @@ impl SetAndNot<Self> for BitsetContainer {
         result.into_efficient_container()
     }
 
+    fn and_not_cardinality(&self, other: &Self) -> usize {
+        let mut count = 0;
+        let pass = self.iter_words()
+            .zip(other.iter_words());
+
+        for (a, b) in pass {
+            count += (a & !b).count_ones();
+        }
+
+        count as usize
+    }
+
     fn inplace_and_not(mut self, other: &Self) -> Container {
          // See the notes in inplace operations at the top of this module for details
         unsafe {
@@ -802,6 +897,11 @@ impl SetAndNot<ArrayContainer> for BitsetContainer {
         bitset.into_efficient_container()
     }
 
+    #[inline]
+    fn and_not_cardinality(&self, other: &ArrayContainer) -> usize {
+        self.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_and_not(mut self, other: &ArrayContainer) -> Container {
         self.clear_list(&other);
         self.into_efficient_container()
@@ -816,7 +916,13 @@ impl SetAndNot<RunContainer> for BitsetContainer {
             bitset.unset_range(run.into_range());
         }
 
-        bitset.into_efficient_container()
+        // Removing runs can leave contiguous values behind, consider every representation
+        Container::Bitset(bitset).optimize()
+    }
+
+    #[inline]
+    fn and_not_cardinality(&self, other: &RunContainer) -> usize {
+        self.cardinality() - SetAnd::and_cardinality(self, other)
     }
 
     fn inplace_and_not(mut self, other: &RunContainer) -> Container {
@@ -824,7 +930,7 @@ impl SetAndNot<RunContainer> for BitsetContainer {
             self.unset_range(rle.into_range());
         }
 
-        self.into_efficient_container()
+        Container::Bitset(self).optimize()
     }
 }
 
@@ -847,6 +953,18 @@ impl SetXor<Self> for BitsetContainer {
         result.into_efficient_container()
     }
 
+    fn xor_cardinality(&self, other: &Self) -> usize {
+        let mut count = 0;
+        let pass = self.iter_words()
+            .zip(other.iter_words());
+
+        for (a, b) in pass {
+            count += (a ^ b).count_ones();
+        }
+
+        count as usize
+    }
+
     fn inplace_xor(mut self, other: &Self) -> Container {
          // See the notes in inplace operations at the top of this module for details
         unsafe {
@@ -869,6 +987,11 @@ impl SetXor<ArrayContainer> for BitsetContainer {
         SetXor::xor(other, self)
     }
 
+    #[inline]
+    fn xor_cardinality(&self, other: &ArrayContainer) -> usize {
+        self.cardinality() + other.cardinality() - 2 * SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_xor(mut self, other: &ArrayContainer) -> Container {
         self.flip_list(&other);
         self.into_efficient_container()
@@ -880,6 +1003,11 @@ impl SetXor<RunContainer> for BitsetContainer {
         SetXor::xor(other, self)
     }
 
+    #[inline]
+    fn xor_cardinality(&self, other: &RunContainer) -> usize {
+        self.cardinality() + other.cardinality() - 2 * SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_xor(self, other: &RunContainer) -> Container {
         SetXor::xor(other, &self)
     }
@@ -996,8 +1124,22 @@ impl<'a> Iterator for Iter<'a> {
             Some(value)
         }
     }
+
+    /// Count the bits left in the current word and every word after it, this is only done
+    /// when asked for so that creating the iterator doesn't scan the whole bitset
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let following = self.words.get((self.word_index + 1)..)
+            .unwrap_or(&[]);
+
+        let remaining = following.iter()
+            .fold(self.word.count_ones() as usize, |sum, w| sum + w.count_ones() as usize);
+
+        (remaining, Some(remaining))
+    }
 }
 
+impl<'a> ExactSizeIterator for Iter<'a> { }
+
 #[cfg(test)]
 mod test {
     use crate::container::*;
@@ -1152,6 +1294,25 @@ mod test {
         assert_eq!(a.cardinality(), 50);
     }
 
+    #[test]
+    fn flip_range_matches_flip() {
+        let data = generate_data(0..65535, 6_000);
+        let ranges = [3..130, 3..4, 64..128, 60..70, 0..65536, 65500..65536, 10..10];
+
+        for range in ranges.iter() {
+            let mut a = BitsetContainer::from_data(&data);
+            let mut b = a.clone();
+
+            a.flip_range(range.clone());
+
+            for value in range.clone() {
+                b.flip(value as u16);
+            }
+
+            assert_eq!(a, b);
+        }
+    }
+
     #[test]
     fn flip_list() {
         let data = generate_data(0..65535, 6_000);
@@ -1195,6 +1356,9 @@ mod test {
         assert!(a.is_full());
         assert!(!a.is_empty());
         assert_eq!(a.cardinality(), BITSET_SIZE_IN_WORDS * 64);
+
+        a.unset_range(0..(1 << 16));
+        assert!(a.is_empty());
     }
 
     #[test]
@@ -1251,6 +1415,20 @@ mod test {
         assert_eq!(rank, 6);
     }
 
+    #[test]
+    fn rank_brute_force() {
+        let data = generate_data(0..65535, 6_000);
+        let a = BitsetContainer::from_data(&data);
+
+        for value in [0, 1, 63, 64, 100, 4095, 30_000, 65535].iter() {
+            let expected = data.iter()
+                .filter(|v| **v <= *value)
+                .count();
+
+            assert_eq!(a.rank(*value), expected);
+        }
+    }
+
     #[test]
     fn select() {
         let range = 0..30;
@@ -1301,6 +1479,35 @@ mod test {
         }
     }
 
+    #[test]
+    fn num_runs() {
+        let mut a = BitsetContainer::new();
+        a.set_range(0..10);
+        a.set_range(60..70);
+        a.set_range(65_500..65_536);
+
+        assert_eq!(a.num_runs(), 3);
+    }
+
+    #[test]
+    fn longest_run() {
+        let mut a = BitsetContainer::new();
+        assert_eq!(a.longest_run(), 0);
+
+        // Runs inside a word, across a word boundary and across several full words
+        a.set_range(3..9);
+        assert_eq!(a.longest_run(), 6);
+        a.set_range(60..70);
+        assert_eq!(a.longest_run(), 10);
+        a.set_range(1_000..1_300);
+        assert_eq!(a.longest_run(), 300);
+        a.set_range(65_000..65_536);
+        assert_eq!(a.longest_run(), 536);
+
+        a.set_range(0..(1 << 16));
+        assert_eq!(a.longest_run(), 1 << 16);
+    }
+
     #[test]
     fn round_trip_serialize() {
         let data = generate_data(0..65535, 6_000);
@@ -1337,6 +1544,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_bitset_or_cardinality() {
+        op_card_test::<BitsetContainer, BitsetContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_bitset_and() {
         op_test::<BitsetContainer, BitsetContainer, u16, _, Container>(
@@ -1358,6 +1577,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_bitset_and_not_cardinality() {
+        op_card_test::<BitsetContainer, BitsetContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_bitset_xor() {
         op_test::<BitsetContainer, BitsetContainer, u16, _, Container>(
@@ -1365,6 +1596,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_bitset_xor_cardinality() {
+        op_card_test::<BitsetContainer, BitsetContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_bitset_subset_of() {
         op_subset_test::<BitsetContainer, BitsetContainer, u16>();
@@ -1416,6 +1659,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_array_or_cardinality() {
+        op_card_test::<BitsetContainer, ArrayContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_array_and() {
         op_test::<BitsetContainer, ArrayContainer, u16, _, Container>(
@@ -1437,6 +1692,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_array_and_not_cardinality() {
+        op_card_test::<BitsetContainer, ArrayContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_array_xor() {
         op_test::<BitsetContainer, ArrayContainer, u16, _, Container>(
@@ -1444,6 +1711,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_array_xor_cardinality() {
+        op_card_test::<BitsetContainer, ArrayContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_array_inplace_or() {
         op_test::<BitsetContainer, ArrayContainer, u16, _, Container>(
@@ -1484,6 +1763,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_run_or_cardinality() {
+        op_card_test::<BitsetContainer, RunContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_run_and() {
         op_test::<BitsetContainer, RunContainer, u16, _, Container>(
@@ -1505,6 +1796,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_run_and_not_cardinality() {
+        op_card_test::<BitsetContainer, RunContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_run_xor() {
         op_test::<BitsetContainer, RunContainer, u16, _, Container>(
@@ -1512,6 +1815,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn bitset_run_xor_cardinality() {
+        op_card_test::<BitsetContainer, RunContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn bitset_run_inplace_or() {
         op_test::<BitsetContainer, RunContainer, u16, _, Container>(
@@ -3,10 +3,13 @@ pub mod array_ops;
 mod bitset;
 mod run;
 
+#[cfg(test)] mod parity_test;
+
 pub use self::array::ArrayContainer;
 pub use self::bitset::BitsetContainer;
 pub use self::run::RunContainer;
 
+use std::cmp::Ordering;
 use std::fmt;
 use std::io::{self, Read, Write};
 use std::iter::Iterator;
@@ -19,10 +22,41 @@ use std::slice;
 /// Default maximum size of an array container before it is converted to another type
 pub const DEFAULT_MAX_SIZE: usize = 4096;
 
+/// The representations a container can be stored in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContainerKind {
+    Array,
+    Bitset,
+    Run
+}
+
+/// Choose the representation with the smallest serialized size for a container holding 
+/// `cardinality` values in `num_runs` runs
+/// 
+/// # Remarks
+/// Arrays are preferred over bitsets up to `DEFAULT_MAX_SIZE`, runs are only chosen if they are strictly smaller
+pub fn choose_representation(cardinality: usize, num_runs: usize) -> ContainerKind {
+    let (kind, size) = if cardinality <= DEFAULT_MAX_SIZE {
+        (ContainerKind::Array, ArrayContainer::serialized_size(cardinality))
+    }
+    else {
+        (ContainerKind::Bitset, BitsetContainer::serialized_size())
+    };
+
+    if RunContainer::serialized_size(num_runs) < size {
+        ContainerKind::Run
+    }
+    else {
+        kind
+    }
+}
+
 /// The set union operation
 pub(crate) trait SetOr<T> {
     fn or(&self, other: &T) -> Container;
 
+    fn or_cardinality(&self, other: &T) -> usize;
+
     fn inplace_or(self, other: &T) -> Container;
 }
 
@@ -39,6 +73,8 @@ pub(crate) trait SetAnd<T> {
 pub(crate) trait SetAndNot<T> {
     fn and_not(&self, other: &T) -> Container;
 
+    fn and_not_cardinality(&self, other: &T) -> usize;
+
     fn inplace_and_not(self, other: &T) -> Container;
 }
 
@@ -46,6 +82,8 @@ pub(crate) trait SetAndNot<T> {
 pub(crate) trait SetXor<T> {
     fn xor(&self, other: &T) -> Container;
 
+    fn xor_cardinality(&self, other: &T) -> usize;
+
     fn inplace_xor(self, other: &T) -> Container;
 }
 
@@ -61,65 +99,78 @@ pub(crate) trait SetNot {
     fn inplace_not(self, range: Range<u32>) -> Container;
 }
 
+macro_rules! dispatch {
+    ($lhs: expr, $rhs: expr, $fn_name: ident) => {
+        match $lhs {
+            Container::Array(c0) => match $rhs {
+                Container::Array(c1) => c0.$fn_name(c1),
+                Container::Bitset(c1) => c0.$fn_name(c1),
+                Container::Run(c1) => c0.$fn_name(c1),
+                Container::None => unreachable!()
+            },
+            Container::Bitset(c0) => match $rhs {
+                Container::Array(c1) => c0.$fn_name(c1),
+                Container::Bitset(c1) => c0.$fn_name(c1),
+                Container::Run(c1) => c0.$fn_name(c1),
+                Container::None => unreachable!()
+            },
+            Container::Run(c0) => match $rhs {
+                Container::Array(c1) => c0.$fn_name(c1),
+                Container::Bitset(c1) => c0.$fn_name(c1),
+                Container::Run(c1) => c0.$fn_name(c1),
+                Container::None => unreachable!()
+            },
+            Container::None => unreachable!()
+        }
+    }
+}
+
 macro_rules! op {
     (#[$doc:meta] $fn_name: ident, $ret_val: ty) => {
         #[$doc]
         pub fn $fn_name(&self, other: &Self) -> $ret_val {
-            match self {
-                Container::Array(c0) => match other {
-                    Container::Array(c1) => c0.$fn_name(c1),
-                    Container::Bitset(c1) => c0.$fn_name(c1),
-                    Container::Run(c1) => c0.$fn_name(c1),
-                    Container::None => unreachable!()
-                },
-                Container::Bitset(c0) => match other {
-                    Container::Array(c1) => c0.$fn_name(c1),
-                    Container::Bitset(c1) => c0.$fn_name(c1),
-                    Container::Run(c1) => c0.$fn_name(c1),
-                    Container::None => unreachable!()
-                },
-                Container::Run(c0) => match other {
-                    Container::Array(c1) => c0.$fn_name(c1),
-                    Container::Bitset(c1) => c0.$fn_name(c1),
-                    Container::Run(c1) => c0.$fn_name(c1),
-                    Container::None => unreachable!()
-                },
-                Container::None => unreachable!()
-            }
+            dispatch!(self, other, $fn_name)
         }
     }
 }
 
-macro_rules! inplace {
+macro_rules! set_op {
     (#[$doc:meta] $fn_name: ident) => {
         #[$doc]
+        pub fn $fn_name(&self, other: &Self) -> Self {
+            dispatch!(self, other, $fn_name).optimize()
+        }
+    }
+}
+
+macro_rules! counted_set_op {
+    (#[$doc:meta] $fn_name: ident, $op: ident) => {
+        #[$doc]
+        pub fn $fn_name(&self, other: &Self) -> (Self, usize) {
+            dispatch!(self, other, $op).optimize_counted()
+        }
+    }
+}
+
+macro_rules! inplace {
+    (#[$doc:meta] $fn_name: ident, $bounded_name: ident) => {
+        #[$doc]
+        #[inline]
+        #[allow(dead_code)]
         pub fn $fn_name(&mut self, other: &Self) {
-            let owned = mem::replace(self, Container::None);
-            let result = match owned {
-                Container::Array(c0) => match other {
-                    Container::Array(c1) => c0.$fn_name(c1),
-                    Container::Bitset(c1) => c0.$fn_name(c1),
-                    Container::Run(c1) => c0.$fn_name(c1),
-                    Container::None => unreachable!()
-                },
-                Container::Bitset(c0) => match other {
-                    Container::Array(c1) => c0.$fn_name(c1),
-                    Container::Bitset(c1) => c0.$fn_name(c1),
-                    Container::Run(c1) => c0.$fn_name(c1),
-                    Container::None => unreachable!()
-                },
-                Container::Run(c0) => match other {
-                    Container::Array(c1) => c0.$fn_name(c1),
-                    Container::Bitset(c1) => c0.$fn_name(c1),
-                    Container::Run(c1) => c0.$fn_name(c1),
-                    Container::None => unreachable!()
-                },
-                Container::None => unreachable!()
-            };
+            self.$bounded_name(other, DEFAULT_MAX_SIZE)
+        }
+
+        #[doc = concat!("Same as [`", stringify!($fn_name), "`](Container::", stringify!($fn_name), ") but arrays in the result hold at most `max_array_size` values")]
+        pub fn $bounded_name(&mut self, other: &Self, max_array_size: usize) {
+            self.replace_with(|owned| {
+                let result = dispatch!(owned, other, $fn_name);
 
-            debug_assert!(!result.is_none());
+                debug_assert!(!result.is_none());
 
-            *self = result;
+                result.optimize()
+                    .into_bounded(max_array_size)
+            });
         }
     }
 }
@@ -160,12 +211,38 @@ impl Container {
             
             Container::Array(container)
         }
-        // Result is a bitset
+        // A single range past the array size is always smallest as one run
         else {
-            let mut container = BitsetContainer::new();
-            container.set_range(range);
-            container.into_efficient_container()
+            let mut container = RunContainer::with_capacity(1);
+            container.add_range(range);
+
+            Container::Run(container)
+        }
+    }
+
+    /// Replace the container with the result of `f` applied to it's current value
+    /// 
+    /// # Remarks
+    /// `self` is temporarily set to `Container::None` while `f` runs. If `f` panics
+    /// an empty container is restored during unwinding so `self` remains usable
+    pub fn replace_with<F>(&mut self, f: F)
+        where F: FnOnce(Container) -> Container
+    {
+        /// Restores an empty container if dropped while the slot is still `None`
+        struct Guard<'a>(&'a mut Container);
+
+        impl<'a> Drop for Guard<'a> {
+            fn drop(&mut self) {
+                if self.0.is_none() {
+                    *self.0 = Container::new();
+                }
+            }
         }
+
+        let guard = Guard(self);
+        let owned = mem::replace(guard.0, Container::None);
+
+        *guard.0 = f(owned);
     }
 
     /// Check whether the container is a `Container::None`
@@ -184,6 +261,167 @@ impl Container {
         }
     }
 
+    /// Set the bits of every value in this container in `bitset`
+    /// 
+    /// # Remarks
+    /// Unlike `inplace_or` the result isn't optimized, making this suitable for accumulating many unions
+    pub fn or_into_bitset(&self, bitset: &mut BitsetContainer) {
+        match self {
+            Container::Array(c) => bitset.set_list(c),
+            Container::Bitset(c) => {
+                for (word, other) in bitset.iter_mut().zip(c.iter_words()) {
+                    *word |= *other;
+                }
+            },
+            Container::Run(c) => {
+                for run in c.iter_runs() {
+                    bitset.set_range(u32::from(run.value)..(u32::from(run.end()) + 1));
+                }
+            },
+            Container::None => unreachable!()
+        }
+    }
+
+    /// Convert the container into the representation with the smallest serialized size
+    /// 
+    /// # Remarks
+    /// Arrays are preferred over bitsets up to `DEFAULT_MAX_SIZE`, runs are only chosen if they are strictly smaller
+    pub fn optimize(self) -> Container {
+        self.optimize_counted().0
+    }
+
+    /// Same as [`optimize`] but also returns the cardinality computed to choose the representation
+    /// 
+    /// [`optimize`]: Container::optimize
+    pub fn optimize_counted(self) -> (Container, usize) {
+        let cardinality = self.cardinality();
+        let num_runs = self.num_runs();
+
+        let container = match choose_representation(cardinality, num_runs) {
+            ContainerKind::Run => match self {
+                Container::Array(c) => Container::Run(c.into()),
+                Container::Bitset(c) => Container::Run(c.into()),
+                _ => self
+            },
+            ContainerKind::Array => match self {
+                Container::Bitset(c) => Container::Array(c.into()),
+                Container::Run(c) => Container::Array(c.into()),
+                _ => self
+            },
+            ContainerKind::Bitset => match self {
+                Container::Array(c) => Container::Bitset(c.into()),
+                Container::Run(c) => Container::Bitset(c.into()),
+                _ => self
+            }
+        };
+
+        (container, cardinality)
+    }
+
+    /// Convert between an array and a bitset so that arrays hold at most `max_array_size` values
+    /// 
+    /// # Remarks
+    /// Bitsets are only counted when the threshold is above `DEFAULT_MAX_SIZE`, 
+    /// below it any bitset smaller than the threshold would already have been demoted
+    pub fn into_bounded(self, max_array_size: usize) -> Container {
+        match self {
+            Container::Array(c) if c.cardinality() > max_array_size => Container::Bitset(c.into()),
+            Container::Bitset(c) if max_array_size > DEFAULT_MAX_SIZE && c.cardinality() <= max_array_size => {
+                Container::Array(c.into())
+            },
+            _ => self
+        }
+    }
+
+    /// Check whether both containers hold the same values regardless of their representation
+    pub fn eq_values(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Container::Array(c0), Container::Array(c1)) => c0.iter().eq(c1.iter()),
+            (Container::Bitset(c0), Container::Bitset(c1)) => c0.iter_words().eq(c1.iter_words()),
+            _ => self.cardinality() == other.cardinality() && self.iter().eq(other.iter())
+        }
+    }
+
+    /// Check whether the containers share any value
+    /// 
+    /// # Remarks
+    /// Returns as soon as a shared value is found without building the intersection
+    pub fn intersects(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Container::Array(c0), Container::Array(c1)) => {
+                let mut iter0 = c0.iter().peekable();
+                let mut iter1 = c1.iter().peekable();
+
+                while let (Some(v0), Some(v1)) = (iter0.peek(), iter1.peek()) {
+                    match v0.cmp(v1) {
+                        Ordering::Less => { iter0.next(); },
+                        Ordering::Greater => { iter1.next(); },
+                        Ordering::Equal => return true
+                    }
+                }
+
+                false
+            },
+            (Container::Bitset(c0), Container::Bitset(c1)) => {
+                c0.iter_words()
+                    .zip(c1.iter_words())
+                    .any(|(w0, w1)| w0 & w1 != 0)
+            },
+            (Container::Run(c0), Container::Run(c1)) => {
+                let mut iter0 = c0.iter_runs().peekable();
+                let mut iter1 = c1.iter_runs().peekable();
+
+                while let (Some(r0), Some(r1)) = (iter0.peek(), iter1.peek()) {
+                    if r0.end() < r1.value {
+                        iter0.next();
+                    }
+                    else if r1.end() < r0.value {
+                        iter1.next();
+                    }
+                    else {
+                        return true;
+                    }
+                }
+
+                false
+            },
+            (Container::Array(c0), Container::Bitset(c1)) => c0.iter().any(|v| c1.contains(*v)),
+            (Container::Bitset(c0), Container::Array(c1)) => c1.iter().any(|v| c0.contains(*v)),
+            (Container::Array(c0), Container::Run(c1)) => c0.iter().any(|v| c1.contains(*v)),
+            (Container::Run(c0), Container::Array(c1)) => c1.iter().any(|v| c0.contains(*v)),
+            (Container::Bitset(c0), Container::Run(c1)) | (Container::Run(c1), Container::Bitset(c0)) => {
+                c1.iter_runs()
+                    .any(|run| c0.cardinality_range(u32::from(run.value)..(u32::from(run.end()) + 1)) != 0)
+            },
+            _ => unreachable!()
+        }
+    }
+
+    /// The number of runs of consecutive values in the container
+    pub fn num_runs(&self) -> usize {
+        match self {
+            Container::Array(c) => c.num_runs(),
+            Container::Bitset(c) => c.num_runs(),
+            Container::Run(c) => c.num_runs(),
+            Container::None => unreachable!()
+        }
+    }
+
+    /// The length of the longest run of consecutive values in the container
+    pub fn longest_run(&self) -> usize {
+        match self {
+            Container::Run(c) => {
+                c.iter_runs()
+                    .map(|run| run.length as usize + 1)
+                    .max()
+                    .unwrap_or(0)
+            },
+            Container::Array(c) => c.longest_run(),
+            Container::Bitset(c) => c.longest_run(),
+            Container::None => unreachable!()
+        }
+    }
+
     /// Shrink the container it fit it's content
     pub fn shrink_to_fit(&mut self) {
         match self {
@@ -194,11 +432,28 @@ impl Container {
         }
     }
 
+    /// The number of bytes allocated on the heap by the underlying container
+    pub fn mem_size(&self) -> usize {
+        match self {
+            Container::Array(c) => c.mem_size(),
+            Container::Bitset(c) => c.mem_size(),
+            Container::Run(c) => c.mem_size(),
+            Container::None => unreachable!()
+        }
+    }
+
     /// Add a value to the underlying container
+    #[inline]
     pub fn add(&mut self, value: u16) {
+        self.add_bounded(value, DEFAULT_MAX_SIZE)
+    }
+
+    /// Add a value to the container, promoting an array container to a bitset 
+    /// once it would hold more than `max_array_size` values
+    pub fn add_bounded(&mut self, value: u16, max_array_size: usize) {
         match self {
             Container::Array(c) => {
-                if !c.add(value) {
+                if !c.add_bounded(value, max_array_size) {
                     let mut bitset: BitsetContainer = c.into();
                     bitset.add(value);
 
@@ -215,9 +470,30 @@ impl Container {
         };
     }
 
+    /// Add all values within the specified range to the underlying container
+    /// 
+    /// # Remarks
+    /// Array containers are promoted to a bitset if the range pushes them past `DEFAULT_MAX_SIZE`
+    #[inline]
+    #[allow(dead_code)]
     pub fn add_range(&mut self, range: Range<u32>) {
+        self.add_range_bounded(range, DEFAULT_MAX_SIZE)
+    }
+
+    /// Add all values within the specified range to the container, promoting an array 
+    /// container to a bitset once it would hold more than `max_array_size` values
+    pub fn add_range_bounded(&mut self, range: Range<u32>, max_array_size: usize) {
         match self {
-            Container::Array(c) => c.add_range(range),
+            Container::Array(c) => {
+                c.add_range(range);
+
+                if c.cardinality() == (1 << 16) {
+                    *self = Container::Run(RunContainer::full());
+                }
+                else if c.cardinality() > max_array_size {
+                    *self = Container::Bitset(c.into());
+                }
+            },
             Container::Bitset(c) => c.add_range(range),
             Container::Run(c) => c.add_range(range),
             Container::None => unreachable!()
@@ -225,13 +501,20 @@ impl Container {
     }
     
     /// Remove a value from the underlying container
+    #[inline]
     pub fn remove(&mut self, value: u16) {
+        self.remove_bounded(value, DEFAULT_MAX_SIZE)
+    }
+
+    /// Remove a value from the container, demoting a bitset container to an array 
+    /// once it holds fewer than `max_array_size` values
+    pub fn remove_bounded(&mut self, value: u16, max_array_size: usize) {
         match self {
             Container::Array(c) => {
                 c.remove(value);
             },
             Container::Bitset(c) => {
-                if c.remove(value) && c.cardinality() < DEFAULT_MAX_SIZE {
+                if c.remove(value) && c.cardinality() < max_array_size {
                     *self = Container::Array(c.into());
                 }
             },
@@ -242,11 +525,12 @@ impl Container {
         }
     }
 
-    /// Remove all elements within [min-max)
+    /// Remove all elements within [min-max), demoting a bitset container to an array 
+    /// once it holds fewer than `max_array_size` values
     /// 
     /// # Returns
     /// Returns true if the container still has elements, returns false otherwise
-    pub fn remove_range(&mut self, range: Range<u32>) -> bool {
+    pub fn remove_range_bounded(&mut self, range: Range<u32>, max_array_size: usize) -> bool {
         match self {
             Container::Array(c) => {
                 c.remove_range(range);
@@ -259,7 +543,7 @@ impl Container {
                     false
                 }
                 else {
-                    if c.cardinality() < DEFAULT_MAX_SIZE {
+                    if c.cardinality() < max_array_size {
                         *self = Container::Array(c.into());
                     }
 
@@ -345,6 +629,16 @@ impl Container {
         }
     }
 
+    /// Get the number of values within [min-max)
+    pub fn cardinality_range(&self, range: Range<u32>) -> usize {
+        match self {
+            Container::Array(c) => c.cardinality_range(range),
+            Container::Bitset(c) => c.cardinality_range(range),
+            Container::Run(c) => c.cardinality_range(range),
+            Container::None => unreachable!()
+        }
+    }
+
     /// Get the minmimu value in the container
     pub fn min(&self) -> Option<u16> {
         match self {
@@ -365,6 +659,26 @@ impl Container {
         }
     }
 
+    /// Get the smallest value not in the container. Returns `None` if the container is full
+    pub fn min_excluded(&self) -> Option<u16> {
+        match self {
+            Container::Array(c) => c.min_excluded(),
+            Container::Bitset(c) => c.min_excluded(),
+            Container::Run(c) => c.min_excluded(),
+            Container::None => unreachable!()
+        }
+    }
+
+    /// Get the smallest value not in the container that is at least `from`. Returns `None` if every such value is present
+    pub fn min_excluded_from(&self, from: u16) -> Option<u16> {
+        match self {
+            Container::Array(c) => c.min_excluded_from(from),
+            Container::Bitset(c) => c.min_excluded_from(from),
+            Container::Run(c) => c.min_excluded_from(from),
+            Container::None => unreachable!()
+        }
+    }
+
     /// Find the number of values smaller or equal to `x`
     pub fn rank(&self, value: u16) -> usize {
         match self {
@@ -390,34 +704,56 @@ impl Container {
         subset_of, bool
     }
 
-    op! {
+    set_op! {
         /// Perform an `or` operation between `self` and `other`
-        or, Self
+        or
     }
 
-    op! {
+    set_op! {
         /// Perform an `and` operation between `self` and `other`
-        and, Self
+        and
     }
 
-    op! {
+    set_op! {
         /// Perform an `and not` operation between `self` and `other`
-        and_not, Self
+        and_not
     }
 
-    op! {
+    set_op! {
         /// Perform an `xor` operation between `self` and `other`
-        xor, Self
+        xor
+    }
+
+    counted_set_op! {
+        /// Perform an `or` operation between `self` and `other` and count the values in the result
+        or_with_cardinality, or
+    }
+
+    counted_set_op! {
+        /// Perform an `and` operation between `self` and `other` and count the values in the result
+        and_with_cardinality, and
+    }
+
+    counted_set_op! {
+        /// Perform an `and not` operation between `self` and `other` and count the values in the result
+        and_not_with_cardinality, and_not
+    }
+
+    counted_set_op! {
+        /// Perform an `xor` operation between `self` and `other` and count the values in the result
+        xor_with_cardinality, xor
     }
 
     /// Compute the negation of this container within the specified range
     pub fn not(&self, range: Range<u32>) -> Self {
-        match self {
+        let result = match self {
             Container::Array(c) => c.not(range),
             Container::Bitset(c) => c.not(range),
             Container::Run(c) => c.not(range),
             Container::None => unreachable!()
-        }
+        };
+
+        result.optimize()
     }
 
     op! {
@@ -425,34 +761,51 @@ impl Container {
         and_cardinality, usize
     }
 
+    op! {
+        /// Compute the cardinality of an `and not` operation between `self` and `other`
+        and_not_cardinality, usize
+    }
+
+    op! {
+        /// Compute the cardinality of an `or` operation between `self` and `other`
+        or_cardinality, usize
+    }
+
+    op! {
+        /// Compute the cardinality of an `xor` operation between `self` and `other`
+        xor_cardinality, usize
+    }
+
     inplace! {
         /// Compute the `or` of self `self` and `other` storing the result in `self`
-        inplace_or
+        inplace_or, inplace_or_bounded
     }
 
     inplace! {
         /// Compute the `and` of self `self` and `other` storing the result in `self`
-        inplace_and
+        inplace_and, inplace_and_bounded
     }
 
     inplace! {
         /// Compute the `and_not` of self `self` and `other` storing the result in `self`
-        inplace_and_not
+        inplace_and_not, inplace_and_not_bounded
     }
 
     inplace! {
         /// Compute the `xor` of self `self` and `other` storing the result in `self`
-        inplace_xor
+        inplace_xor, inplace_xor_bounded
     }
 
     /// Compute the negation of self inplace within the specified range
     pub fn inplace_not(self, range: Range<u32>) -> Container {
-        match self {
+        let result = match self {
             Container::Array(c) => c.inplace_not(range),
             Container::Bitset(c) => c.inplace_not(range),
             Container::Run(c) => c.inplace_not(range),
             Container::None => unreachable!()
-        }
+        };
+
+        result.optimize()
     }
     
     /// Get a generic iterator over the container values
@@ -468,23 +821,218 @@ impl Container {
             iter
         }
     }
+
+    /// Fold every value in the container into an accumulator
+    ///
+    /// # Remarks
+    /// Values are visited by iterating the underlying storage directly rather than
+    /// through the generic iterator, avoiding the per value dispatch
+    pub fn fold<B, F>(&self, init: B, mut f: F) -> B
+        where F: FnMut(B, u16) -> B
+    {
+        match self {
+            Container::Array(c) => {
+                c.iter().fold(init, |acc, value| f(acc, *value))
+            },
+            Container::Bitset(c) => {
+                let mut acc = init;
+                for (i, word) in c.iter_words().enumerate() {
+                    let base = (i * 64) as u16;
+                    let mut word = *word;
+
+                    while word != 0 {
+                        acc = f(acc, base + word.trailing_zeros() as u16);
+                        word &= word - 1;
+                    }
+                }
+
+                acc
+            },
+            Container::Run(c) => {
+                let mut acc = init;
+                for run in c.iter_runs() {
+                    for value in run.value..=run.end() {
+                        acc = f(acc, value);
+                    }
+                }
+
+                acc
+            },
+            Container::None => unreachable!()
+        }
+    }
+
+    /// Write the values of the container that are at least `from` into `out`, combined with `high`
+    /// 
+    /// # Returns
+    /// The number of values written and the value to resume from if `out` filled before the container was exhausted
+    pub fn decode_into(&self, from: u16, high: u32, out: &mut [u32]) -> (usize, Option<u16>) {
+        match self {
+            Container::Array(c) => {
+                let values = &c[array_ops::count_less(c, from)..];
+                let count = values.len().min(out.len());
+
+                for (dst, value) in out.iter_mut().zip(values.iter()) {
+                    *dst = high | u32::from(*value);
+                }
+
+                (count, values.get(count).copied())
+            },
+            Container::Bitset(c) => {
+                let mut count = 0;
+                let first = usize::from(from) / 64;
+
+                for (i, word) in c.iter_words().enumerate().skip(first) {
+                    let base = (i * 64) as u32;
+                    let mut word = *word;
+
+                    // Mask off the values before `from` in the first word
+                    if i == first {
+                        word &= !0 << (from % 64);
+                    }
+
+                    while word != 0 {
+                        let value = base + word.trailing_zeros();
+                        if count == out.len() {
+                            return (count, Some(value as u16));
+                        }
+
+                        out[count] = high | value;
+                        count += 1;
+                        word &= word - 1;
+                    }
+                }
+
+                (count, None)
+            },
+            Container::Run(c) => {
+                let mut count = 0;
+                let from = u32::from(from);
+
+                for run in c.iter_runs() {
+                    let range = run.into_range();
+                    if range.end <= from {
+                        continue;
+                    }
+
+                    for value in range.start.max(from)..range.end {
+                        if count == out.len() {
+                            return (count, Some(value as u16));
+                        }
+
+                        out[count] = high | value;
+                        count += 1;
+                    }
+                }
+
+                (count, None)
+            },
+            Container::None => unreachable!()
+        }
+    }
+
+    /// Create a container from up to 1024 words of a little endian dense bitvector
+    /// where bit `i` represents the value `i`
+    /// 
+    /// # Remarks
+    /// The representation is chosen from the number of set bits and converted to runs if smaller.
+    /// Returns `None` if no bits are set
+    pub fn from_dense_words(words: &[u64]) -> Option<Self> {
+        debug_assert!(words.len() <= bitset::BITSET_SIZE_IN_WORDS);
+
+        let cardinality: usize = words.iter()
+            .map(|w| w.count_ones() as usize)
+            .sum();
+
+        if cardinality == 0 {
+            return None;
+        }
+
+        let container = {
+            if cardinality > DEFAULT_MAX_SIZE {
+                let mut bitset = BitsetContainer::new();
+                bitset[..words.len()].copy_from_slice(words);
+
+                Container::Bitset(bitset)
+            }
+            else {
+                let mut array = ArrayContainer::with_capacity(cardinality);
+                for (i, word) in words.iter().enumerate() {
+                    let base = (i * 64) as u16;
+                    let mut word = *word;
+
+                    while word != 0 {
+                        array.push(base + word.trailing_zeros() as u16);
+                        word &= word - 1;
+                    }
+                }
+
+                Container::Array(array)
+            }
+        };
+
+        Some(container.optimize())
+    }
+
+    /// Set the bits of the values in this container in `out`, a little endian dense bitvector 
+    /// where bit `i` represents the value `i`
+    /// 
+    /// # Remarks
+    /// Bitsets are copied word by word, other containers set the bit of each value.
+    /// Values past the end of `out` are ignored
+    pub fn write_dense(&self, out: &mut [u8]) {
+        match self {
+            Container::Bitset(c) => {
+                for (dst, word) in out.chunks_mut(8).zip(c.iter_words()) {
+                    dst.copy_from_slice(&word.to_le_bytes()[..dst.len()]);
+                }
+            },
+            Container::Array(_) | Container::Run(_) => {
+                for value in self.iter() {
+                    let index = usize::from(value / 8);
+                    if index >= out.len() {
+                        break;
+                    }
+
+                    out[index] |= 1 << (value % 8);
+                }
+            },
+            Container::None => unreachable!()
+        }
+    }
 }
 
 impl Container {
     /// Get the serialized size of a container
+    /// 
+    /// # Remarks
+    /// The format determines whether a container is an array or bitset by it's cardinality,
+    /// containers built with a custom array size are serialized as whichever the format expects
     pub fn serialized_size(&self) -> usize {
         match self {
-            Container::Array(c) => ArrayContainer::serialized_size(c.cardinality()),
-            Container::Bitset(_c) => BitsetContainer::serialized_size(),
+            Container::Array(_) | Container::Bitset(_) if self.serializes_as_bitset() => {
+                BitsetContainer::serialized_size()
+            },
+            Container::Array(_) | Container::Bitset(_) => {
+                ArrayContainer::serialized_size(self.cardinality())
+            },
             Container::Run(c) => RunContainer::serialized_size(c.num_runs()),
             _ => unreachable!()
         }
     }
 
+    /// Check whether a non run container is stored as a bitset in the serialized format
+    #[inline]
+    pub fn serializes_as_bitset(&self) -> bool {
+        !self.is_run() && self.cardinality() > DEFAULT_MAX_SIZE
+    }
+
     /// Serialize the container into the provided writer
     #[cfg(target_endian = "little")]
     pub fn serialize<W: Write>(&self, buf: &mut W) -> io::Result<usize> {
         match self {
+            Container::Array(c) if self.serializes_as_bitset() => BitsetContainer::from(c.clone()).serialize(buf),
+            Container::Bitset(c) if !self.serializes_as_bitset() => ArrayContainer::from(c.clone()).serialize(buf),
             Container::Array(c) => c.serialize(buf),
             Container::Bitset(c) => c.serialize(buf),
             Container::Run(c) => c.serialize(buf),
@@ -527,8 +1075,21 @@ impl<'a> Iterator for Iter<'a> {
             ContainerIter::Run(c) => c.next()
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = match &self.iter {
+            ContainerIter::None => 0,
+            ContainerIter::Array(c) => c.len(),
+            ContainerIter::Bitset(c) => c.len(),
+            ContainerIter::Run(c) => c.len()
+        };
+
+        (len, Some(len))
+    }
 }
 
+impl<'a> ExactSizeIterator for Iter<'a> { }
+
 fn is_valid_range(range: Range<u32>) -> bool {
     let valid_len = range.len() <= (1 << 16);
     let valid_bounds = range.start <= (1 << 16) && range.end <= (1 << 16);
@@ -560,4 +1121,244 @@ mod test {
             self.cardinality()
         }
     }
+
+    /// Get a short name for the type of container
+    fn kind(container: &Container) -> &'static str {
+        match container {
+            Container::Array(_c) => "array",
+            Container::Bitset(_c) => "bitset",
+            Container::Run(_c) => "run",
+            Container::None => "none"
+        }
+    }
+
+    #[test]
+    fn optimize() {
+        // Sparse values are best stored as an array
+        let data = generate_data(0..65535, 1_000);
+        let a = Container::Bitset(BitsetContainer::from(ArrayContainer::from_data(&data)));
+        assert_eq!(kind(&a.optimize()), "array");
+
+        // Dense random values are best stored as a bitset
+        let data = generate_data(0..65535, 20_000);
+        let a = Container::Run(RunContainer::from_data(&data));
+        assert_eq!(kind(&a.optimize()), "bitset");
+
+        // Long contiguous ranges are best stored as a run
+        let mut a = BitsetContainer::new();
+        a.set_range(0..30_000);
+        let a = Container::Bitset(a).optimize();
+        assert_eq!(kind(&a), "run");
+        assert_eq!(a.cardinality(), 30_000);
+    }
+
+    #[test]
+    fn intersects() {
+        let in_each = |data: &[u16]| {
+            let array = ArrayContainer::from_data(data);
+            vec![
+                Container::Bitset(BitsetContainer::from(array.clone())),
+                Container::Run(RunContainer::from(array.clone())),
+                Container::Array(array)
+            ]
+        };
+
+        let evens: Vec<u16> = (0..2_000).map(|v| v * 2).collect();
+        let odds: Vec<u16> = (0..2_000).map(|v| v * 2 + 1).collect();
+        let high: Vec<u16> = (65_000..=65_535).collect();
+        let sets = [evens, odds, high, vec![3_999], vec![65_535]];
+
+        for (i, a) in sets.iter().enumerate() {
+            for (j, b) in sets.iter().enumerate() {
+                for c0 in in_each(a).iter() {
+                    for c1 in in_each(b).iter() {
+                        assert_eq!(
+                            c0.intersects(c1), c0.and_cardinality(c1) != 0, 
+                            "Failed for {} {} and {} {}", kind(c0), i, kind(c1), j
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn min_excluded_from() {
+        let mut data = generate_data(0..65535, 3_000);
+        data.extend(100..300);
+        data.extend(65_000..=65_535);
+        data.sort_unstable();
+        data.dedup();
+
+        let array = ArrayContainer::from_data(&data);
+        let containers = [
+            Container::Bitset(BitsetContainer::from(array.clone())),
+            Container::Run(RunContainer::from(array.clone())),
+            Container::Array(array)
+        ];
+
+        for container in containers.iter() {
+            for from in [0, 1, 99, 100, 150, 299, 300, 4_000, 64_999, 65_000, 65_535].iter() {
+                let expected = (u32::from(*from)..(1 << 16))
+                    .find(|value| data.binary_search(&(*value as u16)).is_err())
+                    .map(|value| value as u16);
+
+                assert_eq!(
+                    container.min_excluded_from(*from), expected, 
+                    "Failed for {} from {}", kind(container), from
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn choose_representation() {
+        let cardinalities = [1, 2, 100, 2_000, 4_095, 4_096, 4_097, 10_000, 30_000, 65_536];
+        let runs = [1, 2, 10, 1_000, 2_047, 2_048, 2_049, 4_096, 10_000, 32_768];
+
+        for cardinality in cardinalities.iter() {
+            for num_runs in runs.iter().filter(|r| *r <= cardinality) {
+                let as_array = ArrayContainer::serialized_size(*cardinality);
+                let as_bitset = BitsetContainer::serialized_size();
+                let as_run = RunContainer::serialized_size(*num_runs);
+
+                let expected = if as_run < as_array.min(as_bitset) {
+                    ContainerKind::Run
+                }
+                else if *cardinality <= DEFAULT_MAX_SIZE {
+                    ContainerKind::Array
+                }
+                else {
+                    ContainerKind::Bitset
+                };
+
+                assert_eq!(
+                    super::choose_representation(*cardinality, *num_runs), 
+                    expected, 
+                    "Failed for {} values in {} runs", 
+                    cardinality,
+                    num_runs
+                );
+            }
+        }
+
+        // Ties go to the non run representations
+        let tie = (ArrayContainer::serialized_size(101) - 2) / 4;
+        assert_eq!(RunContainer::serialized_size(tie), ArrayContainer::serialized_size(101));
+        assert_eq!(super::choose_representation(101, tie), ContainerKind::Array);
+
+        assert_eq!(super::choose_representation(65_536, 1), ContainerKind::Run);
+        assert_eq!(super::choose_representation(DEFAULT_MAX_SIZE, DEFAULT_MAX_SIZE), ContainerKind::Array);
+        assert_eq!(super::choose_representation(DEFAULT_MAX_SIZE + 1, DEFAULT_MAX_SIZE), ContainerKind::Bitset);
+    }
+
+    #[test]
+    fn set_op_result_types() {
+        // Two overlapping dense ranges produce a single run
+        let a = Container::from_range(0..30_000);
+        let b = Container::from_range(10_000..40_000);
+
+        assert_eq!(kind(&a.or(&b)), "run");
+        assert_eq!(kind(&a.and(&b)), "run");
+        assert_eq!(kind(&a.and_not(&b)), "run");
+        assert_eq!(kind(&a.xor(&b)), "run");
+
+        // Intersecting a range with dense random data produces a bitset
+        let data = generate_data(0..65535, 40_000);
+        let c = Container::Bitset(BitsetContainer::from(RunContainer::from_data(&data)));
+        let result = a.and(&c);
+        assert_eq!(kind(&result), "bitset");
+
+        // Intersecting with sparse random data produces an array
+        let data = generate_data(0..65535, 2_000);
+        let d = Container::Array(ArrayContainer::from_data(&data));
+        let result = a.and(&d);
+        assert_eq!(kind(&result), "array");
+
+        // Inplace ops pick the same representation
+        let mut e = a.clone();
+        e.inplace_and(&c);
+        assert_eq!(kind(&e), "bitset");
+
+        let mut e = a.clone();
+        e.inplace_or(&b);
+        assert_eq!(kind(&e), "run");
+    }
+
+    #[test]
+    fn add_range_promotes_array() {
+        let mut container = Container::new();
+        container.add_range(0..10_000);
+
+        match &container {
+            Container::Bitset(_c) => (),
+            _ => panic!("Expected a bitset container")
+        }
+
+        assert_eq!(container.cardinality(), 10_000);
+        assert!(container.contains_range(0..10_000));
+        assert!(!container.contains(10_000));
+    }
+
+    #[test]
+    fn add_range_full() {
+        let mut container = Container::new();
+        container.add(7);
+        container.add_range(0..(1 << 16));
+
+        match &container {
+            Container::Run(c) => assert!(c.is_full()),
+            _ => panic!("Expected a run container")
+        }
+
+        assert_eq!(container.cardinality(), 1 << 16);
+    }
+
+    #[test]
+    fn replace_with_unwind() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        let data = generate_data(0..65535, 1_000);
+        let mut container = Container::Array(ArrayContainer::from_data(&data));
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            container.replace_with(|_c| panic!("injected panic"));
+        }));
+
+        assert!(result.is_err());
+        assert!(!container.is_none());
+        assert!(container.is_empty());
+
+        // The restored container can still be operated on
+        let other = Container::Array(ArrayContainer::from_data(&data));
+        container.inplace_or(&other);
+        assert_eq!(container.cardinality(), data.len());
+
+        container.inplace_and(&other);
+        assert_eq!(container.cardinality(), data.len());
+    }
+
+    #[test]
+    fn iter_len() {
+        let data = generate_data(0..65535, 3_000);
+        let array = Container::Array(ArrayContainer::from_data(&data));
+        let bitset = Container::Bitset(BitsetContainer::from(ArrayContainer::from_data(&data)));
+
+        let runs: Vec<u16> = (10..20).chain(100..1_000).chain(65_000..=65_535).collect();
+        let run = Container::Run(RunContainer::from_data(&runs));
+
+        for container in [array, bitset, run, Container::Run(RunContainer::full())].iter() {
+            let mut iter = container.iter();
+            let mut expected = container.cardinality();
+
+            assert_eq!(iter.len(), expected, "{}", kind(container));
+
+            while iter.next().is_some() {
+                expected -= 1;
+                assert_eq!(iter.len(), expected, "{}", kind(container));
+            }
+
+            assert_eq!(expected, 0);
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,95 @@
+#![cfg(test)]
+
+//! Parity tests ensuring every `inplace_*` operation produces the same contents as its
+//! allocating counterpart for each pair of container types
+//!
+//! Several inplace operations forward to the allocating version, these tests guard
+//! against a dedicated inplace implementation diverging from it
+
+use crate::container::*;
+use crate::test::*;
+
+/// Check that `inplace` matches `allocating` and return the inplace result for `op_test`
+fn parity(allocating: Container, inplace: Container) -> Container {
+    assert_eq!(
+        inplace.cardinality(),
+        allocating.cardinality(),
+        "Unequal cardinality; inplace {}, allocating {}",
+        inplace.cardinality(),
+        allocating.cardinality()
+    );
+
+    assert!(inplace.iter().eq(allocating.iter()), "Inplace result differs from allocating result");
+
+    inplace
+}
+
+macro_rules! parity_test {
+    ($name: ident, $lhs: ty, $rhs: ty, $op: expr, $trait: ident, $fn_name: ident, $inplace_fn_name: ident) => {
+        #[test]
+        fn $name() {
+            op_test::<$lhs, $rhs, u16, _, Container>(
+                $op, |a, b| {
+                    let allocating = $trait::$fn_name(&a, &b);
+                    let inplace = $trait::$inplace_fn_name(a, &b);
+
+                    parity(allocating, inplace)
+                }
+            );
+        }
+    }
+}
+
+macro_rules! parity_tests {
+    ($lhs: ty, $rhs: ty, $or: ident, $and: ident, $and_not: ident, $xor: ident) => {
+        parity_test!($or, $lhs, $rhs, OpType::Or, SetOr, or, inplace_or);
+        parity_test!($and, $lhs, $rhs, OpType::And, SetAnd, and, inplace_and);
+        parity_test!($and_not, $lhs, $rhs, OpType::AndNot, SetAndNot, and_not, inplace_and_not);
+        parity_test!($xor, $lhs, $rhs, OpType::Xor, SetXor, xor, inplace_xor);
+    }
+}
+
+parity_tests!(
+    ArrayContainer, ArrayContainer,
+    array_array_or, array_array_and, array_array_and_not, array_array_xor
+);
+
+parity_tests!(
+    ArrayContainer, BitsetContainer,
+    array_bitset_or, array_bitset_and, array_bitset_and_not, array_bitset_xor
+);
+
+parity_tests!(
+    ArrayContainer, RunContainer,
+    array_run_or, array_run_and, array_run_and_not, array_run_xor
+);
+
+parity_tests!(
+    BitsetContainer, ArrayContainer,
+    bitset_array_or, bitset_array_and, bitset_array_and_not, bitset_array_xor
+);
+
+parity_tests!(
+    BitsetContainer, BitsetContainer,
+    bitset_bitset_or, bitset_bitset_and, bitset_bitset_and_not, bitset_bitset_xor
+);
+
+parity_tests!(
+    BitsetContainer, RunContainer,
+    bitset_run_or, bitset_run_and, bitset_run_and_not, bitset_run_xor
+);
+
+parity_tests!(
+    RunContainer, ArrayContainer,
+    run_array_or, run_array_and, run_array_and_not, run_array_xor
+);
+
+parity_tests!(
+    RunContainer, BitsetContainer,
+    run_bitset_or, run_bitset_and, run_bitset_and_not, run_bitset_xor
+);
+
+parity_tests!(
+    RunContainer, RunContainer,
+    run_run_or, run_run_and, run_run_and_not, run_run_xor
+);
@@ -41,7 +41,7 @@ impl Rle16 {
     
     #[inline]
     pub fn into_range(self) -> Range<u32> {
-        u32::from(self.value)..u32::from(self.value + self.length + 1)
+        u32::from(self.value)..(u32::from(self.value) + u32::from(self.length) + 1)
     }
 }
 
@@ -89,11 +89,70 @@ impl RunContainer {
         }
     }
     
+    /// Create a new run container containing every possible value
+    pub fn full() -> Self {
+        Self {
+            runs: vec![Rle16::new(0, u16::MAX)]
+        }
+    }
+    
+    /// The number of runs the container can hold without reallocating
+    #[cfg(test)]
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.runs.capacity()
+    }
+
+    /// Reserve space for `additional` runs
+    #[cfg(test)]
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.runs.reserve(additional);
+    }
+
     /// Shrink the run container's backing memory to fit it's contents
+    /// 
+    /// # Remarks
+    /// Adjacent runs are coalesced first so no memory is wasted on redundant runs
     #[inline]
     pub fn shrink_to_fit(&mut self) {
+        self.coalesce();
         self.runs.shrink_to_fit()
     }
+
+    /// The number of bytes allocated by the run container
+    #[inline]
+    pub fn mem_size(&self) -> usize {
+        self.runs.capacity() * mem::size_of::<Rle16>()
+    }
+
+    /// Merge any adjacent or overlapping runs into a single run
+    /// 
+    /// # Remarks
+    /// Operations that mutate the runs directly can leave runs such as `[0-4][5-9]`
+    /// which represent the same values as `[0-9]` using more space
+    pub fn coalesce(&mut self) {
+        if self.runs.len() < 2 {
+            return;
+        }
+
+        let mut write = 0;
+        for read in 1..self.runs.len() {
+            let current = self.runs[write];
+            let next = self.runs[read];
+
+            if u32::from(next.value) <= u32::from(current.end()) + 1 {
+                let end = current.end().max(next.end());
+                self.runs[write].length = end - current.value;
+            }
+            else {
+                write += 1;
+                self.runs[write] = next;
+            }
+        }
+
+        self.runs.truncate(write + 1);
+    }
     
     /// Add a value to the run container
     pub fn add(&mut self, value: u16) {
@@ -145,18 +204,27 @@ impl RunContainer {
                     }
                 }
 
-                self.runs.push(Rle16::new(value, 0));
+                // The value preceeds all runs
+                self.runs.insert(0, Rle16::new(value, 0));
             }
         }
     }
     
     /// Add all values in the range [min-max) to the run container
+    /// 
+    /// # Remarks
+    /// Runs overlapping or adjacent to the range are fused with it into a single run
     pub fn add_range(&mut self, range: Range<u32>) {
         let min = range.start;
         let max = range.end;
 
+        if min >= max {
+            return;
+        }
+
+        // Both searches work on inclusive values, treat the runs touching either end as common
         let runs_min = self.rle_count_less(min);
-        let runs_max = self.rle_count_greater(max);
+        let runs_max = self.rle_count_greater(max - 1);
 
         let common = self.runs.len() - runs_min - runs_max;
         if common == 0 {
@@ -166,13 +234,14 @@ impl RunContainer {
             );
         }
         else {
-            let common_min = self.runs[runs_min].value;
-            let common_max = self.runs[runs_min + common - 1].end();
-            let result_min = common_min.min(min as u16);
-            let result_max = common_max.max(max as u16);
+            let common_min = u32::from(self.runs[runs_min].value);
+            let common_max = u32::from(self.runs[runs_min + common - 1].end());
+            let result_min = common_min.min(min);
+            let result_max = common_max.max(max - 1);
 
-            self.runs[runs_min] = Rle16::new(result_min, result_max - result_min);
-            self.runs.splice((runs_min + 1)..runs_max, iter::empty());
+            // Fuse all common runs into the first one
+            self.runs[runs_min] = Rle16::new(result_min as u16, (result_max - result_min) as u16);
+            self.runs.splice((runs_min + 1)..(runs_min + common), iter::empty());
         }
     }
     
@@ -219,65 +288,61 @@ impl RunContainer {
     
     /// Remove all values in the range [min-max) from the run container
     pub fn remove_range(&mut self, range: Range<u32>) {
+        if range.start >= range.end {
+            return;
+        }
+
         let min = range.start as u16;
         let max = (range.end - 1) as u16;
 
-        let mut si0 = None;
-        let mut si1 = None;
-
-        // Update the left-most run
-        match self.find_run(min) {
-            SearchResult::ExactMatch(mut index) => {
-                let run = self.runs[index as usize];
+        // The absolute index of the first run that lies entirely within the range
+        let start = match self.find_run(min) {
+            SearchResult::ExactMatch(index) => {
+                let run = self.runs[index];
                 if min > run.value && max < run.end() {
                     // Split into two runs
 
                     // Right interval
-                    self.runs.insert(index as usize + 1, Rle16::new(max + 1, run.end() - (max + 1)));
+                    self.runs.insert(index + 1, Rle16::new(max + 1, run.end() - (max + 1)));
 
                     // Left interval
-                    self.runs[index as usize].length = (min - 1) - run.value;
+                    self.runs[index].length = (min - 1) - run.value;
                     return;
                 }
 
+                // Truncate the run overlapping the start of the range and keep it
                 if min > run.value {
-                    self.runs[index as usize].length = (min - 1) - run.value;
-                    index += 1;
+                    self.runs[index].length = (min - 1) - run.value;
+                    index + 1
+                }
+                else {
+                    index
                 }
-
-                si0 = Some(index);
-            },
-            SearchResult::PossibleMatch(index) => {
-                si0 = Some(index);
             },
-            _ => ()
-        }
+            SearchResult::PossibleMatch(index) => index + 1,
+            SearchResult::NoMatch => 0
+        };
+
+        // The absolute index one past the last run that lies entirely within the range
+        let end = match self.find_run(max) {
+            SearchResult::ExactMatch(index) => {
+                let run_max = self.runs[index].end();
 
-        // Update the right-most run
-        match self.find_run(max) {
-            SearchResult::ExactMatch(mut index) => {
-                let run_max = self.runs[index as usize].end();
+                // Truncate the run overlapping the end of the range and keep it
                 if run_max > max {
-                    self.runs[index as usize] = Rle16::new(max + 1, run_max - (max + 1));
-                    index = index.checked_sub(1)
-                        .unwrap_or(0);
+                    self.runs[index] = Rle16::new(max + 1, run_max - (max + 1));
+                    index
+                }
+                else {
+                    index + 1
                 }
-
-                si1 = Some(index);
-            },
-            SearchResult::PossibleMatch(index) => {
-                si1 = Some(index);                
             },
-            _ => ()
-        }
-
-        if let (Some(si0), Some(si1)) = (si0, si1) {
-            let start = si0 + 1;
-            let end = start + (si1 + 1);
+            SearchResult::PossibleMatch(index) => index + 1,
+            SearchResult::NoMatch => 0
+        };
 
-            if end < self.runs.len() {
-                self.runs.splice(start..end, iter::empty());
-            }
+        if start < end {
+            self.runs.splice(start..end, iter::empty());
         }
     }
     
@@ -300,63 +365,31 @@ impl RunContainer {
     
     /// Check if the container contains all the values in [min-max)
     pub fn contains_range(&self, range: Range<u32>) -> bool {
-        let mut count = 0;
-        let index;
+        // We always contain the empty set
+        if range.is_empty() {
+            return true;
+        }
 
         let min = range.start as u16;
         let max = (range.end - 1) as u16;
 
-        match self.binary_search(min) {
-            SearchResult::ExactMatch(i) => {
-                index = i;
-            },
-            SearchResult::PossibleMatch(i) => {
-                let v = self.runs[i];
-
-                if min - v.value > v.length {
-                    return false;
-                }
-                else {
-                    index = i;
-                }
-            },
-            SearchResult::NoMatch => {
-                return false;
-            }
+        let index = match self.binary_search(min) {
+            SearchResult::ExactMatch(i) => i,
+            SearchResult::PossibleMatch(i) if min - self.runs[i].value <= self.runs[i].length => i,
+            _ => return false
         };
 
-        for run in &self.runs[index..self.runs.len()] {
-            let stop = run.value + run.length;
-            if run.value >= max {
-                break;
-            }
-
-            if stop >= max {
-                if max > run.value {
-                    count += max - run.value;
-                }
-
+        // Follow any adjacent runs until the end of the range is covered
+        let mut end = self.runs[index].end();
+        for run in self.runs[(index + 1)..].iter() {
+            if end >= max || u32::from(run.value) != u32::from(end) + 1 {
                 break;
             }
 
-            let min_length = {
-                if stop > min { 
-                    stop - min 
-                }
-                else {
-                    0
-                }
-            };
-
-            if min_length < run.length {
-                count += min_length;
-            }
-            else {
-                count += run.length;
-            }
+            end = run.end();
         }
 
-        count >= max - min - 1
+        end >= max
     }
     
     /// The cardinality of the run container
@@ -375,6 +408,27 @@ impl RunContainer {
         card
     }
 
+    /// Get the number of values within [min-max)
+    pub fn cardinality_range(&self, range: Range<u32>) -> usize {
+        debug_assert!(is_valid_range(range.clone()));
+
+        let mut card = 0;
+        for rle in self.iter_runs() {
+            let start = u32::from(rle.value).max(range.start);
+            let end = (u32::from(rle.end()) + 1).min(range.end);
+
+            if u32::from(rle.value) >= range.end {
+                break;
+            }
+
+            if start < end {
+                card += (end - start) as usize;
+            }
+        }
+
+        card
+    }
+
     /// The number of runs in the run container
     #[inline]
     pub fn num_runs(&self) -> usize {
@@ -418,28 +472,54 @@ impl RunContainer {
         
         Some(run.value + run.length)
     }
+
+    /// Get the smallest value not in this container. Returns `None` if the container is full
+    pub fn min_excluded(&self) -> Option<u16> {
+        self.min_excluded_from(0)
+    }
+
+    /// Get the smallest value not in this container that is at least `from`. Returns `None` if every such value is present
+    pub fn min_excluded_from(&self, from: u16) -> Option<u16> {
+        // Skip the runs ending before `from`, they can't contain it
+        let start = self.runs.partition_point(|run| run.end() < from);
+
+        let mut next = u32::from(from);
+        for run in self.runs[start..].iter() {
+            if u32::from(run.value) > next {
+                break;
+            }
+
+            next = next.max(u32::from(run.end()) + 1);
+        }
+
+        if next < (1 << 16) {
+            Some(next as u16)
+        }
+        else {
+            None
+        }
+    }
     
     /// Get the rank of a value in the set. The relative position of an element in the set
+    /// 
+    /// # Remarks
+    /// Runs cover `value..=end()` so a value equal to the end of a run counts the whole run.
+    /// The sum is accumulated as a `usize` since a full container holds one more value than `u16::MAX`
     pub fn rank(&self, value: u16) -> usize {
         let mut sum = 0;
         for run in self.runs.iter() {
-            let start = run.value;
-            let length = run.length;
-            let end = start + length;
-
-            if value < end {
-                if value < start {
-                    break;
-                }
-                
-                return (sum + (value - start) + 1) as usize;
+            if value < run.value {
+                break;
             }
-            else {
-                sum += length + 1;
+
+            if value <= run.end() {
+                return sum + usize::from(value - run.value) + 1;
             }
+
+            sum += usize::from(run.length) + 1;
         }
 
-        sum as usize
+        sum
     }
 
     /// Select the element with `rank` starting the search from `start_rank`
@@ -461,33 +541,16 @@ impl RunContainer {
 
     /// Convert self into the most efficient container. Returns self if already optimal
     pub fn into_efficient_container(self) -> Container {
-        let cardinality = self.cardinality();
-        let size_as_run = RunContainer::serialized_size(self.num_runs());
-        let size_as_bitset = BitsetContainer::serialized_size();
-        let size_as_array = ArrayContainer::serialized_size(cardinality);
-        let min_size_other = size_as_array.min(size_as_bitset);
-
-        // Run is still smallest, leave as is
-        if size_as_run < min_size_other {
-            return Container::Run(self);
+        match choose_representation(self.cardinality(), self.num_runs()) {
+            ContainerKind::Run => Container::Run(self),
+            ContainerKind::Array => Container::Array(self.into()),
+            ContainerKind::Bitset => Container::Bitset(self.into())
         }
-
-        // Array is smallest, convert
-        if cardinality < DEFAULT_MAX_SIZE {
-            return Container::Array(self.into());
-        }
-
-        // Bitset is smallest, convert
-        Container::Bitset(self.into())
     }
     
     /// Iterate over the values of the run container
     pub fn iter(&self) -> Iter {
-        Iter {
-            runs: &self.runs,
-            rle_index: 0,
-            value_index: 0
-        }
+        Iter::new(&self.runs)
     }
     
     /// Iterate over the runs of the run container
@@ -503,8 +566,8 @@ impl RunContainer {
         }
 
         let mut low = 0;
-        let mut high = self.runs.len() - 1;
-        while low <= high {
+        let mut high = self.runs.len();
+        while low < high {
             let middle = (low + high) >> 1;
             let value = self.runs[middle].value;
 
@@ -512,7 +575,7 @@ impl RunContainer {
                 low = middle + 1;
             }
             else if value > key {
-                high = middle - 1;
+                high = middle;
             }
             else {
                 return SearchResult::ExactMatch(middle);
@@ -530,8 +593,8 @@ impl RunContainer {
     /// Find the run containing `key`
     fn find_run(&self, key: u16) -> SearchResult {
         let mut low = 0;
-        let mut high = self.runs.len() - 1;
-        while low <= high {
+        let mut high = self.runs.len();
+        while low < high {
             let middle = (low + high) >> 1;
             let min = self.runs[middle].value;
             let max = self.runs[middle].end();
@@ -539,7 +602,7 @@ impl RunContainer {
                 low = middle + 1;
             }
             else if key < min {
-                high = middle - 1;
+                high = middle;
             }
             else {
                 return SearchResult::ExactMatch(middle);
@@ -554,15 +617,15 @@ impl RunContainer {
         }
     }
 
-    /// Get the number of runs before `value` 
+    /// Get the number of runs ending before `value - 1`, runs ending at `value - 1` are adjacent and not counted
     fn rle_count_less(&self, value: u32) -> usize {
         if self.runs.is_empty() {
             return 0;
         }
 
         let mut low = 0;
-        let mut high = self.runs.len() - 1;
-        while low <= high {
+        let mut high = self.runs.len();
+        while low < high {
             let middle = (low + high) >> 1;
             let min_value = u32::from(self.runs[middle].value);
             let max_value = min_value + u32::from(self.runs[middle].length);
@@ -571,7 +634,7 @@ impl RunContainer {
                 low = middle + 1;
             }
             else if value < min_value {
-                high = middle - 1;
+                high = middle;
             }
             else {
                 return middle;
@@ -581,15 +644,15 @@ impl RunContainer {
         low
     }
 
-    /// Get the number of runs after `value`
+    /// Get the number of runs starting after `value + 1`, runs starting at `value + 1` are adjacent and not counted
     fn rle_count_greater(&self, value: u32) -> usize {
         if self.runs.is_empty() {
             return 0;
         }
 
         let mut low = 0;
-        let mut high = self.runs.len() - 1;
-        while low <= high {
+        let mut high = self.runs.len();
+        while low < high {
             let middle = (low + high) >> 1;
             let min_value = u32::from(self.runs[middle].value);
             let max_value = min_value + u32::from(self.runs[middle].length);
@@ -598,7 +661,7 @@ impl RunContainer {
                 low = middle + 1;
             }
             else if value + 1 < min_value {
-                high = middle - 1;
+                high = middle;
             }
             else {
                 return self.runs.len() - (middle + 1);
@@ -633,6 +696,9 @@ impl RunContainer {
     }
 
     /// Deserialize a run container from the provided buffer
+    /// 
+    /// # Returns
+    /// The container or an `InvalidData` error if the runs are unsorted, overlapping or exceed the container
     #[cfg(target_endian = "little")]
     pub fn deserialize<R: Read>(buf: &mut R) -> io::Result<Self> {
         let mut bytes = [0; 2];
@@ -641,19 +707,45 @@ impl RunContainer {
         let num_runs    = u16::from_le_bytes(bytes) as usize;
         let mut result  = Self::with_capacity(num_runs);
 
+        // Size the runs upfront so the container's length always matches what was read
+        result.runs.resize(num_runs, Rle16::new(0, 0));
+
         unsafe {
             let num_bytes = num_runs * mem::size_of::<Rle16>();
             let ptr = result.as_mut_ptr() as *mut u8;
             let slice = slice::from_raw_parts_mut(ptr, num_bytes);
 
-            let num_read = buf.read(slice)?;
-            if num_read != num_bytes {
-                return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
-            }
+            buf.read_exact(slice)?;
+        }
+
+        if !result.has_valid_runs() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData, 
+                "Run container runs are unsorted, overlapping or out of bounds"
+            ));
         }
 
         Ok(result)
     }
+
+    /// Check that the runs are sorted, don't overlap and end within the container
+    fn has_valid_runs(&self) -> bool {
+        // The smallest value the next run is allowed to start at
+        let mut next_start = 0;
+
+        for run in self.runs.iter() {
+            let start = u32::from(run.value);
+            let end = start + u32::from(run.length);
+
+            if start < next_start || end > u32::from(u16::MAX) {
+                return false;
+            }
+
+            next_start = end + 1;
+        }
+
+        true
+    }
 }
 
 impl From<ArrayContainer> for RunContainer {
@@ -838,6 +930,11 @@ impl SetOr<Self> for RunContainer {
         Container::Run(result)
     }
     
+    #[inline]
+    fn or_cardinality(&self, other: &Self) -> usize {
+        self.cardinality() + other.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_or(mut self, other: &Self) -> Container {
         // Self contains the final result
         if self.is_full() || other.is_empty() {
@@ -980,6 +1077,11 @@ impl SetOr<ArrayContainer> for RunContainer {
         Container::Run(result)
     }
 
+    #[inline]
+    fn or_cardinality(&self, other: &ArrayContainer) -> usize {
+        self.cardinality() + other.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_or(mut self, other: &ArrayContainer) -> Container {
         if self.is_full() {
             return Container::Run(self);
@@ -1059,7 +1161,7 @@ impl SetOr<BitsetContainer> for RunContainer {
 
         for rle in self.iter_runs() {
             let min = u32::from(rle.value);
-            let max = u32::from(rle.end() + 1);
+            let max = u32::from(rle.end()) + 1;
 
             result.set_range(min..max);
         }
@@ -1067,6 +1169,11 @@ impl SetOr<BitsetContainer> for RunContainer {
         Container::Bitset(result)
     }
 
+    #[inline]
+    fn or_cardinality(&self, other: &BitsetContainer) -> usize {
+        self.cardinality() + other.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_or(self, other: &BitsetContainer) -> Container {
         if self.is_full() {
             return Container::Run(self)
@@ -1097,10 +1204,11 @@ impl SetAnd<Self> for RunContainer {
         let mut i0 = 0;
         let mut i1 = 0;
 
-        let mut start0 = self.runs[i0].value;
-        let mut start1 = other.runs[i1].value;
-        let mut end0 = self.runs[i0].end() + 1;
-        let mut end1 = other.runs[i1].end() + 1;
+        // Work in u32 so the exclusive end of a run reaching `u16::MAX` doesn't overflow
+        let mut start0 = u32::from(self.runs[i0].value);
+        let mut start1 = u32::from(other.runs[i1].value);
+        let mut end0 = u32::from(self.runs[i0].end()) + 1;
+        let mut end1 = u32::from(other.runs[i1].end()) + 1;
 
         while i0 < self.num_runs() && i1 < other.num_runs() {
             // Runs don't overlap, advance either or
@@ -1108,16 +1216,16 @@ impl SetAnd<Self> for RunContainer {
                 i0 += 1;
 
                 if i0 < self.num_runs() {
-                    start0 = self.runs[i0].value;
-                    end0 = self.runs[i0].end() + 1;
+                    start0 = u32::from(self.runs[i0].value);
+                    end0 = u32::from(self.runs[i0].end()) + 1;
                 }
             }
             else if end1 <= start0 {
                 i1 += 1;
 
                 if i1 < other.num_runs() {
-                    start1 = other.runs[i1].value;
-                    end1 = other.runs[i1].end() + 1;
+                    start1 = u32::from(other.runs[i1].value);
+                    end1 = u32::from(other.runs[i1].end()) + 1;
                 }
             }
             // Runs overlap, try to merge if possible
@@ -1131,13 +1239,13 @@ impl SetAnd<Self> for RunContainer {
                     i1 += 1;
 
                     if i0 < self.num_runs() {
-                        start0 = self.runs[i0].value;
-                        end0 = self.runs[i0].end() + 1;
+                        start0 = u32::from(self.runs[i0].value);
+                        end0 = u32::from(self.runs[i0].end()) + 1;
                     }
 
                     if i1 < other.num_runs() {
-                        start1 = other.runs[i1].value;
-                        end1 = other.runs[i1].end() + 1;
+                        start1 = u32::from(other.runs[i1].value);
+                        end1 = u32::from(other.runs[i1].end()) + 1;
                     }
                 }
                 else if end0 < end1 {
@@ -1145,8 +1253,8 @@ impl SetAnd<Self> for RunContainer {
 
                     i0 += 1;
                     if i0 < self.num_runs() {
-                        start0 = self.runs[i0].value;
-                        end0 = self.runs[i0].end() + 1;
+                        start0 = u32::from(self.runs[i0].value);
+                        end0 = u32::from(self.runs[i0].end()) + 1;
                     }
                 }
                 else {
@@ -1154,12 +1262,12 @@ impl SetAnd<Self> for RunContainer {
 
                     i1 += 1;
                     if i1 < other.num_runs() {
-                        start1 = other.runs[i1].value;
-                        end1 = other.runs[i1].end() + 1;
+                        start1 = u32::from(other.runs[i1].value);
+                        end1 = u32::from(other.runs[i1].end()) + 1;
                     }
                 }
 
-                let run = Rle16::new(last_start, first_end - last_start - 1);
+                let run = Rle16::new(last_start as u16, (first_end - last_start - 1) as u16);
                 result.runs.push(run);
             }
         }
@@ -1181,10 +1289,11 @@ impl SetAnd<Self> for RunContainer {
         let mut i0 = 0;
         let mut i1 = 0;
 
-        let mut start0 = self.runs[i0].value;
-        let mut start1 = other.runs[i1].value;
-        let mut end0 = self.runs[i0].end() + 1;
-        let mut end1 = other.runs[i1].end() + 1;
+        // Work in u32 so the exclusive end of a run reaching `u16::MAX` doesn't overflow
+        let mut start0 = u32::from(self.runs[i0].value);
+        let mut start1 = u32::from(other.runs[i1].value);
+        let mut end0 = u32::from(self.runs[i0].end()) + 1;
+        let mut end1 = u32::from(other.runs[i1].end()) + 1;
 
         while i0 < self.num_runs() && i1 < other.num_runs() {
             // Runs don't overlap, advance either or
@@ -1192,16 +1301,16 @@ impl SetAnd<Self> for RunContainer {
                 i0 += 1;
 
                 if i0 < self.num_runs() {
-                    start0 = self.runs[i0].value;
-                    end0 = self.runs[i0].end() + 1;
+                    start0 = u32::from(self.runs[i0].value);
+                    end0 = u32::from(self.runs[i0].end()) + 1;
                 }
             }
             else if end1 <= start0 {
                 i1 += 1;
 
                 if i1 < other.num_runs() {
-                    start1 = other.runs[i1].value;
-                    end1 = other.runs[i1].end() + 1;
+                    start1 = u32::from(other.runs[i1].value);
+                    end1 = u32::from(other.runs[i1].end()) + 1;
                 }
             }
             // Runs overlap, try to merge if possible
@@ -1215,13 +1324,13 @@ impl SetAnd<Self> for RunContainer {
                     i1 += 1;
 
                     if i0 < self.num_runs() {
-                        start0 = self.runs[i0].value;
-                        end0 = self.runs[i0].end() + 1;
+                        start0 = u32::from(self.runs[i0].value);
+                        end0 = u32::from(self.runs[i0].end()) + 1;
                     }
 
                     if i1 < other.num_runs() {
-                        start1 = other.runs[i1].value;
-                        end1 = other.runs[i1].end() + 1;
+                        start1 = u32::from(other.runs[i1].value);
+                        end1 = u32::from(other.runs[i1].end()) + 1;
                     }
                 }
                 else if end0 < end1 {
@@ -1229,8 +1338,8 @@ impl SetAnd<Self> for RunContainer {
 
                     i0 += 1;
                     if i0 < self.num_runs() {
-                        start0 = self.runs[i0].value;
-                        end0 = self.runs[i0].end() + 1;
+                        start0 = u32::from(self.runs[i0].value);
+                        end0 = u32::from(self.runs[i0].end()) + 1;
                     }
                 }
                 else {
@@ -1238,8 +1347,8 @@ impl SetAnd<Self> for RunContainer {
 
                     i1 += 1;
                     if i1 < other.num_runs() {
-                        start1 = other.runs[i1].value;
-                        end1 = other.runs[i1].end() + 1;
+                        start1 = u32::from(other.runs[i1].value);
+                        end1 = u32::from(other.runs[i1].end()) + 1;
                     }
                 }
 
@@ -1284,6 +1393,11 @@ impl SetAnd<ArrayContainer> for RunContainer {
                 rle = self.runs[rle_index];
             }
 
+            // All runs have been exhausted, no remaining values can intersect
+            if rle_index == self.runs.len() {
+                break;
+            }
+
             if rle.value > value {
                 array_index = array_ops::advance_until(
                     &other,
@@ -1328,6 +1442,11 @@ impl SetAnd<ArrayContainer> for RunContainer {
                 rle = self.runs[rle_index];
             }
 
+            // All runs have been exhausted, no remaining values can intersect
+            if rle_index == self.runs.len() {
+                break;
+            }
+
             if rle.value > value {
                 array_index = array_ops::advance_until(
                     &other,
@@ -1428,43 +1547,39 @@ impl SetAndNot<Self> for RunContainer {
         let mut i_a = 0;
         let mut i_b = 0;
 
+        // Exclusive ends are tracked as u32 since a run may end at `u16::MAX`
         let (mut start_a, mut start_b, mut end_a, mut end_b) = {
-            let run_a = self.runs[i_a];
-            let run_b = other.runs[i_b];
-
-            (
-                run_a.value,
-                run_b.value,
-                run_a.end() + 1,
-                run_b.end() + 1
-            )
+            let run_a = self.runs[i_a].into_range();
+            let run_b = other.runs[i_b].into_range();
+
+            (run_a.start, run_b.start, run_a.end, run_b.end)
         };
 
         while i_a < self.runs.len() && i_b < other.runs.len() {
             if end_a <= start_b {
                 result.runs.push(
-                    Rle16::new(start_a, end_a - start_a - 1)
+                    Rle16::new(start_a as u16, (end_a - start_a - 1) as u16)
                 );
 
                 i_a += 1;
                 if i_a < self.runs.len() {
-                    let run = self.runs[i_a];
-                    start_a = run.value;
-                    end_a = run.end() + 1;
+                    let run = self.runs[i_a].into_range();
+                    start_a = run.start;
+                    end_a = run.end;
                 }
             }
             else if end_b <= start_a {
                 i_b += 1;
                 if i_b < other.runs.len() {
-                    let run = other.runs[i_b];
-                    start_b = run.value;
-                    end_b = run.end() + 1;
+                    let run = other.runs[i_b].into_range();
+                    start_b = run.start;
+                    end_b = run.end;
                 }
             }
             else {
                 if start_a < start_b {
                     result.runs.push(
-                        Rle16::new(start_a, start_b - start_a - 1)
+                        Rle16::new(start_a as u16, (start_b - start_a - 1) as u16)
                     );
                 }
                 
@@ -1474,16 +1589,16 @@ impl SetAndNot<Self> for RunContainer {
                 else {
                     i_a += 1;
                     if i_a < self.runs.len() {
-                        let run = self.runs[i_a];
-                        start_a = run.value;
-                        end_a = run.end() + 1;
+                        let run = self.runs[i_a].into_range();
+                        start_a = run.start;
+                        end_a = run.end;
                     }
                 }
             }
         }
 
         if i_a < self.runs.len() {
-            result.runs.push(Rle16::new(start_a, end_a - start_a - 1));
+            result.runs.push(Rle16::new(start_a as u16, (end_a - start_a - 1) as u16));
 
             i_a += 1;
             if i_a < self.runs.len() {
@@ -1494,6 +1609,11 @@ impl SetAndNot<Self> for RunContainer {
         result.into_efficient_container()
     }
 
+    #[inline]
+    fn and_not_cardinality(&self, other: &Self) -> usize {
+        self.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_and_not(self, other: &Self) -> Container {
         SetAndNot::and_not(&self, other)
     }
@@ -1510,62 +1630,29 @@ impl SetAndNot<ArrayContainer> for RunContainer {
                 return Container::Run(self.clone());
             }
 
-            let mut result = RunContainer::with_capacity(cardinality + other.cardinality());
-            let mut rle0_pos = 0;
-            let mut rle1_pos = 0;
-
-            let rle = self.runs[rle0_pos];
-            let mut rle0_start = rle.value;
-            let mut rle0_end = rle.end();
-            let mut rle1_start = other[rle1_pos];
+            let mut result = RunContainer::with_capacity(self.num_runs() + other.cardinality());
+            let mut index = 0;
 
-            while rle0_pos < self.num_runs() && rle1_pos < other.cardinality() {
-                if rle0_end == rle1_start {
-                    result.runs.push(Rle16::new(rle0_start, rle0_end - rle0_start - 1));
-                    rle0_pos += 1;
+            for run in self.runs.iter() {
+                // Computed as u32 so that runs ending at 65535 don't overflow
+                let mut start = u32::from(run.value);
+                let end = u32::from(run.end()) + 1;
 
-                    if rle0_pos < self.num_runs() {
-                        let r = self.runs[rle0_pos];
+                index = array_ops::advance_until(other, index, run.value);
 
-                        rle0_start = r.value;
-                        rle0_end = r.end();
+                // Split the run around every value in the array contained within it
+                while index < other.cardinality() && u32::from(other[index]) < end {
+                    let value = u32::from(other[index]);
+                    if start < value {
+                        result.runs.push(Rle16::new(start as u16, (value - start - 1) as u16));
                     }
-                }
-                else if rle1_start < rle0_start {
-                    rle1_pos += 1;
 
-                    if rle1_pos < other.cardinality() {
-                        rle1_start = other[rle1_pos];
-                    }
-                }
-                else {
-                    if rle0_start < rle1_start {
-                        result.runs.push(Rle16::new(rle0_start, rle1_start - rle0_start - 1));
-                    }
-
-                    if rle1_start + 1 < rle0_end {
-                        rle0_start = rle1_start + 1;
-                    }
-                    else {
-                        rle0_pos += 1;
-
-                        if rle0_pos < self.num_runs() {
-                            let r = self.runs[rle0_pos];
-
-                            rle0_start = r.value;
-                            rle0_end = r.end();
-                        }
-                    }
+                    start = value + 1;
+                    index += 1;
                 }
 
-                if rle0_pos < self.num_runs() {
-                    result.runs.push(Rle16::new(rle0_start, rle0_end - rle0_start - 1));
-                    rle0_pos += 1;
-
-                    if rle0_pos < self.num_runs() {
-                        let len = self.num_runs() - rle0_pos;
-                        result.copy_from_slice(&self.runs[rle0_pos..len]);
-                    }
+                if start < end {
+                    result.runs.push(Rle16::new(start as u16, (end - start - 1) as u16));
                 }
             }
 
@@ -1577,54 +1664,31 @@ impl SetAndNot<ArrayContainer> for RunContainer {
             
             let mut index = 0;
             for run in self.runs.iter() {
-                let start = run.value;
-                let end = run.end() + 1;
+                let start = u32::from(run.value);
+                let end = u32::from(run.end()) + 1;
 
-                index = array_ops::advance_until(&other, index, start);
+                index = array_ops::advance_until(other, index, run.value);
 
-                if index >= other.cardinality() {
-                    for i in start..end {
-                        array.push(i as u16);
-                    }
-                }
-                else {
-                    let mut next = other[index];
-                    if next >= end {
-                        for i in start..end {
-                            array.push(i as u16);
-                        }
-
-                        index -= 1;
+                for value in start..end {
+                    if index < other.cardinality() && u32::from(other[index]) == value {
+                        index += 1;
                     }
                     else {
-                        for i in start..end {
-                            if i != next {
-                                array.push(i as u16);
-                            }
-                            else {
-                                next = {
-                                    if index + 1 >= other.cardinality() {
-                                        0
-                                    }
-                                    else {
-                                        index += 1;
-                                        
-                                        other[index]
-                                    }
-                                };
-                            }
-                        }
-
-                        index -= 1;
+                        array.push(value as u16);
                     }
                 }
             }
             
-            return Container::Array(array);
+            return Container::Array(array).optimize();
         }
 
         let bitset: BitsetContainer = self.into();
-        bitset.inplace_and_not(other)
+        bitset.inplace_and_not(other).optimize()
+    }
+
+    #[inline]
+    fn and_not_cardinality(&self, other: &ArrayContainer) -> usize {
+        self.cardinality() - SetAnd::and_cardinality(self, other)
     }
 
     fn inplace_and_not(self, other: &ArrayContainer) -> Container {
@@ -1636,18 +1700,18 @@ impl SetAndNot<BitsetContainer> for RunContainer {
     fn and_not(&self, other: &BitsetContainer) -> Container {
         let cardinality = self.cardinality();
         
-        // Result is an array
+        // Result is an array, it may still be contiguous enough to be stored as runs
         if cardinality <= DEFAULT_MAX_SIZE {
             let mut array = ArrayContainer::with_capacity(cardinality);
             for run in self.runs.iter() {
-                for value in run.value..(run.end() + 1) {
+                for value in run.value..=run.end() {
                     if !other.get(value) {
                         array.push(value);
                     }
                 }
             }
 
-            Container::Array(array)
+            Container::Array(array).optimize()
         }
         // Result may be a bitset
         else {
@@ -1666,17 +1730,16 @@ impl SetAndNot<BitsetContainer> for RunContainer {
 
             bitset.unset_range(last_pos..(1 << 16));
 
-            // Result is not a bitset, convert to array
-            if bitset.cardinality() <= DEFAULT_MAX_SIZE {
-                Container::Array(bitset.into())
-            }
-            // Result is a bitset
-            else {
-                Container::Bitset(bitset)
-            }
+            // Subtracting from runs often leaves the result contiguous, consider every representation
+            Container::Bitset(bitset).optimize()
         }
     }
 
+    #[inline]
+    fn and_not_cardinality(&self, other: &BitsetContainer) -> usize {
+        self.cardinality() - SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_and_not(self, other: &BitsetContainer) -> Container {
         SetAndNot::and_not(&self, other)
     }
@@ -1732,6 +1795,11 @@ impl SetXor<Self> for RunContainer {
         Container::Run(result)
     }
 
+    #[inline]
+    fn xor_cardinality(&self, other: &Self) -> usize {
+        self.cardinality() + other.cardinality() - 2 * SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_xor(self, other: &Self) -> Container {
         SetXor::xor(&self, other)
     }
@@ -1777,6 +1845,11 @@ impl SetXor<ArrayContainer> for RunContainer {
         result.into_efficient_container()
     }
 
+    #[inline]
+    fn xor_cardinality(&self, other: &ArrayContainer) -> usize {
+        self.cardinality() + other.cardinality() - 2 * SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_xor(self, other: &ArrayContainer) -> Container {
         SetXor::xor(&self, other)
     }
@@ -1797,6 +1870,11 @@ impl SetXor<BitsetContainer> for RunContainer {
         }
     }
 
+    #[inline]
+    fn xor_cardinality(&self, other: &BitsetContainer) -> usize {
+        self.cardinality() + other.cardinality() - 2 * SetAnd::and_cardinality(self, other)
+    }
+
     fn inplace_xor(self, other: &BitsetContainer) -> Container {
         SetXor::xor(&self, other)
     }
@@ -1811,26 +1889,23 @@ impl Subset<Self> for RunContainer {
         let mut i_0 = 0;
         let mut i_1 = 0;
 
+        // Both endpoints are inclusive, see `Rle16::end`
         while i_0 < self.runs.len() && i_1 < other.runs.len() {
             let start_0 = self.runs[i_0].value;
             let start_1 = other.runs[i_1].value;
-            let stop_0 = start_0 + self.runs[i_0].length;
-            let stop_1 = start_1 + other.runs[i_1].length;
+            let end_0 = self.runs[i_0].end();
+            let end_1 = other.runs[i_1].end();
 
-            if start_0 < start_1 {
+            if start_0 > end_1 {
+                // The run in other lies entirely before the run in self
+                i_1 += 1;
+            }
+            else if start_0 < start_1 || end_0 > end_1 {
+                // The run in self overlaps a value outside of the run in other
                 return false;
             }
             else {
-                if stop_0 < stop_1 {
-                    i_0 += 1;
-                }
-                else if stop_0 == stop_1 {
-                    i_0 += 1;
-                    i_1 += 1;
-                }
-                else {
-                    i_1 += 1;
-                }
+                i_0 += 1;
             }
         }
 
@@ -1893,179 +1968,219 @@ impl SetNot for RunContainer {
             return Container::Run(self.clone());
         }
 
+        // Flipping a range adds at most one run
         let mut result = RunContainer::with_capacity(self.num_runs() + 1);
-        let mut k = 0;
-        while k < self.num_runs() && u32::from(self.runs[k].value) < range.start {
-            result.runs[k] = self.runs[k];
+        result.runs.extend_from_slice(&self.runs);
 
-            k += 1;
+        SetNot::inplace_not(result, range)
+    }
+
+    fn inplace_not(mut self, range: Range<u32>) -> Container {
+        if range.is_empty() {
+            return Container::Run(self);
         }
 
-        let min = range.start as u16;
-        let max = (range.end - 1) as u16;
+        self.coalesce();
 
-        append_exclusive(&mut result.runs, min, max - min);
+        let start = range.start;
+        let end = range.end;
 
-        while k < self.num_runs() {
-            let rle = self.runs[k];
+        // The runs overlapping or adjacent to the range, every other run is left untouched
+        let lo = self.runs.iter()
+            .position(|run| run.into_range().end >= start)
+            .unwrap_or(self.runs.len());
 
-            append_exclusive(&mut result.runs, rle.value, rle.length);
+        let mut hi = lo + self.runs[lo..].iter()
+            .take_while(|run| u32::from(run.value) <= end)
+            .count();
 
-            k += 1;
-        }
+        // The flipped runs are written back over the affected runs in order. At most one
+        // run is written for each run read before the write position catches up, so unread runs
+        // are never overwritten. Only the final run can need a new slot
+        let mut write = lo;
+        let mut emit = |runs: &mut Vec<Rle16>, hi: &mut usize, piece: Range<u32>| {
+            if write > lo && runs[write - 1].into_range().end == piece.start {
+                let previous = &mut runs[write - 1];
+                previous.length = (piece.end - 1 - u32::from(previous.value)) as u16;
+                return;
+            }
 
-        result.into_efficient_container()
-    }
+            let run = Rle16::new(piece.start as u16, (piece.end - piece.start - 1) as u16);
+            if write < *hi {
+                runs[write] = run;
+            }
+            else {
+                runs.insert(write, run);
+                *hi += 1;
+            }
 
-    fn inplace_not(mut self, range: Range<u32>) -> Container {
-        // Check to see if the result will fit in the currently allocated container
-        // if not fallback to the allocating version
-        if self.runs.capacity() == self.runs.len() {
-            let last_before_range = {
-                if range.start > 0 { 
-                    self.contains((range.start - 1) as u16) 
-                }
-                else { 
-                    false
-                }
-            };
+            write += 1;
+        };
 
-            let first_in_range = self.contains(range.start as u16);
+        let mut cursor = start;
+        let mut suffix = None;
 
-            if last_before_range == first_in_range {
-                let last_in_range = self.contains((range.end - 1) as u16);
-                let first_after_range = self.contains(range.end as u16);
+        let mut read = lo;
+        while read < hi {
+            let run = self.runs[read].into_range();
 
-                // Contents won't fit in the current allocation
-                if last_in_range == first_after_range {
-                    return SetNot::not(&self, range);
-                }
+            // Values of the run before the range are kept
+            if run.start < start {
+                emit(&mut self.runs, &mut hi, run.start..start);
             }
-        }
-
-        // Perform the not in place with the current allocation
-        let mut k = 0;
-        while k < self.num_runs() && u32::from(self.runs[k].value) < range.start {
-            k += 1;
-        }
 
-        let mut buffered = Rle16::new(0, 0);
-        let mut next = buffered;
-        if k < self.num_runs() {
-            buffered = self.runs[k];
-        }
+            // The gap between the previous run and this one is flipped on
+            let clipped = run.start.max(start)..run.end.min(end);
+            if clipped.start > cursor {
+                emit(&mut self.runs, &mut hi, cursor..clipped.start);
+            }
 
-        append_exclusive(&mut self.runs, range.start as u16, (range.end - range.start - 1) as u16);
+            cursor = clipped.end;
 
-        while k < self.num_runs() {
-            if k + 1 < self.num_runs() {
-                next = self.runs[k + 1];
+            // Values of the run after the range are kept
+            if run.end > end {
+                suffix = Some(end.max(run.start)..run.end);
             }
 
-            append_exclusive(&mut self.runs, buffered.value, buffered.length);
+            read += 1;
+        }
+
+        if cursor < end {
+            emit(&mut self.runs, &mut hi, cursor..end);
+        }
 
-            buffered = next;
-            k += 1;
+        if let Some(suffix) = suffix {
+            emit(&mut self.runs, &mut hi, suffix);
         }
 
-        self.runs.truncate(k);
+        self.runs.drain(write..hi);
         self.into_efficient_container()
     }
 }
 
 /// An iterator over the values of a run structure
 pub struct Iter<'a> {
-    /// The rle encoded words we're reading from
+    /// The rle encoded words that still have values to read
     runs: &'a [Rle16],
 
-    /// The index of the rle word we're reading
-    rle_index: usize,
+    /// The number of values already read from the front of the first run
+    front: u16,
+
+    /// The number of values already read from the back of the last run
+    back: u16,
 
-    /// The index of the last read value in the rle
-    value_index: u16
+    /// The number of values left to read
+    remaining: usize
+}
+
+impl<'a> Iter<'a> {
+    /// Create an iterator over the values of `runs`
+    fn new(runs: &'a [Rle16]) -> Self {
+        let remaining = runs.iter()
+            .map(|run| run.length as usize + 1)
+            .sum();
+
+        Self {
+            runs,
+            front: 0,
+            back: 0,
+            remaining
+        }
+    }
 }
 
 impl<'a> Iterator for Iter<'a> {
     type Item = u16;
     
     fn next(&mut self) -> Option<Self::Item> {
-        if self.rle_index < self.runs.len() {
-            let rle = self.runs[self.rle_index];
-            
-            if self.value_index <= rle.length {
-                // Extract value
-                let value = rle.value + self.value_index;
-
-                // Bump index
-                let next = self.value_index.checked_add(1);
+        if self.remaining == 0 {
+            return None;
+        }
 
-                // Increment run if necessary
-                if next.is_none() || next.unwrap() > rle.length {
-                    self.rle_index += 1;
-                    self.value_index = 0;
-                }
-                else {
-                    self.value_index = next.unwrap();
-                }
+        let rle = self.runs[0];
+        let value = rle.value + self.front;
 
-                Some(value)
-            }
-            else {
-                None
-            }
+        // Move to the next run once the current one is exhausted
+        if self.front == rle.length {
+            self.runs = &self.runs[1..];
+            self.front = 0;
         }
         else {
-            None
+            self.front += 1;
         }
-    }
-}
-
-fn append_exclusive(runs: &mut Vec<Rle16>, start: u16, length: u16) {
-    let is_empty = runs.is_empty();
-    let old_end = runs.last_mut()
-        .and_then(|x| Some(x.end().saturating_add(1)));
 
-    let last_run = runs.last_mut();
+        self.remaining -= 1;
 
-    if is_empty || (old_end.is_some() && start > old_end.unwrap()) {
-        runs.push(Rle16::new(start, length));
-        return;
+        Some(value)
     }
 
-    let last_run = last_run.unwrap();
-    let old_end = old_end.unwrap();
-
-    if old_end == start {
-        last_run.length += length + 1;
-        return;
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
     }
+}
 
-    let new_end = start + length + 1;
-    if start == last_run.value {
-        if new_end < old_end {
-            *last_run = Rle16::new(new_end, old_end - new_end - 1);
-            return;
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
         }
-        else if new_end > old_end {
-            *last_run = Rle16::new(old_end, new_end - old_end - 1);
-            return;
+
+        let last = self.runs.len() - 1;
+        let rle = self.runs[last];
+        let value = rle.end() - self.back;
+
+        // Move to the previous run once the current one is exhausted
+        if self.back == rle.length {
+            self.runs = &self.runs[..last];
+            self.back = 0;
         }
         else {
-            runs.pop();
+            self.back += 1;
+        }
+
+        self.remaining -= 1;
+
+        Some(value)
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> { }
+
+fn append_exclusive(runs: &mut Vec<Rle16>, start: u16, length: u16) {
+    // Exclusive ends are tracked as u32 since a run may end at `u16::MAX`
+    let start = u32::from(start);
+    let new_end = start + u32::from(length) + 1;
+
+    let (old_start, old_end) = match runs.last() {
+        Some(run) if start <= u32::from(run.end()) + 1 => {
+            let range = run.into_range();
+            (range.start, range.end)
+        },
+        _ => {
+            runs.push(Rle16::new(start as u16, length));
             return;
         }
+    };
+
+    let to_run = |start: u32, end: u32| Rle16::new(start as u16, (end - start - 1) as u16);
+
+    // The new run is adjacent to the last, merge them
+    runs.pop();
+    if old_end == start {
+        runs.push(to_run(old_start, new_end));
+        return;
     }
 
-    // Checked version of `start - last_run.value - 1`
-    last_run.length = start
-        .saturating_sub(last_run.value)
-        .saturating_sub(1);
+    // The runs overlap, keep the values in exactly one of them
+    if old_start < start {
+        runs.push(to_run(old_start, start));
+    }
 
     if new_end < old_end {
-        runs.push(Rle16::new(new_end, old_end - new_end - 1));
+        runs.push(to_run(new_end, old_end));
     }
     else if new_end > old_end {
-        runs.push(Rle16::new(old_end, new_end - old_end - 1));
+        runs.push(to_run(old_end, new_end));
     }
 }
 
@@ -2077,16 +2192,15 @@ fn append(runs: &mut Vec<Rle16>, run: Rle16, previous_run: &mut Rle16) {
     let prev_end = previous_run.end();
 
     // Add a new run
-    if run.value > prev_end + 1 {
+    if u32::from(run.value) > u32::from(prev_end) + 1 {
         runs.push(run);
 
         *previous_run = run;
     }
     // Merge runs
     else {
-        let new_end = run.value + run.length + 1;
-        if new_end > prev_end {
-            previous_run.length = new_end - 1 - previous_run.value;
+        if run.end() > prev_end {
+            previous_run.length = run.end() - previous_run.value;
 
             let len = runs.len();
             runs[len - 1] = *previous_run;
@@ -2095,14 +2209,15 @@ fn append(runs: &mut Vec<Rle16>, run: Rle16, previous_run: &mut Rle16) {
 }
 
 fn append_value(runs: &mut Vec<Rle16>, value: u16, prev_rle: &mut Rle16) {
-    let prev_end = prev_rle.end();
-    if value > prev_end + 1 {
+    // Widened since the previous run may end at `u16::MAX`
+    let prev_end = u32::from(prev_rle.end());
+    if u32::from(value) > prev_end + 1 {
         let rle = Rle16::new(value, 0);
         runs.push(rle);
 
         *prev_rle = rle;
     }
-    else if value == prev_end + 1 {
+    else if u32::from(value) == prev_end + 1 {
         prev_rle.length += 1;
 
         let len = runs.len();
@@ -2114,6 +2229,7 @@ fn append_value(runs: &mut Vec<Rle16>, value: u16, prev_rle: &mut Rle16) {
 mod test {
     use crate::container::*;
     use crate::test::*;
+    use super::Rle16;
 
     impl TestShim<u16> for RunContainer {
         fn from_data(data: &[u16]) -> Self {
@@ -2135,6 +2251,112 @@ mod test {
         }
     }
 
+    #[test]
+    fn and_not_run_output() {
+        let mut run = RunContainer::new();
+        run.add_range(0..30_000);
+
+        // Removing a few small ranges leaves a handful of long runs
+        let mut bitset = BitsetContainer::new();
+        bitset.set_range(10_000..10_010);
+        bitset.set_range(20_000..20_500);
+        bitset.set_range(40_000..50_000);
+
+        let result = SetAndNot::and_not(&run, &bitset);
+        match &result {
+            Container::Run(c) => assert_eq!(c.num_runs(), 3),
+            _ => panic!("Expected a run container")
+        }
+
+        assert_eq!(result.cardinality(), 30_000 - 510);
+        assert!(result.iter().eq((0..10_000).chain(10_010..20_000).chain(20_500..30_000)));
+
+        // Small contiguous results are also stored as runs
+        let mut run = RunContainer::new();
+        run.add_range(0..4_000);
+
+        let result = SetAndNot::and_not(&run, &bitset);
+        assert!(result.is_run());
+        assert_eq!(result.cardinality(), 4_000);
+
+        let array = ArrayContainer::from_data(&[100, 2_000]);
+        let result = SetAndNot::and_not(&run, &array);
+        assert!(result.is_run());
+        assert_eq!(result.cardinality(), 3_998);
+    }
+
+    #[test]
+    fn iter() {
+        let runs: Vec<u16> = (0..10).chain(100..1_000).chain(65_000..=65_535).collect();
+        let a = RunContainer::from_data(&runs);
+
+        // Forward
+        let mut iter = a.iter();
+        assert_eq!(iter.len(), runs.len());
+        for (i, expected) in runs.iter().enumerate() {
+            assert_eq!(iter.next(), Some(*expected));
+            assert_eq!(iter.len(), runs.len() - i - 1);
+        }
+
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.len(), 0);
+
+        // Reverse
+        assert!(a.iter().rev().eq(runs.iter().rev().cloned()));
+
+        // Alternating ends meet in the middle without repeating values
+        let mut iter = a.iter();
+        let mut found = Vec::new();
+        let mut back = Vec::new();
+        while let Some(value) = iter.next() {
+            found.push(value);
+
+            if let Some(value) = iter.next_back() {
+                back.push(value);
+            }
+        }
+
+        found.extend(back.iter().rev());
+        assert_eq!(found, runs);
+
+        // A single full run
+        let full = RunContainer::full();
+        assert_eq!(full.iter().len(), 1 << 16);
+        assert_eq!(full.iter().next_back(), Some(u16::MAX));
+        assert!(full.iter().rev().eq((0..=u16::MAX).rev()));
+
+        assert_eq!(RunContainer::new().iter().len(), 0);
+        assert_eq!(RunContainer::new().iter().next_back(), None);
+    }
+
+    #[test]
+    fn coalesce() {
+        let mut a = RunContainer::new();
+        a.runs = vec![
+            Rle16::new(0, 4),
+            Rle16::new(5, 4),
+            Rle16::new(10, 0),
+            Rle16::new(20, 9),
+            Rle16::new(30, 9),
+            Rle16::new(40, 0),
+            Rle16::new(65000, 535)
+        ];
+
+        let expected: Vec<u16> = a.iter().collect();
+        let cardinality = a.cardinality();
+
+        a.coalesce();
+
+        assert_eq!(a.num_runs(), 3);
+        assert_eq!(a.cardinality(), cardinality);
+        assert!(a.iter().eq(expected.iter().cloned()));
+        
+        // Already coalesced runs are left alone
+        a.shrink_to_fit();
+        assert_eq!(a.num_runs(), 3);
+        assert!(a.iter().eq(expected.iter().cloned()));
+    }
+
     #[test]
     fn add() {
         let mut a = RunContainer::new();
@@ -2149,6 +2371,75 @@ mod test {
         }
     }
 
+    #[test]
+    fn add_unordered() {
+        let mut a = RunContainer::new();
+        for value in [50, 10, 11, 9, 100, 0].iter() {
+            a.add(*value);
+        }
+
+        let found: Vec<u16> = a.iter().collect();
+        assert_eq!(found, vec![0, 9, 10, 11, 50, 100]);
+    }
+
+    #[test]
+    fn add_range_overlapping() {
+        let mut a = RunContainer::new();
+        a.add_range(100..200);
+        a.add_range(10..20);
+        a.add_range(150..300);
+        a.add_range(20..30);
+
+        assert_eq!(a.num_runs(), 2);
+        assert_eq!(a.cardinality(), 20 + 200);
+
+        let found: Vec<u16> = a.iter().collect();
+        let expected: Vec<u16> = (10..30).chain(100..300).collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn add_range_neighbours() {
+        // (range, expected runs) added on top of the runs [10-19] and [40-49]
+        let cases = [
+            (0..5, vec![(0, 4), (10, 19), (40, 49)]),
+            (0..9, vec![(0, 8), (10, 19), (40, 49)]),
+            (0..10, vec![(0, 19), (40, 49)]),
+            (20..30, vec![(10, 29), (40, 49)]),
+            (21..30, vec![(10, 19), (21, 29), (40, 49)]),
+            (21..39, vec![(10, 19), (21, 38), (40, 49)]),
+            (21..40, vec![(10, 19), (21, 49)]),
+            (20..40, vec![(10, 49)]),
+            (15..45, vec![(10, 49)]),
+            (50..60, vec![(10, 19), (40, 59)]),
+            (51..60, vec![(10, 19), (40, 49), (51, 59)]),
+            (12..14, vec![(10, 19), (40, 49)]),
+            (30..30, vec![(10, 19), (40, 49)]),
+            (65_000..65_536, vec![(10, 19), (40, 49), (65_000, 65_535)]),
+        ];
+
+        for (range, expected) in cases.iter() {
+            let mut a = RunContainer::new();
+            a.add_range(10..20);
+            a.add_range(40..50);
+            a.add_range(range.clone());
+
+            let found: Vec<(u16, u16)> = a.runs.iter().map(|run| (run.value, run.end())).collect();
+            assert_eq!(&found, expected, "Failed for {:?}", range);
+
+            let mut values: Vec<u16> = (10..20).chain(40..50).chain(range.clone().map(|v| v as u16)).collect();
+            values.sort_unstable();
+            values.dedup();
+            assert!(a.iter().eq(values.into_iter()));
+        }
+
+        // Empty ranges leave an empty container untouched
+        let mut a = RunContainer::new();
+        a.add_range(0..0);
+        a.add_range(5..5);
+        assert!(a.runs.is_empty());
+    }
+
     #[test]
     fn add_range() {
         let range = 0..(1 << 16);
@@ -2186,6 +2477,41 @@ mod test {
         }
     }
 
+    #[test]
+    fn remove_range_multiple_runs() {
+        let runs = [0..10, 20..30, 40..50, 60..70, 80..90, 65_530..65_536];
+        let removals = [
+            15..75,         // Covers several middle runs
+            5..85,          // Truncates both ends
+            0..65_536,      // Covers everything
+            25..45,         // Truncates neighbouring runs
+            20..30,         // Exactly one run
+            10..20,         // Only a gap
+            0..1,           // The first value
+            65_535..65_536, // The last value
+            42..44,         // Splits a run
+        ];
+
+        for removal in removals.iter() {
+            let mut a = RunContainer::new();
+            for run in runs.iter() {
+                a.add_range(run.clone());
+            }
+
+            a.remove_range(removal.clone());
+
+            let expected: Vec<u16> = runs.iter()
+                .flat_map(|r| r.clone())
+                .filter(|v| !removal.contains(v))
+                .map(|v| v as u16)
+                .collect();
+            let found: Vec<u16> = a.iter().collect();
+
+            assert_eq!(a.cardinality(), expected.len(), "Removing {:?}", removal);
+            assert_eq!(found, expected, "Removing {:?}", removal);
+        }
+    }
+
     #[test]
     fn contains() {
         let mut a = RunContainer::new();
@@ -2201,6 +2527,35 @@ mod test {
         a.add_range(0..100);
 
         assert!(a.contains_range(25..75));
+        assert!(a.contains_range(0..100));
+        assert!(!a.contains_range(0..101));
+        assert!(!a.contains_range(99..200));
+
+        // The range may end inside a later run, but not past a gap
+        a.add_range(200..300);
+        assert!(a.contains_range(250..300));
+        assert!(!a.contains_range(50..250));
+        assert!(!a.contains_range(100..200));
+
+        let full = RunContainer::full();
+        assert!(full.contains_range(0..(1 << 16)));
+        assert!(full.contains_range(65_000..(1 << 16)));
+    }
+
+    #[test]
+    fn and_run_at_max() {
+        let mut a = RunContainer::new();
+        a.add_range(0..10);
+        a.add_range(60_000..(1 << 16));
+
+        let mut b = RunContainer::new();
+        b.add_range(5..20);
+        b.add_range(65_000..(1 << 16));
+
+        let expected: Vec<u16> = (5..10).chain(65_000..=65_535).collect();
+        let result = SetAnd::and(&a, &b);
+        assert!(result.iter().eq(expected.iter().copied()));
+        assert_eq!(SetAnd::and_cardinality(&a, &b), expected.len());
     }
 
     #[test]
@@ -2258,6 +2613,27 @@ mod test {
         assert_eq!(rank, 6);
     }
 
+    #[test]
+    fn rank_boundaries() {
+        let mut a = RunContainer::new();
+        a.add_range(0..1);
+        a.add_range(10..20);
+        a.add_range(30..31);
+        a.add_range(65_000..(1 << 16));
+
+        // Run starts, ends, values either side of them and gaps
+        let probes = [0, 1, 9, 10, 11, 19, 20, 21, 29, 30, 31, 64_999, 65_000, 65_001, u16::MAX - 1, u16::MAX];
+        for probe in probes.iter() {
+            let expected = (0..=*probe).filter(|v| a.contains(*v)).count();
+            assert_eq!(a.rank(*probe), expected, "Failed for {}", probe);
+        }
+
+        let full = RunContainer::full();
+        assert_eq!(full.rank(u16::MAX), 1 << 16);
+        assert_eq!(full.rank(0), 1);
+        assert_eq!(RunContainer::new().rank(100), 0);
+    }
+
     #[test]
     fn select() {
         let range = 0..30;
@@ -2309,12 +2685,71 @@ mod test {
         assert!(deserialized.is_ok());
 
         let deserialized = deserialized.unwrap();
-        let iter = deserialized.iter()
-            .zip(a.iter());
+        assert_eq!(deserialized.num_runs(), a.num_runs());
+        assert_eq!(deserialized.cardinality(), a.cardinality());
+        assert!(deserialized.iter().eq(a.iter()));
+        assert_eq!(cursor.position() as usize, num_bytes);
+    }
 
-        for (found, expected) in iter {
-            assert_eq!(found, expected);
+    #[test]
+    fn deserialize_known_runs() {
+        // Runs [10, 14], [100, 100] and [65_000, 65_535] encoded as value, length - 1 pairs
+        let data: [u8; 14] = [
+            3, 0,
+            10, 0, 4, 0,
+            100, 0, 0, 0,
+            0xE8, 0xFD, 0x17, 0x02
+        ];
+
+        let container = RunContainer::deserialize(&mut std::io::Cursor::new(&data[..])).unwrap();
+        let expected = (10..15).chain(100..101).chain(65_000..=65_535);
+
+        assert_eq!(container.num_runs(), 3);
+        assert_eq!(container.cardinality(), 5 + 1 + 536);
+        assert!(!container.is_empty());
+        assert!(container.iter().eq(expected));
+
+        // A truncated buffer is reported rather than producing a partial container
+        let truncated = RunContainer::deserialize(&mut std::io::Cursor::new(&data[..12]));
+        assert_eq!(truncated.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn deserialize_invalid_runs() {
+        fn encode(runs: &[(u16, u16)]) -> Vec<u8> {
+            let mut data = Vec::new();
+            data.extend_from_slice(&(runs.len() as u16).to_le_bytes());
+
+            for (value, length) in runs.iter() {
+                data.extend_from_slice(&value.to_le_bytes());
+                data.extend_from_slice(&length.to_le_bytes());
+            }
+
+            data
+        }
+
+        let deserialize = |runs: &[(u16, u16)]| {
+            RunContainer::deserialize(&mut std::io::Cursor::new(encode(runs)))
+        };
+
+        // Unsorted, overlapping and out of bounds runs are rejected
+        let invalid: [&[(u16, u16)]; 4] = [
+            &[(100, 5), (10, 5)],
+            &[(10, 5), (15, 2)],
+            &[(10, 5), (10, 5)],
+            &[(65_530, 10)]
+        ];
+
+        for runs in invalid.iter() {
+            let result = deserialize(runs);
+
+            assert!(result.is_err(), "Accepted {:?}", runs);
+            assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
         }
+
+        // Adjacent runs and runs ending on the last value are accepted
+        let valid = deserialize(&[(0, 9), (10, 5), (65_000, 535)]).unwrap();
+        assert_eq!(valid.cardinality(), 10 + 6 + 536);
     }
 
     #[test]
@@ -2324,6 +2759,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_run_or_cardinality() {
+        op_card_test::<RunContainer, RunContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn run_run_and() {
         op_test::<RunContainer, RunContainer, u16, _, Container>(
@@ -2345,6 +2792,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_run_and_not_cardinality() {
+        op_card_test::<RunContainer, RunContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn run_run_xor() {
         op_test::<RunContainer, RunContainer, u16, _, Container>(
@@ -2352,6 +2811,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_run_xor_cardinality() {
+        op_card_test::<RunContainer, RunContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn run_not() {
         let data = generate_data(0..65535, 20_000);
@@ -2360,7 +2831,7 @@ mod test {
 
         assert_eq!(
             not_a.cardinality(), 
-            usize::from(std::u16::MAX) - a.cardinality()
+            (1 << 16) - a.cardinality()
         );
 
         // Ensure that `not_a` contains no elements of A
@@ -2369,6 +2840,63 @@ mod test {
         }
     }
 
+    #[test]
+    fn run_not_ranges() {
+        let mut a = RunContainer::new();
+        for (start, end) in [(0, 10), (100, 200), (300, 301), (1_000, 5_000), (65_000, 65_536)].iter() {
+            a.add_range(*start..*end);
+        }
+
+        let ranges = [
+            0..1, 0..10, 5..15, 10..100, 10..101, 150..250, 200..300, 
+            250..1_200, 301..1_000, 4_999..65_000, 60_000..65_536, 0..65_536
+        ];
+
+        for range in ranges.iter() {
+            let expected: Vec<u16> = (0..=65_535u16)
+                .filter(|v| a.contains(*v) != range.contains(&u32::from(*v)))
+                .collect();
+
+            let found = a.not(range.clone());
+            assert_eq!(found.cardinality(), expected.len(), "Failed for {:?}", range);
+            assert!(found.iter().eq(expected.iter().cloned()), "Failed for {:?}", range);
+
+            let found = SetNot::inplace_not(a.clone(), range.clone());
+            assert!(found.iter().eq(expected.iter().cloned()), "Failed inplace for {:?}", range);
+        }
+    }
+
+    #[test]
+    fn run_inplace_not_reuses_allocation() {
+        let mut a = RunContainer::new();
+        for i in 0..200u32 {
+            a.add_range((i * 300)..(i * 300 + 100));
+        }
+
+        let original: Vec<u16> = a.iter().collect();
+
+        // Flipping within a run splits it, flipping again restores it
+        for range in [10_020..10_050, 10_000..10_100, 10_050..10_250, 0..65_536].iter() {
+            let mut runs = a.clone();
+            runs.reserve(1);
+            let capacity = runs.capacity();
+
+            let flipped = match SetNot::inplace_not(runs, range.clone()) {
+                Container::Run(c) => c,
+                _ => panic!("Expected a run container for {:?}", range)
+            };
+            assert!(flipped.capacity() <= capacity);
+
+            let restored = match SetNot::inplace_not(flipped, range.clone()) {
+                Container::Run(c) => c,
+                _ => panic!("Expected a run container for {:?}", range)
+            };
+
+            assert!(restored.iter().eq(original.iter().cloned()), "Failed for {:?}", range);
+            assert_eq!(restored.capacity(), capacity, "Reallocated for {:?}", range);
+        }
+    }
+
     #[test]
     fn run_run_inplace_or() {
         op_test::<RunContainer, RunContainer, u16, _, Container>(
@@ -2402,6 +2930,77 @@ mod test {
         op_subset_test::<RunContainer, RunContainer, u16>();
     }
 
+    #[test]
+    fn run_run_ops_at_max() {
+        // Runs ending at `u16::MAX` can't have their exclusive end represented as a u16
+        let mut a = RunContainer::new();
+        a.add_range(60_000..(1 << 16));
+        a.add_range(100..200);
+
+        let mut b = RunContainer::new();
+        b.add_range(65_000..(1 << 16));
+        b.add_range(150..300);
+
+        for (x, y) in [(&a, &b), (&b, &a), (&a, &a), (&a, &RunContainer::full())].iter() {
+            let in_x = |v: &u16| x.contains(*v);
+            let in_y = |v: &u16| y.contains(*v);
+
+            let expected_or: Vec<u16> = (0..=u16::MAX).filter(|v| in_x(v) || in_y(v)).collect();
+            let expected_and_not: Vec<u16> = (0..=u16::MAX).filter(|v| in_x(v) && !in_y(v)).collect();
+            let expected_xor: Vec<u16> = (0..=u16::MAX).filter(|v| in_x(v) != in_y(v)).collect();
+
+            assert!(SetOr::or(*x, *y).iter().eq(expected_or.iter().cloned()));
+            assert!(SetAndNot::and_not(*x, *y).iter().eq(expected_and_not.iter().cloned()));
+            assert!(SetXor::xor(*x, *y).iter().eq(expected_xor.iter().cloned()));
+        }
+    }
+
+    #[test]
+    fn run_run_subset_of_boundaries() {
+        let from_runs = |runs: &[(u16, u16)]| {
+            let mut c = RunContainer::new();
+            c.runs = runs.iter()
+                .map(|(start, end)| Rle16::new(*start, end - start))
+                .collect();
+            c
+        };
+
+        let outer = from_runs(&[(10, 20), (30, 40), (65_500, u16::MAX)]);
+
+        // Runs sharing one or both endpoints of the enclosing runs
+        let subsets = [
+            &[(10, 20)][..],
+            &[(10, 10), (20, 20)][..],
+            &[(15, 20), (30, 35)][..],
+            &[(40, 40), (65_500, u16::MAX)][..],
+            &[(u16::MAX, u16::MAX)][..],
+            &[(10, 20), (30, 40), (65_500, u16::MAX)][..]
+        ];
+
+        for runs in subsets.iter() {
+            assert!(from_runs(runs).subset_of(&outer), "{:?}", runs);
+        }
+
+        // Runs differing by a single element at a boundary
+        let non_subsets = [
+            &[(9, 20)][..],
+            &[(10, 21)][..],
+            &[(29, 29)][..],
+            &[(41, 41)][..],
+            &[(15, 20), (21, 21)][..],
+            &[(30, 40), (65_499, 65_510)][..],
+            &[(20, 30)][..]
+        ];
+
+        for runs in non_subsets.iter() {
+            assert!(!from_runs(runs).subset_of(&outer), "{:?}", runs);
+        }
+
+        assert!(RunContainer::new().subset_of(&outer));
+        assert!(outer.subset_of(&RunContainer::full()));
+        assert!(!RunContainer::full().subset_of(&outer));
+    }
+
     #[test]
     fn run_array_or() {
         op_test::<RunContainer, ArrayContainer, u16, _, Container>(
@@ -2409,6 +3008,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_array_or_cardinality() {
+        op_card_test::<RunContainer, ArrayContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn run_array_and() {
         op_test::<RunContainer, ArrayContainer, u16, _, Container>(
@@ -2430,6 +3041,45 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_array_and_not_cardinality() {
+        op_card_test::<RunContainer, ArrayContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
+    #[test]
+    fn run_array_and_not_exhaustive() {
+        for seed in 0..32 {
+            // Small runs hit the run producing path, larger ones the array producing path
+            for (range, count) in [(0..64, 20), (0..8_000, 3_000)].iter() {
+                let mut data_a = generate_seeded_data(range.clone(), *count, seed);
+                data_a.push(65534);
+                data_a.push(65535);
+
+                let mut data_b = generate_seeded_data(range.clone(), count / 2, seed + 64);
+                if seed % 2 == 0 {
+                    data_b.push(65535);
+                }
+
+                let a = RunContainer::from_data(&data_a);
+                let b = ArrayContainer::from_data(&data_b);
+
+                let expected = compute_result(&data_a, &data_b, OpType::AndNot);
+                let found: Vec<u16> = a.and_not(&b)
+                    .iter()
+                    .collect();
+
+                assert_eq!(found, expected);
+            }
+        }
+    }
+
     #[test]
     fn run_array_xor() {
         op_test::<RunContainer, ArrayContainer, u16, _, Container>(
@@ -2437,6 +3087,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_array_xor_cardinality() {
+        op_card_test::<RunContainer, ArrayContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn run_array_inplace_or() {
         op_test::<RunContainer, ArrayContainer, u16, _, Container>(
@@ -2488,6 +3150,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_bitset_or_cardinality() {
+        op_card_test::<RunContainer, BitsetContainer, u16, _>(
+            OpType::Or, |a, b| {
+                let card = a.or_cardinality(&b);
+                assert_eq!(card, a.or(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn run_bitset_and() {
         op_test::<RunContainer, BitsetContainer, u16, _, Container>(
@@ -2509,6 +3183,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_bitset_and_not_cardinality() {
+        op_card_test::<RunContainer, BitsetContainer, u16, _>(
+            OpType::AndNot, |a, b| {
+                let card = a.and_not_cardinality(&b);
+                assert_eq!(card, a.and_not(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn run_bitset_xor() {
         op_test::<RunContainer, BitsetContainer, u16, _, Container>(
@@ -2516,6 +3202,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn run_bitset_xor_cardinality() {
+        op_card_test::<RunContainer, BitsetContainer, u16, _>(
+            OpType::Xor, |a, b| {
+                let card = a.xor_cardinality(&b);
+                assert_eq!(card, a.xor(&b).cardinality());
+
+                card
+            }
+        );
+    }
+
     #[test]
     fn run_bitset_inplace_or() {
         op_test::<RunContainer, BitsetContainer, u16, _, Container>(
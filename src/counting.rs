@@ -0,0 +1,248 @@
+use crate::RoaringBitmap;
+
+/// A bitmap tracking how many times each value has been inserted
+///
+/// # Structure
+/// Counts are stored bit-sliced, bit `i` of every value's count is held in the bitmap `planes[i]`.
+/// A value with a count of zero is absent from every plane
+///
+/// # Remarks
+/// Bulk operations are computed plane by plane using the regular bitmap operations,
+/// so the cost scales with the number of planes rather than with the counts themselves
+#[derive(Clone, Debug, Default)]
+pub struct CountingBitmap {
+    /// The bit planes of the counts, least significant first
+    planes: Vec<RoaringBitmap>
+}
+
+impl CountingBitmap {
+    /// Create a new counting bitmap where every count is zero
+    pub fn new() -> Self {
+        Self {
+            planes: Vec::new()
+        }
+    }
+
+    /// Increment the count of `value` by one
+    ///
+    /// # Panics
+    /// Panics if the count of `value` would exceed `u32::MAX`
+    pub fn increment(&mut self, value: u32) {
+        // Fail before touching any plane if the carry would ripple out of the top plane
+        assert!(
+            self.planes.len() < 32 || !self.planes.iter().all(|plane| plane.contains(value)),
+            "Count overflowed u32"
+        );
+
+        // Ripple the carry up through the planes
+        for plane in self.planes.iter_mut() {
+            if plane.contains(value) {
+                plane.remove(value);
+            }
+            else {
+                plane.add(value);
+                return;
+            }
+        }
+
+        self.push_plane(RoaringBitmap::from_slice(&[value]));
+    }
+
+    /// Increment the count of every value in `values` by one
+    ///
+    /// # Panics
+    /// Panics if any count would exceed `u32::MAX`
+    pub fn increment_all(&mut self, values: &RoaringBitmap) {
+        // Fail before touching any plane if a value's count is already saturated
+        if self.planes.len() == 32 {
+            let mut saturated = values.clone();
+            for plane in self.planes.iter() {
+                if saturated.is_empty() {
+                    break;
+                }
+
+                saturated.inplace_and(plane);
+            }
+
+            assert!(saturated.is_empty(), "Count overflowed u32");
+        }
+
+        let mut carry = values.clone();
+
+        for plane in self.planes.iter_mut() {
+            if carry.is_empty() {
+                return;
+            }
+
+            let next_carry = plane.and(&carry);
+            plane.inplace_xor(&carry);
+            carry = next_carry;
+        }
+
+        if !carry.is_empty() {
+            self.push_plane(carry);
+        }
+    }
+
+    /// Get the number of times `value` has been inserted
+    pub fn get_count(&self, value: u32) -> u32 {
+        self.planes.iter()
+            .enumerate()
+            .filter(|(_i, plane)| plane.contains(value))
+            .fold(0, |count, (i, _plane)| count | (1 << i))
+    }
+
+    /// Get a bitmap of every value with a count of at least `n`
+    ///
+    /// # Remarks
+    /// Values with a count of zero are never included, so `n == 0` behaves the same as `n == 1`
+    pub fn values_with_count_at_least(&self, n: u32) -> RoaringBitmap {
+        let n = n.max(1);
+
+        // The threshold needs more bits than the counts have
+        if self.planes.len() < 32 && n >> self.planes.len() != 0 {
+            return RoaringBitmap::new();
+        }
+
+        // Walk the planes from the most significant bit tracking the values
+        // whose counts are greater than and equal to the prefix of `n` seen so far
+        let mut greater = RoaringBitmap::new();
+        let mut equal = self.present();
+
+        for (i, plane) in self.planes.iter().enumerate().rev() {
+            if (n >> i) & 1 == 1 {
+                equal.inplace_and(plane);
+            }
+            else {
+                greater.inplace_or(&equal.and(plane));
+                equal.inplace_and_not(plane);
+            }
+        }
+
+        greater.inplace_or(&equal);
+        greater
+    }
+
+    /// Get a bitmap of every value with a non zero count
+    pub fn present(&self) -> RoaringBitmap {
+        let mut result = RoaringBitmap::new();
+        for plane in self.planes.iter() {
+            result.inplace_or(plane);
+        }
+
+        result
+    }
+
+    /// Add a new most significant plane
+    fn push_plane(&mut self, plane: RoaringBitmap) {
+        assert!(self.planes.len() < 32, "Count overflowed u32");
+
+        self.planes.push(plane);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use crate::{CountingBitmap, RoaringBitmap};
+    use crate::test::*;
+
+    #[test]
+    fn increment() {
+        let mut counts = CountingBitmap::new();
+        let mut expected = BTreeMap::new();
+
+        let data = generate_data(0..1_000_000u32, 2_000);
+        for (i, value) in data.iter().enumerate() {
+            for _ in 0..(i % 13) {
+                counts.increment(*value);
+                *expected.entry(*value).or_insert(0) += 1;
+            }
+        }
+
+        for value in data.iter() {
+            assert_eq!(counts.get_count(*value), *expected.get(value).unwrap_or(&0));
+        }
+
+        assert_eq!(counts.get_count(5_000_000), 0);
+    }
+
+    #[test]
+    fn increment_all() {
+        let a = RoaringBitmap::from_slice(&generate_seeded_data(0..500_000, 20_000, 1));
+        let b = RoaringBitmap::from_slice(&generate_seeded_data(0..500_000, 20_000, 2));
+
+        let mut bulk = CountingBitmap::new();
+        let mut single = CountingBitmap::new();
+        for bitmap in [&a, &b, &a, &a, &b].iter() {
+            bulk.increment_all(bitmap);
+
+            for value in bitmap.iter() {
+                single.increment(value);
+            }
+        }
+
+        for value in a.or(&b).iter() {
+            assert_eq!(bulk.get_count(value), single.get_count(value));
+        }
+
+        assert!(bulk.present().iter().eq(a.or(&b).iter()));
+    }
+
+    #[test]
+    fn overflow_leaves_counts_unchanged() {
+        use std::panic::{self, AssertUnwindSafe};
+
+        // Reaching a count of u32::MAX by incrementing isn't feasible, so fill the planes directly
+        let mut counts = CountingBitmap::new();
+        for i in 0..32 {
+            let mut plane = RoaringBitmap::from_slice(&[5]);
+            if i % 2 == 0 {
+                plane.add(7);
+            }
+
+            counts.planes.push(plane);
+        }
+
+        let before = counts.clone();
+        let unchanged = |counts: &CountingBitmap| {
+            [5, 7, 9].iter().all(|value| counts.get_count(*value) == before.get_count(*value))
+        };
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| counts.increment(5)));
+        assert!(result.is_err());
+        assert!(unchanged(&counts));
+
+        let values = RoaringBitmap::from_slice(&[5, 7, 9]);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| counts.increment_all(&values)));
+        assert!(result.is_err());
+        assert!(unchanged(&counts));
+
+        // Counts below the limit can still be incremented
+        counts.increment(7);
+        counts.increment_all(&RoaringBitmap::from_slice(&[9]));
+        assert_eq!(counts.get_count(7), before.get_count(7) + 1);
+        assert_eq!(counts.get_count(9), 1);
+        assert_eq!(counts.get_count(5), u32::MAX);
+    }
+
+    #[test]
+    fn values_with_count_at_least() {
+        let mut counts = CountingBitmap::new();
+        for value in 0..100u32 {
+            for _ in 0..value {
+                counts.increment(value);
+            }
+        }
+
+        for n in [0u32, 1, 2, 7, 8, 9, 31, 32, 33, 64, 99, 100, 1_000].iter() {
+            let found = counts.values_with_count_at_least(*n);
+            let expected = (1..100u32).filter(|count| count >= n);
+
+            assert!(found.iter().eq(expected), "Failed for threshold {}", n);
+        }
+
+        assert!(CountingBitmap::new().values_with_count_at_least(1).is_empty());
+    }
+}
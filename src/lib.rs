@@ -17,45 +17,55 @@
 // TODO: Ensure soundness in the face of panics where necessary
 
 mod container;
+mod counting;
 mod roaring;
 
 #[cfg(test)] mod test;
+#[cfg(test)] mod prop_test;
 
 pub use roaring::*;
+pub use counting::CountingBitmap;
+pub use container::array_ops::set_simd_enabled;
 
 use std::ops::{RangeBounds, Bound};
 
-/// Convert a range into a bounded range based on some internal constraints
+/// Convert a range into exclusive bounds based on some internal constraints
+/// 
+/// # Remarks
+/// The bounds are one size wider than the range so that an inclusive end of the maximum value
+/// is representable, `..=u32::MAX` becomes `(0, 1 << 32)`
 trait IntoBound<T>: Sized {
-    fn into_bound(self) -> (T, T);
+    type Wide;
+
+    fn into_bound(self) -> (Self::Wide, Self::Wide);
 }
 
 macro_rules! impl_into_bound {
-    ($type:ident) => {
+    ($type:ident => $wide:ident) => {
         impl<T> IntoBound<$type> for T
-    where T: RangeBounds<$type>
-    {
-        /// Convert the range into a range bounded by [0-max]
-        fn into_bound(self) -> ($type, $type) {
-            let start = match self.start_bound() {
-                Bound::Excluded(bound) => *bound + 1,
-                Bound::Included(bound) => *bound,
-                Bound::Unbounded => 0
-            };
-
-            let end = match self.end_bound() {
-                Bound::Excluded(bound) => *bound,
-                Bound::Included(bound) => *bound + 1,
-                Bound::Unbounded => <$type>::max_value()
-            };
-
-            (start, end)
-        }
-    }
+            where T: RangeBounds<$type>
+        {
+            type Wide = $wide;
+
+            /// Convert the range into a range bounded by [0-max + 1)
+            fn into_bound(self) -> ($wide, $wide) {
+                let start = match self.start_bound() {
+                    Bound::Excluded(bound) => $wide::from(*bound) + 1,
+                    Bound::Included(bound) => $wide::from(*bound),
+                    Bound::Unbounded => 0
+                };
 
+                let end = match self.end_bound() {
+                    Bound::Excluded(bound) => $wide::from(*bound),
+                    Bound::Included(bound) => $wide::from(*bound) + 1,
+                    Bound::Unbounded => $wide::from(<$type>::MAX) + 1
+                };
+
+                (start, end)
+            }
+        }
     };
 }
 
-impl_into_bound!(u16);
-impl_into_bound!(usize);
-impl_into_bound!(u32);
\ No newline at end of file
+impl_into_bound!(u16 => u32);
+impl_into_bound!(u32 => u64);
@@ -0,0 +1,284 @@
+#![cfg(test)]
+
+//! Property tests comparing bitmap set operations against `BTreeSet`
+//!
+//! Every case is generated from a seed so failures are reproducible.
+//! The base seed and number of cases can be overridden with the `ROARING_PROP_SEED`
+//! and `ROARING_PROP_CASES` environment variables
+
+use std::collections::BTreeSet;
+use std::env;
+
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+
+use crate::RoaringBitmap;
+
+/// The default base seed for generating cases
+const DEFAULT_SEED: u64 = 0x5EED_B175;
+
+/// The default number of cases to run for each property
+const DEFAULT_CASES: u64 = 48;
+
+/// A randomly generated input set
+///
+/// # Remarks
+/// Inputs are kept as a list of values plus a list of ranges so they can be shrunk
+/// by removing elements while still producing every container type
+#[derive(Clone, Debug)]
+struct Input {
+    /// Individual values added to the bitmap
+    values: Vec<u32>,
+
+    /// Ranges added to the bitmap, large ranges produce bitset and run containers
+    ranges: Vec<(u32, u32)>
+}
+
+impl Input {
+    /// Generate a random input of roughly `scale` elements spread over a few containers
+    fn generate(rng: &mut SmallRng, scale: usize) -> Self {
+        // Keep values within a handful of containers so the inputs overlap
+        let max_key = rng.gen_range(1, 8u32);
+        let gen_value = |rng: &mut SmallRng| rng.gen_range(0, max_key << 16);
+
+        let num_values = rng.gen_range(0, scale + 1);
+        let values = (0..num_values)
+            .map(|_| gen_value(rng))
+            .collect();
+
+        let num_ranges = rng.gen_range(0, 4);
+        let ranges = (0..num_ranges)
+            .map(|_| {
+                let start = gen_value(rng);
+                let len = match rng.gen_range(0, 3) {
+                    0 => rng.gen_range(1, 64),
+                    1 => rng.gen_range(64, 8_192),
+                    _ => rng.gen_range(8_192, 1 << 17)
+                };
+
+                (start, start.saturating_add(len))
+            })
+            .collect();
+
+        Self {
+            values,
+            ranges
+        }
+    }
+
+    /// Build the bitmap represented by this input
+    fn to_bitmap(&self) -> RoaringBitmap {
+        let mut bitmap = RoaringBitmap::new();
+        for (start, end) in self.ranges.iter() {
+            bitmap.add_range(*start..*end);
+        }
+
+        for value in self.values.iter() {
+            bitmap.add(*value);
+        }
+
+        bitmap
+    }
+
+    /// Build the reference set represented by this input
+    fn to_set(&self) -> BTreeSet<u32> {
+        let mut set: BTreeSet<u32> = self.values.iter().cloned().collect();
+        for (start, end) in self.ranges.iter() {
+            set.extend(*start..*end);
+        }
+
+        set
+    }
+
+    /// Produce smaller variants of this input, biggest reductions first
+    fn shrink(&self) -> Vec<Self> {
+        let mut candidates = Vec::new();
+
+        for i in 0..self.ranges.len() {
+            let mut ranges = self.ranges.clone();
+            ranges.remove(i);
+            candidates.push(Self { values: self.values.clone(), ranges });
+        }
+
+        let len = self.values.len();
+        let mut chunk = len / 2;
+        while chunk > 0 {
+            for start in (0..len).step_by(chunk) {
+                let mut values = self.values.clone();
+                values.drain(start..(start + chunk).min(len));
+                candidates.push(Self { values, ranges: self.ranges.clone() });
+            }
+
+            chunk /= 2;
+        }
+
+        candidates
+    }
+}
+
+/// Get the base seed and number of cases to run
+fn config() -> (u64, u64) {
+    let read = |name: &str, default: u64| {
+        env::var(name)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    };
+
+    (read("ROARING_PROP_SEED", DEFAULT_SEED), read("ROARING_PROP_CASES", DEFAULT_CASES))
+}
+
+/// Run a binary property over many generated input pairs.
+/// On failure the inputs are shrunk and the smallest failing pair is reported with its seed
+fn check<F>(name: &str, property: F)
+    where F: Fn(&Input, &Input) -> Result<(), String>
+{
+    let (base_seed, cases) = config();
+
+    for case in 0..cases {
+        let seed = base_seed.wrapping_add(case);
+        let mut rng = SmallRng::seed_from_u64(seed);
+
+        // Start small and grow so the simplest failures are found first
+        let scale = 1 + (case as usize * 64);
+        let a = Input::generate(&mut rng, scale);
+        let b = Input::generate(&mut rng, scale);
+
+        if let Err(message) = property(&a, &b) {
+            let (a, b, message) = shrink(a, b, message, &property);
+
+            panic!(
+                "Property '{}' failed with seed {}: {}\na: {:?}\nb: {:?}",
+                name,
+                seed,
+                message,
+                a,
+                b
+            );
+        }
+    }
+}
+
+/// Shrink a failing pair of inputs until no smaller variant fails
+fn shrink<F>(mut a: Input, mut b: Input, mut message: String, property: &F) -> (Input, Input, String)
+    where F: Fn(&Input, &Input) -> Result<(), String>
+{
+    'outer: loop {
+        for candidate in a.shrink() {
+            if let Err(m) = property(&candidate, &b) {
+                a = candidate;
+                message = m;
+                continue 'outer;
+            }
+        }
+
+        for candidate in b.shrink() {
+            if let Err(m) = property(&a, &candidate) {
+                b = candidate;
+                message = m;
+                continue 'outer;
+            }
+        }
+
+        return (a, b, message);
+    }
+}
+
+/// Compare the contents of a bitmap with the expected set
+fn compare(op: &str, found: &RoaringBitmap, expected: &BTreeSet<u32>) -> Result<(), String> {
+    if found.cardinality() != expected.len() {
+        return Err(format!(
+            "{}: cardinality {} != expected {}",
+            op,
+            found.cardinality(),
+            expected.len()
+        ));
+    }
+
+    let mismatch = found.iter()
+        .zip(expected.iter())
+        .find(|(f, e)| f != *e);
+
+    if let Some((f, e)) = mismatch {
+        return Err(format!("{}: found {} where {} was expected", op, f, e));
+    }
+
+    Ok(())
+}
+
+/// Compare a computed cardinality with the expected value
+fn compare_card(op: &str, found: usize, expected: usize) -> Result<(), String> {
+    if found != expected {
+        Err(format!("{}: cardinality {} != expected {}", op, found, expected))
+    }
+    else {
+        Ok(())
+    }
+}
+
+#[test]
+fn construction() {
+    check("construction", |a, _b| {
+        compare("construct", &a.to_bitmap(), &a.to_set())
+    });
+}
+
+#[test]
+fn or() {
+    check("or", |a, b| {
+        let (ba, bb) = (a.to_bitmap(), b.to_bitmap());
+        let expected = a.to_set().union(&b.to_set()).cloned().collect();
+
+        compare("or", &ba.or(&bb), &expected)?;
+        compare_card("or_cardinality", ba.or_cardinality(&bb), expected.len())?;
+
+        let mut inplace = ba.clone();
+        inplace.inplace_or(&bb);
+        compare("inplace_or", &inplace, &expected)
+    });
+}
+
+#[test]
+fn and() {
+    check("and", |a, b| {
+        let (ba, bb) = (a.to_bitmap(), b.to_bitmap());
+        let expected = a.to_set().intersection(&b.to_set()).cloned().collect();
+
+        compare("and", &ba.and(&bb), &expected)?;
+        compare_card("and_cardinality", ba.and_cardinality(&bb), expected.len())?;
+
+        let mut inplace = ba.clone();
+        inplace.inplace_and(&bb);
+        compare("inplace_and", &inplace, &expected)
+    });
+}
+
+#[test]
+fn and_not() {
+    check("and_not", |a, b| {
+        let (ba, bb) = (a.to_bitmap(), b.to_bitmap());
+        let expected = a.to_set().difference(&b.to_set()).cloned().collect();
+
+        compare("and_not", &ba.and_not(&bb), &expected)?;
+        compare_card("and_not_cardinality", ba.and_not_cardinality(&bb), expected.len())?;
+
+        let mut inplace = ba.clone();
+        inplace.inplace_and_not(&bb);
+        compare("inplace_and_not", &inplace, &expected)
+    });
+}
+
+#[test]
+fn xor() {
+    check("xor", |a, b| {
+        let (ba, bb) = (a.to_bitmap(), b.to_bitmap());
+        let expected = a.to_set().symmetric_difference(&b.to_set()).cloned().collect();
+
+        compare("xor", &ba.xor(&bb), &expected)?;
+        compare_card("xor_cardinality", ba.xor_cardinality(&bb), expected.len())?;
+
+        let mut inplace = ba.clone();
+        inplace.inplace_xor(&bb);
+        compare("inplace_xor", &inplace, &expected)
+    });
+}
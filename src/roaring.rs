@@ -1,9 +1,13 @@
 #![deny(arithmetic_overflow)]
 
-use std::io::{self, Read, Write, Seek, SeekFrom};
-use std::ops::{RangeBounds, Range};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::ops::{RangeBounds, Range, RangeInclusive};
 use std::fmt;
 use std::mem;
+use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
 
 use crate::IntoBound;
 use crate::container::{self, *, array_ops};
@@ -46,15 +50,45 @@ use crate::container::{self, *, array_ops};
 /// Queries using the normal ops will create a new bitmap for every operation.
 /// 
 /// `cardinality()` (`len()`) queries may lazily evaluate the cardinality of some containers if they are determined to be out of date
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct RoaringBitmap {
     /// List of containers in this roaring bitmap
     containers: Vec<Container>,
 
     /// List of keys corresponding to the containers in the bitmap
-    keys: Vec<u16>
+    keys: Vec<u16>,
+
+    /// The number of values an array container may hold before it's promoted to a bitset
+    max_array_size: usize,
+
+    /// How the container and key vectors grow when containers are inserted
+    growth_policy: GrowthPolicy,
+
+    /// The smallest and largest values, computed on demand and reset when the contents change
+    bounds: BoundsCache
+}
+
+impl Default for RoaringBitmap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bitmaps are equal if they contain the same values regardless of how their containers are stored
+impl PartialEq for RoaringBitmap {
+    fn eq(&self, other: &Self) -> bool {
+        if self.keys != other.keys {
+            return false;
+        }
+
+        self.containers.iter()
+            .zip(other.containers.iter())
+            .all(|(c0, c1)| c0.eq_values(c1))
+    }
 }
 
+impl Eq for RoaringBitmap { }
+
 impl RoaringBitmap {
     /// Create a new empty roaring bitmap
     #[inline]
@@ -66,18 +100,84 @@ impl RoaringBitmap {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             containers: Vec::with_capacity(capacity),
-            keys: Vec::with_capacity(capacity)
+            keys: Vec::with_capacity(capacity),
+            max_array_size: DEFAULT_MAX_SIZE,
+            growth_policy: GrowthPolicy::default(),
+            bounds: BoundsCache::new()
+        }
+    }
+
+    /// Create a new roaring bitmap whose array containers hold up to `size` values before
+    /// being promoted to a bitset
+    /// 
+    /// # Remarks
+    /// The threshold is consulted when adding and removing values or ranges, and is carried over 
+    /// to the results of set operations. Optimization still chooses representations based on 
+    /// `DEFAULT_MAX_SIZE`. The threshold doesn't affect the serialized format
+    pub fn with_max_array_size(size: usize) -> Self {
+        Self {
+            max_array_size: size,
+            ..Self::new()
+        }
+    }
+
+    /// The number of values an array container may hold before it's promoted to a bitset
+    #[inline]
+    pub fn max_array_size(&self) -> usize {
+        self.max_array_size
+    }
+
+    /// Change the number of values an array container may hold before it's promoted to a bitset
+    /// 
+    /// # Remarks
+    /// Existing containers are converted to respect the new threshold. 
+    /// Bitmaps built by [`from_parts`] or deserialized use `DEFAULT_MAX_SIZE`, 
+    /// this restores a custom threshold on them
+    /// 
+    /// [`from_parts`]: RoaringBitmap::from_parts
+    pub fn set_max_array_size(&mut self, size: usize) {
+        self.max_array_size = size;
+        self.enforce_max_array_size();
+    }
+
+    /// Convert any containers that don't respect the array threshold
+    fn enforce_max_array_size(&mut self) {
+        let max_array_size = self.max_array_size;
+        for container in self.containers.iter_mut() {
+            container.replace_with(|c| c.into_bounded(max_array_size));
+        }
+    }
+
+    /// Create a new roaring bitmap whose container vectors grow according to `policy`
+    /// 
+    /// # Remarks
+    /// The policy is consulted when `add`, `add_range` and `inplace_or` insert new containers.
+    /// Bitmaps produced by set operations use the default policy
+    pub fn with_growth_policy(policy: GrowthPolicy) -> Self {
+        Self {
+            growth_policy: policy,
+            ..Self::new()
         }
     }
+
+    /// How the container vectors of this bitmap grow when containers are inserted
+    #[inline]
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.growth_policy
+    }
+
+    /// Reserve space for `additional` containers following the growth policy
+    fn reserve_containers(&mut self, additional: usize) {
+        self.growth_policy.reserve(&mut self.containers, additional);
+        self.growth_policy.reserve(&mut self.keys, additional);
+    }
     
     /// Create a new roaring bitmap with the specified range and step
     pub fn from_range<R: RangeBounds<u32>>(range: R) -> Self {
         let (min, max) = range.into_bound();
-        let min = u64::from(min);
-        let max = u64::from(max);
 
         // No elements, just return an empty bitmap
-        if max - min == 0 {
+        if min >= max {
             return Self::new();
         }
 
@@ -93,7 +193,7 @@ impl RoaringBitmap {
             bitmap.containers.push(container);
             bitmap.keys.push(key as u16);
 
-            value += container_max as u64;
+            value = (key + 1) << 16;
         }
 
         bitmap
@@ -107,8 +207,59 @@ impl RoaringBitmap {
         bitmap
     }
 
+    /// Create a new roaring bitmap containing every value within each of the intervals
+    /// 
+    /// # Remarks
+    /// Overlapping and adjacent intervals are coalesced first so no container is filled more than once,
+    /// see [`add_ranges`]. The inclusive intervals returned by [`to_intervals`] are accepted directly
+    /// 
+    /// [`add_ranges`]: RoaringBitmap::add_ranges
+    /// [`to_intervals`]: RoaringBitmap::to_intervals
+    pub fn from_intervals<I, R>(intervals: I) -> Self
+        where I: IntoIterator<Item=R>,
+              R: RangeBounds<u32>
+    {
+        let mut bitmap = Self::new();
+        bitmap.add_ranges(intervals);
+
+        bitmap
+    }
+
+    /// Append the contents of `other` whose values are all greater than those in `self`
+    /// 
+    /// # Remarks
+    /// No merging is performed other than for a container shared by the largest key of `self` 
+    /// and the smallest key of `other`. The containers of `other` are cloned onto the end of `self`
+    pub fn append_bitmap(&mut self, other: &Self) {
+        debug_assert!(
+            match (self.max(), other.min()) {
+                (Some(max), Some(min)) => max < min,
+                _ => true
+            },
+            "Values in `other` must be greater than the values in `self`"
+        );
+
+        self.bounds.invalidate();
+
+        let mut start = 0;
+        if let (Some(last), Some(first)) = (self.keys.last(), other.keys.first()) {
+            if last == first {
+                let index = self.containers.len() - 1;
+                self.containers[index].inplace_or_bounded(&other.containers[0], self.max_array_size);
+
+                start = 1;
+            }
+        }
+
+        let max_array_size = self.max_array_size;
+        self.keys.extend_from_slice(&other.keys[start..]);
+        self.containers.extend(other.containers[start..].iter().map(|c| c.clone().into_bounded(max_array_size)));
+    }
+
     /// Copy the contents of `other` into self overwriting any existing values
     pub fn copy_from(&mut self, other: &RoaringBitmap) {
+        self.bounds.invalidate();
+
         self.containers.clear();
         self.keys.clear();
 
@@ -118,17 +269,20 @@ impl RoaringBitmap {
     
     /// Add a value to the bitmap
     pub fn add(&mut self, value: u32) {
+        self.bounds.invalidate();
+
         let x_high = (value >> 16) as u16;
 
         match self.keys.binary_search(&x_high) {
             Ok(i) => {
-                self.containers[i].add(value as u16)
+                self.containers[i].add_bounded(value as u16, self.max_array_size)
             },
             Err(i) => {
-                let mut array = ArrayContainer::new();
-                array.add(value as u16);
+                let mut container = Container::new();
+                container.add_bounded(value as u16, self.max_array_size);
 
-                self.containers.insert(i, Container::Array(array));
+                self.reserve_containers(1);
+                self.containers.insert(i, container);
                 self.keys.insert(i, x_high);
             }
         }
@@ -144,143 +298,407 @@ impl RoaringBitmap {
         let x_high = (value >> 16) as u16;
 
         if let Ok(i) = self.get_index(x_high) {
-            self.containers[i].add(value as u16);
+            self.containers[i].add_bounded(value as u16, self.max_array_size);
 
             i
         }
         else {
-            let mut array = ArrayContainer::new();
-            array.add(value as u16);
+            let mut container = Container::new();
+            container.add_bounded(value as u16, self.max_array_size);
 
-            self.containers.push(Container::Array(array));
-            self.keys.push(x_high);
+            // Keep the keys sorted, the new key may fall before existing ones
+            let i = self.get_index(x_high).unwrap_err();
+            self.reserve_containers(1);
+            self.containers.insert(i, container);
+            self.keys.insert(i, x_high);
 
-            self.containers.len() - 1
+            i
         }
     }
 
     /// Add a range of values to the bitmap
     pub fn add_range<R: RangeBounds<u32>>(&mut self, range: R) {
+        self.bounds.invalidate();
+
         let (min, max) = range.into_bound();
+        if min >= max {
+            return;
+        }
 
         // Determine keys
-        let min_key = min >> 16;
-        let max_key = max >> 16;
-        let span = (max_key - min_key + 1) as isize;
-        
-        // Determine lengths
-        let prefix_len = array_ops::count_less(&self.keys, min_key as u16) as isize;
-        let suffix_len = array_ops::count_greater(&self.keys, max_key as u16) as isize;
-        let common_len = (self.keys.len() as isize) - prefix_len - suffix_len;
+        let min_key = (min >> 16) as u16;
+        let max_key = ((max - 1) >> 16) as u16;
 
         // Reserve extra space for the new containers
-        let mut len = self.keys.len();
-        if span > common_len {
-            let required = (span - common_len) as usize;
-            self.containers.reserve(required);
-            self.keys.reserve(required);
+        let span = (max_key - min_key) as usize + 1;
+        let common_len = self.get_index_range(min_key, max_key).len();
+        self.reserve_containers(span - common_len);
 
-            len += required;
-        }
+        let start = array_ops::count_less(&self.keys, min_key);
+        for (i, key) in (start..).zip(min_key..=max_key) {
+            let container_min = if min_key == key { (min & 0xFFFF) as u32 } else { 0 };
+            let container_max = if max_key == key { (((max - 1) & 0xFFFF) + 1) as u32 } else { 1 << 16 };
 
-        let mut src: isize = prefix_len + common_len - 1; // isize as this could potentially be -1
-        let mut dst: isize = (len as isize) - suffix_len - 1;
-        for key in (min_key..=max_key).rev() {
-            let container_min = if min_key == key { min & 0xFFFF } else { 0 };
-            let container_max = if max_key == key { max & 0xFFFF } else { 1 << 16 };
+            if i < self.keys.len() && self.keys[i] == key {
+                self.containers[i].add_range_bounded(container_min..container_max, self.max_array_size);
+            }
+            else {
+                let container = Container::from_range(container_min..container_max)
+                    .into_bounded(self.max_array_size);
+                self.containers.insert(i, container);
+                self.keys.insert(i, key);
+            }
+        }
+    }
+    
+    /// Add all values within each of the ranges to the bitmap
+    /// 
+    /// # Remarks
+    /// The ranges are sorted and coalesced before being applied to the bitmap in a single sweep,
+    /// this is faster than calling `add_range` for each range when there are many clustered ranges
+    pub fn add_ranges<I, R>(&mut self, ranges: I)
+        where I: IntoIterator<Item=R>,
+              R: RangeBounds<u32>
+    {
+        self.bounds.invalidate();
+
+        // Widen the bounds so ranges including u32::MAX are representable
+        let mut ranges: Vec<Range<u64>> = ranges.into_iter()
+            .map(|range| {
+                let (min, max) = range.into_bound();
+                min..max
+            })
+            .filter(|range| !range.is_empty())
+            .collect();
+
+        if ranges.is_empty() {
+            return;
+        }
 
-            if src >= 0 && self.keys[src as usize] == key as u16 {
-               let container = &mut self.containers[src as usize];
-               container.add_range(container_min..container_max);
+        ranges.sort_unstable_by_key(|range| range.start);
 
-               src -= 1;
+        // Merge any overlapping or adjacent ranges
+        let mut merged: Vec<Range<u64>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range)
             }
-            else {
-                // TODO: Figure out a way to remove this branch
-                let container = Container::from_range(container_min..container_max);
-                if dst > self.keys.len() as isize {
-                    self.containers.push(container);
-                    self.keys.push(key as u16);
+        }
+
+        let old_keys = mem::take(&mut self.keys);
+        let old_containers = mem::take(&mut self.containers);
+        self.keys.reserve(old_keys.len());
+        self.containers.reserve(old_containers.len());
+
+        let mut old = old_keys.into_iter()
+            .zip(old_containers)
+            .peekable();
+
+        for range in merged {
+            let min_key = (range.start >> 16) as u16;
+            let max_key = ((range.end - 1) >> 16) as u16;
+
+            for key in min_key..=max_key {
+                let container_min = if min_key == key { (range.start & 0xFFFF) as u32 } else { 0 };
+                let container_max = if max_key == key { (((range.end - 1) & 0xFFFF) + 1) as u32 } else { 1 << 16 };
+
+                // Move over any existing containers preceeding this one
+                while let Some((k, c)) = old.next_if(|(k, _c)| *k < key) {
+                    self.keys.push(k);
+                    self.containers.push(c);
+                }
+
+                // A previous range already touched this container
+                if self.keys.last() == Some(&key) {
+                    let last = self.containers.len() - 1;
+                    self.containers[last].add_range_bounded(container_min..container_max, self.max_array_size);
+                }
+                else if let Some((k, mut c)) = old.next_if(|(k, _c)| *k == key) {
+                    c.add_range_bounded(container_min..container_max, self.max_array_size);
+
+                    self.keys.push(k);
+                    self.containers.push(c);
                 }
                 else {
-                    self.containers.insert(dst as usize, container);
-                    self.keys.insert(dst as usize, key as u16);
+                    self.keys.push(key);
+                    let container = Container::from_range(container_min..container_max)
+                        .into_bounded(self.max_array_size);
+                    self.containers.push(container);
                 }
             }
+        }
+
+        for (k, c) in old {
+            self.keys.push(k);
+            self.containers.push(c);
+        }
+    }
+
+    /// Add all values within the inclusive interval [start-end] to the bitmap
+    /// 
+    /// # Remarks
+    /// Containers completely covered by the interval are always stored as a single run
+    pub fn add_interval(&mut self, start: u32, end: u32) {
+        self.bounds.invalidate();
+
+        debug_assert!(start <= end);
+
+        let min_key = (start >> 16) as u16;
+        let max_key = (end >> 16) as u16;
+
+        let max_array_size = self.max_array_size;
+        let first = array_ops::count_less(&self.keys, min_key);
+        for (i, key) in (first..).zip(min_key..=max_key) {
+            let container_min = if min_key == key { start & 0xFFFF } else { 0 };
+            let container_max = if max_key == key { (end & 0xFFFF) + 1 } else { 1 << 16 };
+            let exists = i < self.keys.len() && self.keys[i] == key;
+
+            if !exists {
+                self.keys.insert(i, key);
+                self.containers.insert(i, Container::new());
+            }
+
+            let container = &mut self.containers[i];
+            if container_min == 0 && container_max == (1 << 16) {
+                *container = Container::Run(RunContainer::full());
+            }
+            else if exists {
+                container.add_range_bounded(container_min..container_max, max_array_size);
 
-            dst -= 1;
+                if container.is_full() && !container.is_run() {
+                    *container = Container::Run(RunContainer::full());
+                }
+            }
+            else {
+                *container = Container::from_range(container_min..container_max)
+                    .into_bounded(max_array_size);
+            }
         }
     }
-    
+
     /// Add a list of values to the bitmap
+    /// 
+    /// # Remarks
+    /// Consecutive values sharing a container that are in ascending order are
+    /// built into a container in one pass and merged with a single union.
+    /// Values out of order are added individually
     pub fn add_slice(&mut self, slice: &[u32]) {
-        // Add the first value so we can nab the container index
-        if slice.is_empty() {
-            return;
-        }
+        self.bounds.invalidate();
 
-        let mut value = slice[0];
-        let mut prev = value;
-        let mut i = 1;
-        let mut c_index = self.add_fetch_container(value);
+        let mut i = 0;
 
         while i < slice.len() {
-            value = slice[i];
-            // Check if the upper 16 bits match the previous value, if so the value goes
-            // into the same container and we can just append to that one
-            if (prev ^ value) >> 16 == 0 {
-                self.containers[c_index].add(value as u16);
+            // Find the group of values that belong to the same container
+            let high = slice[i] >> 16;
+            let len = slice[i..].iter()
+                .take_while(|value| *value >> 16 == high)
+                .count();
+
+            let group = &slice[i..(i + len)];
+            if group.len() > 1 && group.windows(2).all(|w| w[0] <= w[1]) {
+                self.add_sorted_group(group);
             }
             else {
-                c_index = self.add_fetch_container(value);
+                let c_index = self.add_fetch_container(group[0]);
+                for value in group[1..].iter() {
+                    self.containers[c_index].add_bounded(*value as u16, self.max_array_size);
+                }
+            }
+
+            i += len;
+        }
+    }
+
+    /// Add a group of ascending values that all belong to the same container
+    fn add_sorted_group(&mut self, group: &[u32]) {
+        let key = (group[0] >> 16) as u16;
+
+        let mut array = ArrayContainer::with_capacity(group.len());
+        let mut prev = None;
+        for value in group.iter() {
+            let low = *value as u16;
+            if prev != Some(low) {
+                // Duplicates are skipped so every value pushed is greater than the last
+                array.push_unchecked(low);
+                prev = Some(low);
+            }
+        }
+
+        let index = self.get_index(key);
+        self.merge_container(index, key, array);
+    }
+
+    /// Union `array` into the container for `key`, where `index` is the result of searching for `key`
+    /// 
+    /// # Returns
+    /// The index of the container holding `key`
+    fn merge_container(&mut self, index: Result<usize, usize>, key: u16, array: ArrayContainer) -> usize {
+        let incoming = Container::Array(array).optimize();
+        let index = match index {
+            Ok(i) => {
+                self.containers[i].inplace_or(&incoming);
+                i
+            },
+            Err(i) => {
+                self.containers.insert(i, incoming);
+                self.keys.insert(i, key);
+                i
+            }
+        };
+
+        // Respect the configured threshold the same way individual adds would
+        if let Container::Array(c) = &self.containers[index] {
+            if c.cardinality() > self.max_array_size {
+                let bitset = BitsetContainer::from(c);
+                self.containers[index] = Container::Bitset(bitset);
+            }
+        }
+
+        index
+    }
+
+    /// Add every value of an ascending stream to the bitmap in a single pass
+    /// 
+    /// # Remarks
+    /// Values are grouped by container and each group is merged with a single union. 
+    /// The containers are walked in step with the stream by galloping forward from the last merged key,
+    /// so no per value search is done. Values out of order are still added correctly but lose this benefit
+    pub fn merge_sorted<I: Iterator<Item=u32>>(&mut self, sorted: I) {
+        self.bounds.invalidate();
+
+        let mut index = 0;
+        let mut key = None;
+        let mut group = Vec::new();
+
+        for value in sorted {
+            let high = (value >> 16) as u16;
+
+            if key != Some(high) {
+                if let Some(key) = key {
+                    index = self.merge_group(index, key, &mut group);
+                }
+
+                key = Some(high);
             }
 
-            prev = value;
-            i += 1;
+            group.push(value as u16);
+        }
+
+        if let Some(key) = key {
+            self.merge_group(index, key, &mut group);
+        }
+    }
+
+    /// Merge the low values of a group sharing `key` into the bitmap, searching forward from `index`.
+    /// The group is emptied afterwards
+    fn merge_group(&mut self, index: usize, key: u16, group: &mut Vec<u16>) -> usize {
+        if !group.windows(2).all(|w| w[0] < w[1]) {
+            group.sort_unstable();
+            group.dedup();
         }
+
+        // The stream went backwards, restart the search from the first container
+        let start = if index < self.keys.len() && self.keys[index] >= key { 0 } else { index };
+        let found = array_ops::advance_until(&self.keys, start, key);
+        let index = if self.keys.get(found) == Some(&key) { Ok(found) } else { Err(found) };
+
+        let mut array = ArrayContainer::new();
+        array.extend_sorted(group);
+        group.clear();
+
+        self.merge_container(index, key, array)
     }
     
     /// Remove a value from the bitmap
     pub fn remove(&mut self, value: u32) {
+        self.bounds.invalidate();
+
         let x_high = (value >> 16) as u16;
         
         if let Ok(i) = self.get_index(x_high) {
-            self.containers[i].remove(value as u16);
+            self.containers[i].remove_bounded(value as u16, self.max_array_size);
             
             if self.containers[i].is_empty() {
-                self.containers.pop();
-                self.keys.pop();
+                self.containers.remove(i);
+                self.keys.remove(i);
             }
         }
     }
 
     /// Remove a range of values from the bitmap
     pub fn remove_range<R: RangeBounds<u32>>(&mut self, range: R) {
+        self.bounds.invalidate();
+
         let (min, max) = range.into_bound();
+        if min >= max {
+            return;
+        }
         
         let min_key = (min >> 16) as u16;
-        let max_key = (max >> 16) as u16;
+        let max_key = ((max - 1) >> 16) as u16;
 
-        let mut i = array_ops::count_less(&self.keys, min_key);
-        while i < self.keys.len() && self.keys[i] <= max_key {
-            let container_min = if min_key == self.keys[i] { min & 0xFFFF } else { 0 };
-            let container_max = if max_key == self.keys[i] { max & 0xFFFF } else { 0xFFFF };
+        // Walk backwards so removing empty containers doesn't shift the ones left to visit
+        for i in self.get_index_range(min_key, max_key).rev() {
+            let key = self.keys[i];
+            let container_min = if min_key == key { (min & 0xFFFF) as u32 } else { 0 };
+            let container_max = if max_key == key { (((max - 1) & 0xFFFF) + 1) as u32 } else { 1 << 16 };
 
             let has_elements = self.containers[i]
-                .remove_range(container_min..(container_max + 1));
+                .remove_range_bounded(container_min..container_max, self.max_array_size);
 
-            if has_elements {
-                i += 1;
-            }
-            else {
+            if !has_elements {
                 self.keys.remove(i);
                 self.containers.remove(i);
             }
         }
     }
+
+    /// Keep only the values outside of `range`, removing everything inside it
+    /// 
+    /// # Remarks
+    /// This is the same as [`remove_range`], the name states the intent when paired with [`keep_inside_range`]
+    /// 
+    /// [`remove_range`]: RoaringBitmap::remove_range
+    /// [`keep_inside_range`]: RoaringBitmap::keep_inside_range
+    pub fn keep_outside_range<R: RangeBounds<u32>>(&mut self, range: R) {
+        self.remove_range(range);
+    }
+
+    /// Keep only the values inside of `range`, removing everything outside it
+    /// 
+    /// # Remarks
+    /// Containers entirely outside of the range are dropped without being visited,
+    /// only the containers at either end of the range are trimmed
+    pub fn keep_inside_range<R: RangeBounds<u32>>(&mut self, range: R) {
+        self.bounds.invalidate();
+
+        let (min, max) = range.into_bound();
+        if min >= max {
+            self.clear();
+            return;
+        }
+
+        let min_key = (min >> 16) as u16;
+        let max_key = ((max - 1) >> 16) as u16;
+
+        let span = self.get_index_range(min_key, max_key);
+        self.keys.truncate(span.end);
+        self.containers.truncate(span.end);
+        self.keys.drain(..span.start);
+        self.containers.drain(..span.start);
+
+        // `min` is at most `u32::MAX` since the range is non empty
+        self.remove_range(..(min as u32));
+        if max <= u64::from(u32::MAX) {
+            self.remove_range((max as u32)..);
+        }
+    }
     
     /// Remove a list of values from the bitmap
     pub fn remove_slice(&mut self, slice: &[u32]) {
+        self.bounds.invalidate();
+
         if slice.is_empty() {
             return;
         }
@@ -299,7 +717,8 @@ impl RoaringBitmap {
                 container.remove(*value as u16);
 
                 if container.is_empty() {
-                    self.containers.pop();
+                    self.containers.remove(index);
+                    self.keys.remove(index);
                     c_index = None;
                 }
             }
@@ -318,75 +737,92 @@ impl RoaringBitmap {
     }
     
     /// Check if the bitmap contains a range of values
+    /// 
+    /// # Remarks
+    /// Every bitmap contains the empty range. Ranges holding a single value, `5..6` or `5..=5`,
+    /// are the same as calling `contains`
     pub fn contains_range<R: RangeBounds<u32>>(&self, range: R) -> bool {
         let (min, max) = range.into_bound();
         
         // We always contain the empty set
-        let len = max - min;
-        if len == 0 {
+        if max <= min {
             return true;
         }
 
-        // Do an optimized single value contains if there's only one element in the set
-        if len == 1 {
-            return self.contains(min);
+        // Do an optimized single value contains if there's only one element in the set.
+        // The bounds are exclusive and 64 bit so this holds for inclusive ranges ending at `u32::MAX` too
+        if max - min == 1 {
+            return self.contains(min as u32);
         }
 
         // Do a ranged contains operation
-        let key_min = min >> 16;
-        let key_max = max >> 16;
-        let key_span = (key_max - key_min) as usize;
+        let key_min = (min >> 16) as u16;
+        let key_max = ((max - 1) >> 16) as u16;
+        let key_span = (key_max - key_min) as usize + 1;
 
-        // Key range exceeds those stored in this bitmap, can't possibly contain the set
-        if self.keys.len() < key_span + 1 {
+        // Every key in the range must be present for the range to be contained
+        let indices = self.get_index_range(key_min, key_max);
+        if indices.len() != key_span {
             return false;
         }
 
-        let ci_min = self.get_index(key_min as u16);
-        let ci_max = self.get_index(key_max as u16);
+        for i in indices {
+            let key = self.keys[i];
+            let container_min = if key_min == key { (min & 0xFFFF) as u32 } else { 0 };
+            let container_max = if key_max == key { (((max - 1) & 0xFFFF) + 1) as u32 } else { 1 << 16 };
 
-        // One or both containers don't exist in this bitmap
-        if ci_min.is_err() || ci_max.is_err() {
-            return false;
+            if !self.containers[i].contains_range(container_min..container_max) {
+                return false;
+            }
         }
 
-        let ci_min = ci_min.unwrap();
-        let ci_max = ci_max.unwrap();
+        // Range is contained in the bitmap
+        true
+    }
+
+    /// Check if the bitmap contains every value in the range
+    /// 
+    /// # Remarks
+    /// This is the same as [`contains_range`]. Ranges follow the usual `RangeBounds` semantics,
+    /// `a..b` excludes `b` while `a..=b` includes it. Every bitmap contains the empty range
+    /// 
+    /// [`contains_range`]: RoaringBitmap::contains_range
+    #[inline]
+    pub fn contains_all<R: RangeBounds<u32>>(&self, range: R) -> bool {
+        self.contains_range(range)
+    }
 
-        // Not enough intermediate keys are present
-        if ci_max - ci_min != key_span {
+    /// Check if the bitmap contains at least one value in the range
+    /// 
+    /// # Remarks
+    /// Equivalent to the bitmap not being disjoint from a bitmap holding the range.
+    /// No bitmap contains any value of the empty range
+    pub fn contains_any<R: RangeBounds<u32>>(&self, range: R) -> bool {
+        let (min, max) = range.into_bound();
+        if min >= max {
             return false;
         }
 
-        let container = &self.containers[ci_min];
-        let value_min = min & 0xFFFF;
-        let value_max = max >> 16;
+        let key_min = (min >> 16) as u16;
+        let key_max = ((max - 1) >> 16) as u16;
 
-        // Min and max are the same, do contains on the single container
-        if key_min == key_max {
-            return container.contains_range(value_min..value_max);
-        }
+        for i in self.get_index_range(key_min, key_max) {
+            let key = self.keys[i];
 
-        // Check if the min container contains [val_min-container_max]
-        if !container.contains_range(value_min..(1 << 16)) {
-            return false;
-        }
+            // Containers are never empty so any container strictly inside the range has a value in it
+            if key != key_min && key != key_max {
+                return true;
+            }
 
-        // Check if the max container contains [container_min-val_max]
-        let container = &self.containers[ci_max];
-        if !container.contains_range(0..value_max) {
-            return false;
-        }
+            let container_min = if key_min == key { (min & 0xFFFF) as u32 } else { 0 };
+            let container_max = if key_max == key { (((max - 1) & 0xFFFF) + 1) as u32 } else { 1 << 16 };
 
-        // Check if all containers in between are full
-        for container in self.containers[(ci_min + 1)..(ci_max - 1)].iter() {
-            if !container.is_full() {
-                return false;
+            if self.containers[i].cardinality_range(container_min..container_max) > 0 {
+                return true;
             }
         }
 
-        // Range is contained in the bitmap
-        true
+        false
     }
 
     /// Get the length of the bitmap
@@ -407,6 +843,99 @@ impl RoaringBitmap {
         cardinality
     }
 
+    /// Get the number of elements stored under each occupied high key, sorted by key
+    pub fn key_histogram(&self) -> Vec<(u16, usize)> {
+        self.keys.iter()
+            .zip(self.containers.iter())
+            .map(|(key, container)| (*key, container.cardinality()))
+            .collect()
+    }
+
+    /// Get the number of values in the bitmap within a range
+    pub fn cardinality_range<R: RangeBounds<u32>>(&self, range: R) -> usize {
+        let (min, max) = range.into_bound();
+        self.cardinality_within(min, max)
+    }
+
+    /// Get the number of values in the bitmap within the exclusive bounds [min-max)
+    fn cardinality_within(&self, min: u64, max: u64) -> usize {
+        if min >= max {
+            return 0;
+        }
+
+        let key_min = (min >> 16) as u16;
+        let key_max = ((max - 1) >> 16) as u16;
+
+        let mut cardinality = 0;
+        for i in self.get_index_range(key_min, key_max) {
+            let key = self.keys[i];
+            let container = &self.containers[i];
+
+            if key != key_min && key != key_max {
+                cardinality += container.cardinality();
+            }
+            else {
+                let container_min = if key_min == key { (min & 0xFFFF) as u32 } else { 0 };
+                let container_max = if key_max == key { (((max - 1) & 0xFFFF) + 1) as u32 } else { 1 << 16 };
+
+                cardinality += container.cardinality_range(container_min..container_max);
+            }
+        }
+
+        cardinality
+    }
+
+    /// Get the number of values within a range that are not in the bitmap
+    /// 
+    /// # Remarks
+    /// This is the cardinality of the complement of the bitmap within `range` computed without building it.
+    /// The result is a `u64` since the complement of an empty bitmap over the full range holds `2^32` values
+    pub fn not_cardinality<R: RangeBounds<u32>>(&self, range: R) -> u64 {
+        let (start, end) = range.into_bound();
+        if start >= end {
+            return 0;
+        }
+
+        (end - start) - self.cardinality_within(start, end) as u64
+    }
+
+    /// Get the number of values the bitmap would hold after adding every value in `range`
+    /// 
+    /// # Remarks
+    /// Computed without modifying or allocating, useful for planning ahead of an `add_range`.
+    /// Returned as a `u64` since the union may span the whole `u32` universe
+    pub fn or_range_cardinality<R: RangeBounds<u32>>(&self, range: R) -> u64 {
+        self.cardinality() as u64 + self.not_cardinality(range)
+    }
+
+    /// Get the fraction of values between the minimum and maximum of the bitmap that are set
+    /// 
+    /// # Remarks
+    /// Returns `0.0` for an empty bitmap
+    pub fn density(&self) -> f64 {
+        match (self.min(), self.max()) {
+            (Some(min), Some(max)) => {
+                let span = u64::from(max - min) + 1;
+
+                self.cardinality() as f64 / span as f64
+            },
+            _ => 0.0
+        }
+    }
+
+    /// Get the average number of bits used per value when the bitmap is serialized
+    /// 
+    /// # Remarks
+    /// Returns `0.0` for an empty bitmap
+    pub fn bits_per_value(&self) -> f64 {
+        let cardinality = self.cardinality();
+        if cardinality == 0 {
+            return 0.0;
+        }
+
+        (self.serialized_size() * 8) as f64 / cardinality as f64
+    }
+
     /// Check if the bitmap is empty
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -416,18 +945,107 @@ impl RoaringBitmap {
     /// Clear the contents of this bitmap
     #[inline]
     pub fn clear(&mut self) {
+        self.bounds.invalidate();
+
         self.containers.clear();
         self.keys.clear();
     }
     
     /// Shrink the memory used by the bitmap to fit it's contents
     pub fn shrink_to_fit(&mut self) {
+        self.bounds.invalidate();
+
         self.containers.shrink_to_fit();
 
         for container in self.containers.iter_mut() {
             container.shrink_to_fit();
         }
     }
+
+    /// Clone the bitmap without carrying over any unused capacity
+    /// 
+    /// # Remarks
+    /// The keys and containers are allocated to their exact length and each container is shrunk,
+    /// giving a copy with the smallest footprint without changing any container representations.
+    /// Useful before storing a bitmap long term or sending it elsewhere
+    pub fn clone_compact(&self) -> Self {
+        let containers = self.containers.iter()
+            .map(|c| {
+                let mut c = c.clone();
+                c.shrink_to_fit();
+                c
+            })
+            .collect();
+
+        Self {
+            containers,
+            keys: self.keys.to_vec(),
+            max_array_size: self.max_array_size,
+            growth_policy: self.growth_policy,
+            bounds: self.bounds.clone()
+        }
+    }
+
+    /// Compact the bitmap to use as little memory as possible
+    ///
+    /// # Remarks
+    /// Every container is shrunk and converted to it's smallest representation,
+    /// run containers have any adjacent runs merged before being evaluated
+    pub fn compact(&mut self) {
+        self.bounds.invalidate();
+
+        for container in self.containers.iter_mut() {
+            container.shrink_to_fit();
+            container.replace_with(|c| c.optimize());
+            container.shrink_to_fit();
+        }
+
+        self.keys.shrink_to_fit();
+        self.containers.shrink_to_fit();
+    }
+
+    /// Remove any containers that are empty or were left as `Container::None`
+    /// 
+    /// # Remarks
+    /// Operations are expected to prune empty containers themselves, this is a defensive cleanup
+    /// restoring that invariant. The relative order of the remaining containers is preserved
+    /// 
+    /// # Returns
+    /// The number of containers removed
+    pub fn trim(&mut self) -> usize {
+        self.bounds.invalidate();
+
+        let len = self.containers.len();
+        let mut write = 0;
+
+        for read in 0..len {
+            let container = &self.containers[read];
+            if container.is_none() || container.is_empty() {
+                continue;
+            }
+
+            self.containers.swap(write, read);
+            self.keys.swap(write, read);
+            write += 1;
+        }
+
+        self.containers.truncate(write);
+        self.keys.truncate(write);
+
+        len - write
+    }
+
+    /// The number of bytes used by the bitmap including it's heap allocations
+    pub fn mem_size(&self) -> usize {
+        let containers: usize = self.containers.iter()
+            .map(|c| c.mem_size())
+            .sum();
+
+        mem::size_of::<Self>()
+            + self.keys.capacity() * mem::size_of::<u16>()
+            + self.containers.capacity() * mem::size_of::<Container>()
+            + containers
+    }
     
     /// Find the element of a given rank in the bitmap,
     /// Returns None if the bitmap is smaller than `rank`
@@ -444,6 +1062,19 @@ impl RoaringBitmap {
 
         None
     }
+
+    /// Find the element of a given rank in the bitmap
+    /// 
+    /// # Returns
+    /// The element or a `SelectError` describing why no element could be selected
+    pub fn try_select(&self, rank: u32) -> Result<u32, SelectError> {
+        if self.is_empty() {
+            return Err(SelectError::Empty);
+        }
+
+        self.select(rank)
+            .ok_or_else(|| SelectError::OutOfRange { cardinality: self.cardinality() })
+    }
     
     /// Find the number of integers smaller or equal to `x`
     pub fn rank(&self, x: u32) -> usize {
@@ -471,7 +1102,23 @@ impl RoaringBitmap {
     }
     
     /// Find the smallest value in the bitmap. Returns None if empty
+    /// 
+    /// # Remarks
+    /// The result is cached until the bitmap is next modified
     pub fn min(&self) -> Option<u32> {
+        BoundsCache::get(&self.bounds.min, || self.find_min())
+    }
+
+    /// Find the largest value in the bitmap. Returns None if empty
+    /// 
+    /// # Remarks
+    /// The result is cached until the bitmap is next modified
+    pub fn max(&self) -> Option<u32> {
+        BoundsCache::get(&self.bounds.max, || self.find_max())
+    }
+
+    /// Find the smallest value in the bitmap bypassing the cache
+    fn find_min(&self) -> Option<u32> {
         if self.containers.is_empty() {
             return None;
         }
@@ -483,8 +1130,8 @@ impl RoaringBitmap {
         Some(low | (u32::from(key) << 16))
     }
     
-    /// Find the largest value in the bitmap. Returns None if empty
-    pub fn max(&self) -> Option<u32> {
+    /// Find the largest value in the bitmap bypassing the cache
+    fn find_max(&self) -> Option<u32> {
         if self.containers.is_empty() {
             return None;
         }
@@ -497,6 +1144,142 @@ impl RoaringBitmap {
         Some(low | (u32::from(key) << 16))
     }
 
+    /// Extract the container for `key` into a standalone bitmap. Returns `None` if the key is absent
+    /// 
+    /// # Remarks
+    /// Only the container for `key` is cloned, making this suitable for sharding a bitmap by key
+    pub fn extract_container(&self, key: u16) -> Option<RoaringBitmap> {
+        let index = self.get_index(key).ok()?;
+
+        Some(RoaringBitmap {
+            containers: vec![self.containers[index].clone()],
+            keys: vec![key],
+            max_array_size: self.max_array_size,
+            growth_policy: self.growth_policy,
+            bounds: BoundsCache::new()
+        })
+    }
+
+    /// Decompose the bitmap into it's sorted keys and the containers for each key
+    /// 
+    /// # Remarks
+    /// The bitmap can be reassembled with [`from_parts`]
+    /// 
+    /// [`from_parts`]: RoaringBitmap::from_parts
+    pub fn into_parts(self) -> (Vec<u16>, Vec<ContainerPart>) {
+        let containers = self.containers.into_iter()
+            .map(ContainerPart)
+            .collect();
+
+        (self.keys, containers)
+    }
+
+    /// Assemble a bitmap from keys and the containers holding the low bits of each key
+    /// 
+    /// # Remarks
+    /// Keys must be sorted and unique, there must be one container per key and no container may be empty
+    pub fn from_parts(keys: Vec<u16>, containers: Vec<ContainerPart>) -> Result<Self, PartsError> {
+        if keys.len() != containers.len() {
+            return Err(PartsError::LengthMismatch { keys: keys.len(), containers: containers.len() });
+        }
+
+        if let Some(index) = keys.windows(2).position(|w| w[0] >= w[1]) {
+            return Err(PartsError::UnsortedKeys(index + 1));
+        }
+
+        if let Some(index) = containers.iter().position(|c| c.0.is_none() || c.0.is_empty()) {
+            return Err(PartsError::EmptyContainer(index));
+        }
+
+        Ok(Self {
+            containers: containers.into_iter().map(|c| c.0).collect(),
+            keys,
+            max_array_size: DEFAULT_MAX_SIZE,
+            growth_policy: GrowthPolicy::default(),
+            bounds: BoundsCache::new()
+        })
+    }
+
+    /// Compute statistics about the runs of consecutive values in the bitmap
+    /// 
+    /// # Remarks
+    /// Useful for deciding whether converting containers to runs is worthwhile before doing so
+    pub fn run_stats(&self) -> RunStats {
+        let mut stats = RunStats::default();
+        let mut cardinality = 0;
+
+        for container in self.containers.iter() {
+            let num_runs = container.num_runs();
+            let container_card = container.cardinality();
+
+            stats.total_runs += num_runs;
+            stats.longest_run = stats.longest_run.max(container.longest_run());
+
+            if container::choose_representation(container_card, num_runs) == ContainerKind::Run {
+                stats.containers_better_as_run += 1;
+            }
+
+            cardinality += container_card;
+        }
+
+        if stats.total_runs > 0 {
+            stats.mean_run_length = cardinality as f64 / stats.total_runs as f64;
+        }
+
+        stats
+    }
+
+    /// Check whether converting containers to runs would reduce the serialized size of the bitmap
+    /// 
+    /// # Remarks
+    /// Every container that `choose_representation` would store as a run is sized as one,
+    /// including the larger header needed once run containers are present.
+    /// The bitmap is not modified, use this to decide whether `compact` is worthwhile
+    pub fn would_run_optimize_help(&self) -> bool {
+        let mut contains_run = false;
+        let mut size = 0;
+
+        for container in self.containers.iter() {
+            let num_runs = container.num_runs();
+
+            if container.is_run() || container::choose_representation(container.cardinality(), num_runs) == ContainerKind::Run {
+                contains_run = true;
+                size += RunContainer::serialized_size(num_runs);
+            }
+            else {
+                size += container.serialized_size();
+            }
+        }
+
+        size += Self::portable_header_size(self.containers.len(), contains_run);
+        size < self.serialized_size()
+    }
+
+    /// Find the smallest value not in the bitmap. Returns None if every `u32` is present
+    /// 
+    /// # Remarks
+    /// Containers are skipped while they are full and contiguous so only the container
+    /// holding the first gap is inspected
+    pub fn min_excluded(&self) -> Option<u32> {
+        for (i, (key, container)) in self.keys.iter().zip(self.containers.iter()).enumerate() {
+            // A container is missing, the first value of it's key is the smallest excluded value
+            if usize::from(*key) != i {
+                return Some((i as u32) << 16);
+            }
+
+            if let Some(low) = container.min_excluded() {
+                return Some((u32::from(*key) << 16) | u32::from(low));
+            }
+        }
+
+        if self.keys.len() < (1 << 16) {
+            Some((self.keys.len() as u32) << 16)
+        }
+        else {
+            None
+        }
+    }
+
     /// Check if this bitmap is a subset of other
     pub fn subset_of(&self, other: &Self) -> bool {
         let len0 = self.containers.len();   // lengths
@@ -511,7 +1294,7 @@ impl RoaringBitmap {
 
             if k0 == k1 {
                 let c0 = &self.containers[i0]; // Containers
-                let c1 = &self.containers[i1];
+                let c1 = &other.containers[i1];
 
                 if !c0.subset_of(c1) {
                     return false;
@@ -532,6 +1315,33 @@ impl RoaringBitmap {
         i0 == len0
     }
 
+    /// Check if this bitmap contains every value in `other`
+    /// 
+    /// # Remarks
+    /// The same as `other.subset_of(self)` but cheap checks on the number of containers, 
+    /// the bounds and the cardinality are done first to reject most non covering bitmaps early.
+    /// Every bitmap contains all of the empty set
+    pub fn contains_all_of(&self, other: &Self) -> bool {
+        if other.is_empty() {
+            return true;
+        }
+
+        if other.containers.len() > self.containers.len() {
+            return false;
+        }
+
+        match (self.min(), self.max(), other.min(), other.max()) {
+            (Some(min0), Some(max0), Some(min1), Some(max1)) if min1 >= min0 && max1 <= max0 => (),
+            _ => return false
+        }
+
+        if other.cardinality() > self.cardinality() {
+            return false;
+        }
+
+        other.subset_of(self)
+    }
+
     /// Compute the Jaccard index between `self` and `other`. 
     /// (Also known as the Tanimoto distance or Jaccard similarity coefficient)
     /// 
@@ -551,18 +1361,40 @@ impl RoaringBitmap {
 
     /// Or this bitmap with `other` (union)
     pub fn or(&self, other: &Self) -> Self {
+        self.or_counted(other, |c0, c1| (c0.or(c1), 0), |_containers| 0).0
+    }
+
+    /// Or this bitmap with `other` (union) and count the values in the result
+    /// 
+    /// # Remarks
+    /// The cardinality of each combined container is taken from the representation check done
+    /// while building it, so the result isn't scanned a second time
+    pub fn or_with_cardinality(&self, other: &Self) -> (Self, usize) {
+        self.or_counted(other, Container::or_with_cardinality, Self::total_cardinality)
+    }
+
+    /// Compute the union of this bitmap and `other`
+    /// 
+    /// # Remarks
+    /// Shared containers are combined with `op`, which also returns the cardinality of it's result.
+    /// Containers carried over unchanged are counted with `count`
+    fn or_counted<F, G>(&self, other: &Self, op: F, count: G) -> (Self, usize)
+        where F: Fn(&Container, &Container) -> (Container, usize),
+              G: Fn(&[Container]) -> usize
+    {
         if self.is_empty() {
-            return other.clone();
+            return (self.adopt_max_array_size(other.clone()), count(&other.containers));
         }
 
         if other.is_empty() {
-            return self.clone();
+            return (self.clone(), count(&self.containers));
         }
 
         let len0 = self.keys.len();
         let len1 = other.keys.len();
 
         let mut result = Self::with_capacity(len0 + len1);
+        let mut cardinality = 0;
         let mut i0 = 0;
         let mut i1 = 0;
 
@@ -573,9 +1405,11 @@ impl RoaringBitmap {
             if k0 == k1 {
                 let c0 = &self.containers[i0];
                 let c1 = &other.containers[i1];
-                let c = c0.or(c1);
+                let (c, c_cardinality) = op(c0, c1);
 
                 if !c.is_empty() {
+                    cardinality += c_cardinality;
+
                     result.containers.push(c);
                     result.keys.push(k0);
                 }
@@ -585,7 +1419,8 @@ impl RoaringBitmap {
             }
             else if k0 < k1 {
                 let c0 = &self.containers[i0];
-                
+                cardinality += count(slice::from_ref(c0));
+
                 result.containers.push(c0.clone());
                 result.keys.push(k0);
 
@@ -593,6 +1428,7 @@ impl RoaringBitmap {
             }
             else {
                 let c1 = &other.containers[i1];
+                cardinality += count(slice::from_ref(c1));
 
                 result.containers.push(c1.clone());
                 result.keys.push(k1);
@@ -602,26 +1438,48 @@ impl RoaringBitmap {
         }
         
         if i0 == len0 {
+            cardinality += count(&other.containers[i1..len1]);
             result.containers.extend_from_slice(&other.containers[i1..len1]);
             result.keys.extend_from_slice(&other.keys[i1..len1]);
         }
         
         if i1 == len1 {
+            cardinality += count(&self.containers[i0..len0]);
             result.containers.extend_from_slice(&self.containers[i0..len0]);
             result.keys.extend_from_slice(&self.keys[i0..len0]);
         }
 
-        result
+        (self.adopt_max_array_size(result), cardinality)
     }
     
     /// And this bitmap with `other` (intersect)
     pub fn and(&self, other: &Self) -> Self {
+        self.and_counted(other, |c0, c1| (c0.and(c1), 0)).0
+    }
+
+    /// And this bitmap with `other` (intersect) and count the values in the result
+    /// 
+    /// # Remarks
+    /// The cardinality of each combined container is taken from the representation check done
+    /// while building it, so the result isn't scanned a second time
+    pub fn and_with_cardinality(&self, other: &Self) -> (Self, usize) {
+        self.and_counted(other, Container::and_with_cardinality)
+    }
+
+    /// Compute the intersection of this bitmap and `other`
+    /// 
+    /// # Remarks
+    /// Shared containers are combined with `op`, which also returns the cardinality of it's result
+    fn and_counted<F>(&self, other: &Self, op: F) -> (Self, usize)
+        where F: Fn(&Container, &Container) -> (Container, usize)
+    {
         let len0 = self.keys.len();
         let len1 = other.keys.len();
 
         let capacity = len0.min(len1);
         let mut result = Self::with_capacity(capacity);
 
+        let mut cardinality = 0;
         let mut i0 = 0;
         let mut i1 = 0;
 
@@ -632,9 +1490,11 @@ impl RoaringBitmap {
             if k0 == k1 {
                 let c0 = &self.containers[i0];
                 let c1 = &other.containers[i1];
-                let c = c0.and(c1);
+                let (c, c_cardinality) = op(c0, c1);
 
                 if !c.is_empty() {
+                    cardinality += c_cardinality;
+
                     result.containers.push(c);
                     result.keys.push(k0);
                 }
@@ -650,23 +1510,97 @@ impl RoaringBitmap {
             }
         }
 
-        result
+        (self.adopt_max_array_size(result), cardinality)
+    }
+
+    /// And this bitmap with `other` (intersect), intersecting the containers in parallel
+    /// 
+    /// # Remarks
+    /// The shared keys are found in a single sequential pass, then each pair of containers is 
+    /// intersected on the rayon thread pool. The result is identical to [`and`]
+    /// 
+    /// [`and`]: RoaringBitmap::and
+    #[cfg(feature = "rayon")]
+    pub fn par_and(&self, other: &Self) -> Self {
+        use rayon::prelude::*;
+
+        let len0 = self.keys.len();
+        let len1 = other.keys.len();
+
+        // Pair up the containers sharing a key
+        let mut pairs = Vec::with_capacity(len0.min(len1));
+        let mut i0 = 0;
+        let mut i1 = 0;
+
+        while i0 < len0 && i1 < len1 {
+            let k0 = self.keys[i0];
+            let k1 = other.keys[i1];
+
+            if k0 == k1 {
+                pairs.push((k0, i0, i1));
+
+                i0 += 1;
+                i1 += 1;
+            }
+            else if k0 < k1 {
+                i0 = array_ops::advance_until(&self.keys, i0, k1);
+            }
+            else {
+                i1 = array_ops::advance_until(&other.keys, i1, k0);
+            }
+        }
+
+        // Collecting a parallel iterator preserves the order of the keys
+        let intersections: Vec<(u16, Container)> = pairs.into_par_iter()
+            .map(|(key, i0, i1)| (key, self.containers[i0].and(&other.containers[i1])))
+            .filter(|(_key, c)| !c.is_empty())
+            .collect();
+
+        let mut result = Self::with_capacity(intersections.len());
+        for (key, c) in intersections {
+            result.keys.push(key);
+            result.containers.push(c);
+        }
+
+        self.adopt_max_array_size(result)
     }
 
     /// And not this bitmap with `other` (difference)
     pub fn and_not(&self, other: &Self) -> Self {
+        self.and_not_counted(other, |c0, c1| (c0.and_not(c1), 0), |_containers| 0).0
+    }
+
+    /// And not this bitmap with `other` (difference) and count the values in the result
+    /// 
+    /// # Remarks
+    /// The cardinality of each combined container is taken from the representation check done
+    /// while building it, so the result isn't scanned a second time
+    pub fn and_not_with_cardinality(&self, other: &Self) -> (Self, usize) {
+        self.and_not_counted(other, Container::and_not_with_cardinality, Self::total_cardinality)
+    }
+
+    /// Compute the difference of this bitmap and `other`
+    /// 
+    /// # Remarks
+    /// Shared containers are combined with `op`, which also returns the cardinality of it's result.
+    /// Containers carried over unchanged are counted with `count`
+    fn and_not_counted<F, G>(&self, other: &Self, op: F, count: G) -> (Self, usize)
+        where F: Fn(&Container, &Container) -> (Container, usize),
+              G: Fn(&[Container]) -> usize
+    {
         if self.is_empty() {
-            return RoaringBitmap::new();
+            return (Self::with_max_array_size(self.max_array_size), 0);
         }
 
         if other.is_empty() {
-            return self.clone();
+            return (self.clone(), count(&self.containers));
         }
 
         let len0 = self.keys.len();
         let len1 = other.keys.len();
         
         let mut result = RoaringBitmap::with_capacity(len0);
+        let mut cardinality = 0;
         let mut i0 = 0;
         let mut i1 = 0;
 
@@ -677,9 +1611,11 @@ impl RoaringBitmap {
             if k0 == k1 {
                 let c0 = &self.containers[i0];
                 let c1 = &other.containers[i1];
-                let c = c0.and_not(c1);
+                let (c, c_cardinality) = op(c0, c1);
 
                 if !c.is_empty() {
+                    cardinality += c_cardinality;
+
                     result.containers.push(c);
                     result.keys.push(k0);
                 }
@@ -689,6 +1625,7 @@ impl RoaringBitmap {
             }
             else if k0 < k1 {
                 let i0_next = array_ops::advance_until(&self.keys, i0, k1);
+                cardinality += count(&self.containers[i0..i0_next]);
 
                 result.containers.extend_from_slice(&self.containers[i0..i0_next]);
                 result.keys.extend_from_slice(&self.keys[i0..i0_next]);
@@ -701,27 +1638,50 @@ impl RoaringBitmap {
         }
 
         if i1 == len1 {
+            cardinality += count(&self.containers[i0..len0]);
             result.containers.extend_from_slice(&self.containers[i0..len0]);
             result.keys.extend_from_slice(&self.keys[i0..len0]);
         }
 
-        result
+        (self.adopt_max_array_size(result), cardinality)
     }
 
-    /// Xor this bitmap with `other` ()
+    /// Xor this bitmap with `other` (symmetric difference)
     pub fn xor(&self, other: &Self) -> Self {
+        self.xor_counted(other, |c0, c1| (c0.xor(c1), 0), |_containers| 0).0
+    }
+
+    /// Xor this bitmap with `other` (symmetric difference) and count the values in the result
+    /// 
+    /// # Remarks
+    /// The cardinality of each combined container is taken from the representation check done
+    /// while building it, so the result isn't scanned a second time
+    pub fn xor_with_cardinality(&self, other: &Self) -> (Self, usize) {
+        self.xor_counted(other, Container::xor_with_cardinality, Self::total_cardinality)
+    }
+
+    /// Compute the symmetric difference of this bitmap and `other`
+    /// 
+    /// # Remarks
+    /// Shared containers are combined with `op`, which also returns the cardinality of it's result.
+    /// Containers carried over unchanged are counted with `count`
+    fn xor_counted<F, G>(&self, other: &Self, op: F, count: G) -> (Self, usize)
+        where F: Fn(&Container, &Container) -> (Container, usize),
+              G: Fn(&[Container]) -> usize
+    {
         if self.is_empty() {
-            return other.clone();
+            return (self.adopt_max_array_size(other.clone()), count(&other.containers));
         }
 
         if other.is_empty() {
-            return self.clone();
+            return (self.clone(), count(&self.containers));
         }
 
         let len0 = self.keys.len();
         let len1 = other.keys.len();
 
         let mut result = Self::with_capacity(len0 + len1);
+        let mut cardinality = 0;
         let mut i0 = 0;
         let mut i1 = 0;
 
@@ -732,9 +1692,11 @@ impl RoaringBitmap {
             if k0 == k1 {
                 let c0 = &self.containers[i0];
                 let c1 = &other.containers[i1];
-                let c = c0.xor(c1);
+                let (c, c_cardinality) = op(c0, c1);
 
                 if !c.is_empty() {
+                    cardinality += c_cardinality;
+
                     result.containers.push(c);
                     result.keys.push(k0);
                 }
@@ -744,7 +1706,8 @@ impl RoaringBitmap {
             }
             else if k0 < k1 {
                 let c0 = &self.containers[i0];
-                
+                cardinality += count(slice::from_ref(c0));
+
                 result.containers.push(c0.clone());
                 result.keys.push(k0);
 
@@ -752,6 +1715,7 @@ impl RoaringBitmap {
             }
             else {
                 let c1 = &other.containers[i1];
+                cardinality += count(slice::from_ref(c1));
 
                 result.containers.push(c1.clone());
                 result.keys.push(k1);
@@ -761,97 +1725,100 @@ impl RoaringBitmap {
         }
         
         if i0 == len0 {
+            cardinality += count(&other.containers[i1..len1]);
             result.containers.extend_from_slice(&other.containers[i1..len1]);
             result.keys.extend_from_slice(&other.keys[i1..len1]);
         }
         
         if i1 == len1 {
+            cardinality += count(&self.containers[i0..len0]);
             result.containers.extend_from_slice(&self.containers[i0..len0]);
             result.keys.extend_from_slice(&self.keys[i0..len0]);
         }
 
+        (self.adopt_max_array_size(result), cardinality)
+    }
+
+    /// Give `result` the array threshold of this bitmap, converting any containers that don't respect it
+    fn adopt_max_array_size(&self, mut result: Self) -> Self {
+        result.set_max_array_size(self.max_array_size);
         result
     }
 
+    /// Sum the cardinality of `containers`
+    #[inline]
+    fn total_cardinality(containers: &[Container]) -> usize {
+        containers.iter()
+            .map(|c| c.cardinality())
+            .sum()
+    }
+
     /// Negate all elements within `range` in this bitmap
     pub fn not<R: RangeBounds<u32>>(&self, range: R) -> Self {
         let (min, max) = range.into_bound();
-        if min == max {
-            return Self::new();
-        }
 
-        let mut start_high = min >> 16;
-        let start_low = min & 0xFFFF;
+        // Nothing to flip, the result is the bitmap unchanged
+        if min >= max {
+            return self.clone();
+        }
 
-        let mut end_high = max >> 16;
-        let end_low = max & 0xFFFF;
+        let min_key = (min >> 16) as u16;
+        let max_key = ((max - 1) >> 16) as u16;
+        let span = usize::from(max_key - min_key) + 1;
 
-        let mut result = Self::new();
+        let mut result = Self::with_capacity(self.keys.len() + span);
 
         // Append all preceding elements that are not to be flipped
-        let end = array_ops::advance_until(&self.keys, 0, start_high as u16);
-        result.containers.extend_from_slice(&self.containers[..end]);
-        result.keys.extend_from_slice(&self.keys[..end]);
-
-        // Range occupies the same container, just flip that
-        if start_high == end_high {
-            result.append_flipped(self, start_high as u16, start_low..end_low);
-        }
-        // Else flip a cross container range
-        else {
-            // Handle a partial start container
-            if start_low > 0 {
-                result.append_flipped(self, start_high as u16, start_low..(1 << 16));
-
-                start_high += 1;
-            }
-
-            if end_low != 0xFFFF {
-                end_high -= 1;
-            }
+        let start = array_ops::count_less(&self.keys, min_key);
+        result.containers.extend_from_slice(&self.containers[..start]);
+        result.keys.extend_from_slice(&self.keys[..start]);
+
+        // Flip every container in the range, creating full ones for missing keys
+        let mut i = start;
+        for key in min_key..=max_key {
+            let container_min = if min_key == key { (min & 0xFFFF) as u32 } else { 0 };
+            let container_max = if max_key == key { (((max - 1) & 0xFFFF) + 1) as u32 } else { 1 << 16 };
+            let range = container_min..container_max;
+
+            if i < self.keys.len() && self.keys[i] == key {
+                let flipped = self.containers[i].not(range);
+                if !flipped.is_empty() {
+                    result.containers.push(flipped);
+                    result.keys.push(key);
+                }
 
-            // Handle all containers in the middle of the range skipping the last container
-            for bound in start_high..=end_high {
-                result.append_flipped(self, bound as u16, 0..(1 << 16));
+                i += 1;
             }
-
-            // Handle a partial final container
-            if end_low != 0xFFFF {
-                end_high += 1;
-
-                result.append_flipped(self, end_high as u16, 0..end_low);
+            else {
+                result.containers.push(Container::from_range(range));
+                result.keys.push(key);
             }
         }
 
         // Append any remaining containers
-        if let Ok(mut i_last) = self.get_index(end_high as u16) {
-            i_last += 1; // Increment to get the next container after the last flipped one
+        result.containers.extend_from_slice(&self.containers[i..]);
+        result.keys.extend_from_slice(&self.keys[i..]);
 
-            if i_last < self.containers.len() {
-                result.containers.extend_from_slice(&self.containers[i_last..]);
-                result.keys.extend_from_slice(&self.keys[i_last..]);
-            }
-        }
-
-        result
+        self.adopt_max_array_size(result)
     }
 
-    /// Insert the negation of the container within `range` with the given key.
-    /// Creates a new full container if no container is found
-    fn append_flipped(&mut self, other: &Self, key: u16, range: Range<u32>) {
-        if let Ok(i) = other.get_index(key) {
-            let unflipped = &other.containers[i];
-            let flipped = unflipped.not(range);
-
-            if !flipped.is_empty() {
-                self.containers.push(flipped);
-                self.keys.push(key);
-            }
-        }
-        else {
-            let c = Container::from_range(range);
-            self.containers.push(c);
-            self.keys.push(key);
+    /// Find all values between the smallest and largest value that are not in this bitmap
+    /// 
+    /// # Remarks
+    /// Unlike [`not`] the complement is restricted to the occupied span of the set,
+    /// the result is empty if the bitmap is empty
+    /// 
+    /// [`not`]: RoaringBitmap::not
+    pub fn gaps(&self) -> Self {
+        match (self.min(), self.max()) {
+            (Some(min), Some(max)) => {
+                // Flip up to but excluding the max so that `u32::MAX` can't overflow the bound,
+                // the unflipped max is then the last value in the result
+                let mut gaps = self.not(min..max);
+                gaps.remove(max);
+                gaps
+            },
+            _ => Self::new()
         }
     }
 
@@ -859,6 +1826,8 @@ impl RoaringBitmap {
     /// 
     /// [`or`]: RoaringBitmap::or
     pub fn inplace_or(&mut self, other: &Self) {
+        self.bounds.invalidate();
+
         // Other is the empty set, self is unchanged
         if other.is_empty() {
             return;
@@ -866,10 +1835,13 @@ impl RoaringBitmap {
 
         // Self is the empty set, copy all of other
         if self.is_empty() {
+            self.reserve_containers(other.keys.len());
             self.copy_from(other);
+            self.enforce_max_array_size();
             return;
         }
 
+        let max_array_size = self.max_array_size;
         let mut len0    = self.keys.len();
         let len1        = other.keys.len();
 
@@ -877,14 +1849,14 @@ impl RoaringBitmap {
         let mut i0 = 0;
         let mut i1 = 0;
         let mut k0 = self.keys[i0];
-        let mut k1 = self.keys[i1];
+        let mut k1 = other.keys[i1];
 
         loop {
             if k0 == k1 {
                 let c0 = &mut self.containers[i0];
 
                 if !c0.is_full() {
-                    c0.inplace_or(&other.containers[i1]);
+                    c0.inplace_or_bounded(&other.containers[i1], max_array_size);
                 }
 
                 i0 += 1;
@@ -894,7 +1866,7 @@ impl RoaringBitmap {
                 if i1 == len1 { break; }
 
                 k0 = self.keys[i0];
-                k1 = self.keys[i1];
+                k1 = other.keys[i1];
             }
             else if k0 < k1 {
                 i0 += 1;
@@ -904,7 +1876,9 @@ impl RoaringBitmap {
                 k0 = self.keys[i0];
             }
             else {
-                let c1 = other.containers[i1].clone();
+                let c1 = other.containers[i1].clone()
+                    .into_bounded(max_array_size);
+                self.reserve_containers(1);
                 self.containers.insert(i0, c1);
                 self.keys.insert(i0, k1);
 
@@ -914,13 +1888,14 @@ impl RoaringBitmap {
 
                 if i1 == len1 { break; }
                 
-                k1 = self.keys[i1];
+                k1 = other.keys[i1];
             }
         }
 
         // Copy in the remaining contents from other
         if i0 == len0 {
-            self.containers.extend_from_slice(&other.containers[i1..]);
+            self.reserve_containers(len1 - i1);
+            self.containers.extend(other.containers[i1..].iter().map(|c| c.clone().into_bounded(max_array_size)));
             self.keys.extend_from_slice(&other.keys[i1..])
         }
     }
@@ -929,6 +1904,9 @@ impl RoaringBitmap {
     /// 
     /// [`and`]: RoaringBitmap::and
     pub fn inplace_and(&mut self, other: &Self) {
+        self.bounds.invalidate();
+
+        let max_array_size = self.max_array_size;
         let mut i0 = 0;
         let mut i1 = 0;
 
@@ -941,7 +1919,7 @@ impl RoaringBitmap {
                     let c0 = &mut self.containers[i0];
                     let c1 = &other.containers[i1];
 
-                    c0.inplace_and(c1);
+                    c0.inplace_and_bounded(c1, max_array_size);
                     c0.is_empty()
                 };
                 
@@ -958,10 +1936,9 @@ impl RoaringBitmap {
             else if k0 < k1 {
                 let iend = array_ops::advance_until(&self.keys, i0, k1);
 
+                // The following containers shift down to `i0` so it doesn't need to advance
                 self.containers.drain(i0..iend);
                 self.keys.drain(i0..iend);
-
-                i0 = iend;
             }
             // Skip past all elements not in ourself
             else {
@@ -983,11 +1960,14 @@ impl RoaringBitmap {
     /// 
     /// [`and_not`]: RoaringBitmap::and_not
     pub fn inplace_and_not(&mut self, other: &Self) {
+        self.bounds.invalidate();
+
         // If either is the empty set then there are no chanegs to be made
         if self.is_empty() || other.is_empty() {
             return;
         }
         
+        let max_array_size = self.max_array_size;
         let mut len0 = self.keys.len();
         let len1 = other.keys.len();
         
@@ -1004,7 +1984,7 @@ impl RoaringBitmap {
                     let c0 = &mut self.containers[i0];
                     let c1 = &other.containers[i1];
 
-                    c0.inplace_and_not(c1);
+                    c0.inplace_and_not_bounded(c1, max_array_size);
                     c0.is_empty()
                 };
                 
@@ -1035,6 +2015,8 @@ impl RoaringBitmap {
     /// 
     /// [`xor`]: RoaringBitmap::xor
     pub fn inplace_xor(&mut self, other: &Self) {
+        self.bounds.invalidate();
+
         // No items in other, self is unchanged
         if other.is_empty() {
             return;
@@ -1044,9 +2026,11 @@ impl RoaringBitmap {
         if self.is_empty() {
             self.clear();
             self.copy_from(other);
+            self.enforce_max_array_size();
             return;
         }
 
+        let max_array_size = self.max_array_size;
         let mut len0 = self.keys.len();
         let len1 = other.keys.len();
 
@@ -1061,12 +2045,13 @@ impl RoaringBitmap {
                     let c0 = &mut self.containers[i0];
                     let c1 = &other.containers[i1];
 
-                    c0.inplace_xor(c1);
+                    c0.inplace_xor_bounded(c1, max_array_size);
                     c0.is_empty()
                 };
 
                 if is_empty {
                     self.containers.remove(i0);
+                    self.keys.remove(i0);
 
                     len0 -= 1;
                 }
@@ -1082,7 +2067,7 @@ impl RoaringBitmap {
             else {
                 let c1 = &other.containers[i1];
 
-                self.containers.insert(i0, c1.clone());
+                self.containers.insert(i0, c1.clone().into_bounded(max_array_size));
                 self.keys.insert(i0, k1);
 
                 i0 += 1;
@@ -1092,60 +2077,160 @@ impl RoaringBitmap {
         }
 
         if i0 == len0 {
-            self.containers.extend_from_slice(&other.containers[i1..]);
+            self.containers.extend(other.containers[i1..].iter().map(|c| c.clone().into_bounded(max_array_size)));
             self.keys.extend_from_slice(&other.keys[i1..]);
         }
     }
 
+    /// Same as [`inplace_or`] but consumes `other`
+    /// 
+    /// # Remarks
+    /// Containers only present in `other` are moved into `self` instead of being cloned
+    /// 
+    /// [`inplace_or`]: RoaringBitmap::inplace_or
+    pub fn inplace_or_owned(&mut self, other: Self) {
+        let max_array_size = self.max_array_size;
+        self.merge_owned(other, |c0, c1| c0.inplace_or_bounded(c1, max_array_size));
+    }
+
+    /// Same as [`inplace_and`] but consumes `other`
+    /// 
+    /// # Remarks
+    /// Provided for symmetry with [`inplace_or_owned`], no containers of `other` can be reused
+    /// 
+    /// [`inplace_and`]: RoaringBitmap::inplace_and
+    /// [`inplace_or_owned`]: RoaringBitmap::inplace_or_owned
+    pub fn inplace_and_owned(&mut self, other: Self) {
+        self.inplace_and(&other);
+    }
+
+    /// Same as [`inplace_and_not`] but consumes `other`
+    /// 
+    /// # Remarks
+    /// Provided for symmetry with [`inplace_or_owned`], no containers of `other` can be reused
+    /// 
+    /// [`inplace_and_not`]: RoaringBitmap::inplace_and_not
+    /// [`inplace_or_owned`]: RoaringBitmap::inplace_or_owned
+    pub fn inplace_and_not_owned(&mut self, other: Self) {
+        self.inplace_and_not(&other);
+    }
+
+    /// Same as [`inplace_xor`] but consumes `other`
+    /// 
+    /// # Remarks
+    /// Containers only present in `other` are moved into `self` instead of being cloned
+    /// 
+    /// [`inplace_xor`]: RoaringBitmap::inplace_xor
+    pub fn inplace_xor_owned(&mut self, other: Self) {
+        let max_array_size = self.max_array_size;
+        self.merge_owned(other, |c0, c1| c0.inplace_xor_bounded(c1, max_array_size));
+    }
+
+    /// Merge the containers of `other` into `self`, moving any containers that aren't shared.
+    /// Shared containers are combined with `op` and dropped if they become empty
+    fn merge_owned<F>(&mut self, other: Self, op: F) 
+        where F: Fn(&mut Container, &Container)
+    {
+        self.bounds.invalidate();
+
+        // Nothing to merge, or steal everything if self is empty
+        if other.is_empty() {
+            return;
+        }
+
+        // Only the contents are taken, self keeps it's own configuration
+        if self.is_empty() {
+            self.keys = other.keys;
+            self.containers = other.containers;
+            self.bounds = other.bounds;
+            self.enforce_max_array_size();
+            return;
+        }
+
+        let max_array_size = self.max_array_size;
+
+        let len = self.containers.len() + other.containers.len();
+        let keys = mem::replace(&mut self.keys, Vec::with_capacity(len));
+        let containers = mem::replace(&mut self.containers, Vec::with_capacity(len));
+
+        let mut iter0 = keys.into_iter().zip(containers).peekable();
+        let mut iter1 = other.keys.into_iter().zip(other.containers).peekable();
+
+        while let (Some((k0, _)), Some((k1, _))) = (iter0.peek(), iter1.peek()) {
+            let (k0, k1) = (*k0, *k1);
+
+            let (key, container) = {
+                if k0 < k1 {
+                    iter0.next().unwrap()
+                }
+                else if k1 < k0 {
+                    let (key, c1) = iter1.next().unwrap();
+                    (key, c1.into_bounded(max_array_size))
+                }
+                else {
+                    let (key, mut c0) = iter0.next().unwrap();
+                    let (_, c1) = iter1.next().unwrap();
+
+                    op(&mut c0, &c1);
+
+                    if c0.is_empty() {
+                        continue;
+                    }
+
+                    (key, c0)
+                }
+            };
+
+            self.keys.push(key);
+            self.containers.push(container);
+        }
+
+        // Move over whatever remains, at most one of these is non empty
+        for (key, container) in iter0.chain(iter1) {
+            self.keys.push(key);
+            self.containers.push(container.into_bounded(max_array_size));
+        }
+    }
+
     /// Same as [`not`] but operates in place on `self`
     /// 
     /// [`not`]: RoaringBitmap::not
     pub fn inplace_not<R: RangeBounds<u32>>(&mut self, range: R) {
-        let (min, max) = range.into_bound();
-        let mut high_start = (min >> 16) as u16;
-        let mut high_end = (max >> 16) as u16;
-        let low_start = min & 0xFFFF;
-        let low_end = max & 0xFFFF;
+        self.bounds.invalidate();
 
-        // Keys are the same, just do it in place
-        if high_start == high_end {
-            self.inplace_flip(high_start, low_start..low_end);
+        let (min, max) = range.into_bound();
+        if min >= max {
+            return;
         }
-        else {
-            // First container is a partial one, flip in place
-            if low_start > 0 {
-                self.inplace_flip(high_start, low_start..(1 << 16));
-                high_start += 1;
-            }
 
-            if low_end != 0xFFFF {
-                high_end -= 1;
-            }
+        let min_key = (min >> 16) as u16;
+        let max_key = ((max - 1) >> 16) as u16;
 
-            for bound in high_start..=high_end {
-                self.inplace_flip(bound, 0..(1 << 16));
-            }
+        for key in min_key..=max_key {
+            let container_min = if min_key == key { (min & 0xFFFF) as u32 } else { 0 };
+            let container_max = if max_key == key { (((max - 1) & 0xFFFF) + 1) as u32 } else { 1 << 16 };
 
-            // End is a partial container, flip in place
-            if low_end != 0xFFFF {
-                self.inplace_flip(high_end + 1, 0..low_end);
-            }
+            self.inplace_flip(key, container_min..container_max);
         }
     }
 
     fn inplace_flip(&mut self, key: u16, range: Range<u32>) {
         match self.get_index(key) {
             Ok(index) => {
-                // Swap the container at `index` for `None`
-                let c = mem::replace(&mut self.containers[index], Container::None);
-
                 // Operate on the container and swap back into the slot at `index`
-                let c = c.inplace_not(range);
-                self.containers[index] = c;
+                let max_array_size = self.max_array_size;
+                self.containers[index].replace_with(|c| c.inplace_not(range).into_bounded(max_array_size));
+
+                if self.containers[index].is_empty() {
+                    self.containers.remove(index);
+                    self.keys.remove(index);
+                }
             },
             Err(index) => {
                 self.keys.insert(index, key);
-                self.containers.insert(index, Container::from_range(range));
+                let container = Container::from_range(range)
+                    .into_bounded(self.max_array_size);
+                self.containers.insert(index, container);
             }
         }
     }
@@ -1155,11 +2240,42 @@ impl RoaringBitmap {
     /// # Remarks
     /// This only computes cardinality in place, no allocations are made
     pub fn or_cardinality(&self, other: &Self) -> usize {
-        let c0 = self.cardinality();
-        let c1 = other.cardinality();
-        let shared = self.and_cardinality(other);
+        self.merged_cardinality(other, Container::or_cardinality)
+    }
+
+    /// Sum the cardinality of a union like operation in a single pass over the keys.
+    /// Shared containers are counted with `op`, containers only in one bitmap are counted in full
+    fn merged_cardinality<F>(&self, other: &Self, op: F) -> usize 
+        where F: Fn(&Container, &Container) -> usize
+    {
+        let len0 = self.containers.len();
+        let len1 = other.containers.len();
+
+        let mut result = 0;
+        let mut i0 = 0;
+        let mut i1 = 0;
+
+        while i0 < len0 && i1 < len1 {
+            let k0 = self.keys[i0];
+            let k1 = other.keys[i1];
+
+            if k0 == k1 {
+                result += op(&self.containers[i0], &other.containers[i1]);
+
+                i0 += 1;
+                i1 += 1;
+            }
+            else if k0 < k1 {
+                result += self.containers[i0].cardinality();
+                i0 += 1;
+            }
+            else {
+                result += other.containers[i1].cardinality();
+                i1 += 1;
+            }
+        }
 
-        c0 + c1 - shared
+        result + Self::total_cardinality(&self.containers[i0..]) + Self::total_cardinality(&other.containers[i1..])
     }
 
     /// Compute the cardinality of `and` on `self` and `other` without storing the result
@@ -1198,6 +2314,124 @@ impl RoaringBitmap {
         result
     }
 
+    /// Count the number of keys shared between `self` and `other`
+    ///
+    /// # Remarks
+    /// This is the number of container pairs `and` would have to intersect
+    /// and can be used to estimate the cost of the operation before performing it
+    pub fn and_touched_keys(&self, other: &Self) -> usize {
+        let len0 = self.keys.len();
+        let len1 = other.keys.len();
+
+        let mut result = 0;
+        let mut i0 = 0;
+        let mut i1 = 0;
+
+        while i0 < len0 && i1 < len1 {
+            let k0 = self.keys[i0];
+            let k1 = other.keys[i1];
+
+            if k0 == k1 {
+                result += 1;
+
+                i0 += 1;
+                i1 += 1;
+            }
+            else if k0 < k1 {
+                i0 = array_ops::advance_until(&self.keys, i0, k1);
+            }
+            else {
+                i1 = array_ops::advance_until(&other.keys, i1, k0);
+            }
+        }
+
+        result
+    }
+
+    /// Estimate the cardinality of `and` on `self` and `other` without intersecting any containers
+    /// 
+    /// # Remarks
+    /// This is an estimate and not an exact count. Values within each shared container are assumed to be
+    /// independent and uniformly distributed, so each pair of containers contributes `c0 * c1 / 2^16`.
+    /// Correlated inputs such as a bitmap and a subset of it are underestimated.
+    /// Only the container cardinalities are read, making it suitable for cost based query planning
+    pub fn estimated_and_cardinality(&self, other: &Self) -> usize {
+        let len0 = self.keys.len();
+        let len1 = other.keys.len();
+
+        let mut result = 0;
+        let mut i0 = 0;
+        let mut i1 = 0;
+
+        while i0 < len0 && i1 < len1 {
+            let k0 = self.keys[i0];
+            let k1 = other.keys[i1];
+
+            if k0 == k1 {
+                let c0 = self.containers[i0].cardinality() as u64;
+                let c1 = other.containers[i1].cardinality() as u64;
+
+                result += c0 * c1;
+
+                i0 += 1;
+                i1 += 1;
+            }
+            else if k0 < k1 {
+                i0 = array_ops::advance_until(&self.keys, i0, k1);
+            }
+            else {
+                i1 = array_ops::advance_until(&other.keys, i1, k0);
+            }
+        }
+
+        // Scale once at the end so sparse containers aren't each rounded down to nothing
+        (result >> 16) as usize
+    }
+
+    /// Check if `and` on `self` and `other` would produce an empty bitmap without computing it
+    /// 
+    /// # Remarks
+    /// Returns as soon as any shared container pair intersects
+    pub fn and_is_empty(&self, other: &Self) -> bool {
+        let len0 = self.keys.len();
+        let len1 = other.keys.len();
+
+        let mut i0 = 0;
+        let mut i1 = 0;
+
+        while i0 < len0 && i1 < len1 {
+            let k0 = self.keys[i0];
+            let k1 = other.keys[i1];
+
+            if k0 == k1 {
+                if self.containers[i0].intersects(&other.containers[i1]) {
+                    return false;
+                }
+
+                i0 += 1;
+                i1 += 1;
+            }
+            else if k0 < k1 {
+                i0 = array_ops::advance_until(&self.keys, i0, k1);
+            }
+            else {
+                i1 = array_ops::advance_until(&other.keys, i1, k0);
+            }
+        }
+
+        true
+    }
+
+    /// Check if `and_not` on `self` and `other` would produce an empty bitmap without computing it
+    /// 
+    /// # Remarks
+    /// This is equivalent to `self` being a subset of `other` and returns as soon
+    /// as a value of `self` is found missing from `other`
+    #[inline]
+    pub fn and_not_is_empty(&self, other: &Self) -> bool {
+        self.subset_of(other)
+    }
+
     /// Compute the cardinality of `and_not` on `self` and `other` without storing the result
     /// 
     /// # Remarks
@@ -1209,16 +2443,86 @@ impl RoaringBitmap {
         c0 - shared
     }
 
+    /// Compute the cardinality of `and_not` on `self` and `other` for each container of `self`
+    /// 
+    /// # Remarks
+    /// Useful for diagnosing which blocks of values a filter removes. 
+    /// Every key of `self` is reported, including those where no values survive
+    /// 
+    /// # Returns
+    /// The key of each container paired with the number of it's values not in `other`, in ascending key order
+    pub fn and_not_cardinality_by_key(&self, other: &Self) -> Vec<(u16, usize)> {
+        let mut result = Vec::with_capacity(self.keys.len());
+        let mut i1 = 0;
+
+        for (key, container) in self.keys.iter().zip(self.containers.iter()) {
+            i1 = array_ops::advance_until(&other.keys, i1, *key);
+
+            let count = match other.keys.get(i1) {
+                Some(k1) if k1 == key => container.and_not_cardinality(&other.containers[i1]),
+                _ => container.cardinality()
+            };
+
+            result.push((*key, count));
+        }
+
+        result
+    }
+
     /// Compute the cardinality of `xor` on `self` and `other` without storing the result
     /// 
     /// # Remarks
     /// This computes cardinality in place, no allocations are made
     pub fn xor_cardinality(&self, other: &Self) -> usize {
-        let c0 = self.cardinality();
-        let c1 = other.cardinality();
-        let shared = self.and_cardinality(other);
+        self.merged_cardinality(other, Container::xor_cardinality)
+    }
+
+    /// Compute the number of values only in `self` and the number of values only in `other`
+    /// 
+    /// # Remarks
+    /// Both counts are found in a single pass over the keys, where computing `and_not_cardinality`
+    /// in both directions would intersect every shared container twice
+    /// 
+    /// # Returns
+    /// A tuple of `(self_only, other_only)`
+    pub fn exclusive_cardinalities(&self, other: &Self) -> (usize, usize) {
+        let len0 = self.containers.len();
+        let len1 = other.containers.len();
+
+        let mut self_only = 0;
+        let mut other_only = 0;
+        let mut i0 = 0;
+        let mut i1 = 0;
+
+        while i0 < len0 && i1 < len1 {
+            let k0 = self.keys[i0];
+            let k1 = other.keys[i1];
+
+            if k0 == k1 {
+                let c0 = &self.containers[i0];
+                let c1 = &other.containers[i1];
+                let shared = c0.and_cardinality(c1);
+
+                self_only += c0.cardinality() - shared;
+                other_only += c1.cardinality() - shared;
+
+                i0 += 1;
+                i1 += 1;
+            }
+            else if k0 < k1 {
+                self_only += self.containers[i0].cardinality();
+                i0 += 1;
+            }
+            else {
+                other_only += other.containers[i1].cardinality();
+                i1 += 1;
+            }
+        }
 
-        c0 + c1 - 2 * shared
+        self_only += Self::total_cardinality(&self.containers[i0..]);
+        other_only += Self::total_cardinality(&other.containers[i1..]);
+
+        (self_only, other_only)
     }
 
     /// Find the index for a given key
@@ -1226,6 +2530,20 @@ impl RoaringBitmap {
     fn get_index(&self, x: u16) -> Result<usize, usize> {
         self.keys.binary_search(&x)
     }
+
+    /// Get the span of container indices whose keys are within [key_min-key_max]
+    /// 
+    /// # Remarks
+    /// The span is empty if no keys are within the range
+    #[inline]
+    fn get_index_range(&self, key_min: u16, key_max: u16) -> Range<usize> {
+        debug_assert!(key_min <= key_max);
+
+        let start = array_ops::count_less(&self.keys, key_min);
+        let end = self.keys.len() - array_ops::count_greater(&self.keys, key_max);
+
+        start..end
+    }
     
     /// Get an iterator over the values of the bitmap
     pub fn iter(&self) -> Iter {
@@ -1245,517 +2563,3684 @@ impl RoaringBitmap {
             index: 0
         }
     }
-}
 
-// Serialization
+    /// Fold every value in the bitmap into an accumulator in ascending order
+    ///
+    /// # Remarks
+    /// This iterates the containers directly which avoids the overhead of [`iter`]
+    /// and is preferable for reductions such as sums or counts
+    ///
+    /// [`iter`]: RoaringBitmap::iter
+    pub fn fold<B, F>(&self, init: B, mut f: F) -> B
+        where F: FnMut(B, u32) -> B
+    {
+        let mut acc = init;
+        for (key, container) in self.keys.iter().zip(self.containers.iter()) {
+            let base = u32::from(*key) << 16;
+
+            acc = container.fold(acc, |acc, value| f(acc, base | u32::from(value)));
+        }
 
-/// An error that occured while deserializing a bitmap
-#[derive(Debug)]
-pub enum DeserializeError {
-    /// An invalid cookie was detected. This is likely not a bitmap. Contains the found value
-    InvalidCookie(u32),
+        acc
+    }
 
-    /// An invalid container count was detected. Contains the value found
-    InvalidContainerCount(u32),
+    /// Get the maximal intervals of consecutive values in the bitmap as inclusive ranges in ascending order
+    /// 
+    /// # Remarks
+    /// Intervals spanning multiple containers are merged. Run containers contribute their runs directly.
+    /// The ranges are inclusive so an interval ending at `u32::MAX` is representable
+    pub fn to_intervals(&self) -> Vec<RangeInclusive<u32>> {
+        // Track the bounds as u64 so the interval ending at u32::MAX doesn't overflow
+        let mut intervals: Vec<(u64, u64)> = Vec::new();
+        let push = |intervals: &mut Vec<(u64, u64)>, start: u64, end: u64| {
+            match intervals.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ => intervals.push((start, end))
+            }
+        };
 
-    /// An IO error occured during deserialization, Contains the underlying error
-    IoError(io::Error)
-}
+        let pass = self.keys.iter()
+            .zip(self.containers.iter());
 
-impl fmt::Display for DeserializeError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            DeserializeError::InvalidCookie(cookie) => write!(f, "Invalid cookie: {}", cookie),
-            DeserializeError::InvalidContainerCount(count) => write!(f, "Invalid container count: {}", count),
-            DeserializeError::IoError(err) => write!(f, "{}", err)
+        for (key, container) in pass {
+            let base = u64::from(*key) << 16;
+
+            if let Container::Run(c) = container {
+                for run in c.iter_runs() {
+                    let range = run.into_range();
+                    push(&mut intervals, base + u64::from(range.start), base + u64::from(range.end));
+                }
+            }
+            else {
+                intervals = container.fold(intervals, |mut intervals, value| {
+                    let value = base + u64::from(value);
+                    push(&mut intervals, value, value + 1);
+
+                    intervals
+                });
+            }
         }
+
+        intervals.into_iter()
+            .map(|(start, end)| (start as u32)..=((end - 1) as u32))
+            .collect()
     }
-}
 
-impl RoaringBitmap {
-    // Constants denoted by the roaring bitmap format spec.
-    // See https://github.com/RoaringBitmap/RoaringFormatSpec for details
-    const SERIAL_COOKIE_NO_RUNCONTAINER: u32 = 12346;
-    const SERIAL_COOKIE: u32 = 12347;
-    const NO_OFFSET_THRESHOLD: u32 = 4;
+    /// Build a dictionary mapping values in the bitmap to their dense 0 based rank
+    /// 
+    /// # Remarks
+    /// The dictionary borrows the bitmap and precomputes the cardinality preceeding every container 
+    /// so that lookups are `O(log n)` rather than a linear scan over the containers
+    pub fn to_rank_map(&self) -> RankDict<'_> {
+        let mut prefix = Vec::with_capacity(self.containers.len());
 
-    /// Get the serialized size of the bitmap
-    pub fn serialized_size(&self) -> usize {
-        let mut count = self.header_size();
-        
-        for c in self.containers.iter() {
-            count += c.serialized_size();
+        let mut cardinality = 0;
+        for container in self.containers.iter() {
+            prefix.push(cardinality);
+            cardinality += container.cardinality();
+        }
+
+        RankDict {
+            bitmap: self,
+            prefix
         }
-        
-        count
     }
-    
-    /// Serialize the bitmap to a stream. The serialized bitmap is little endian encoded.
-    ///
-    /// # Returns
-    /// The number of bytes written to the buffer
-    #[cfg(target_endian = "little")]
-    pub fn serialize<W: Write>(&self, buf: &mut W) -> io::Result<usize> {
-        let mut start_offset;
-        let mut bytes_written = 0;
 
-        // Write the header
-        let has_run = self.has_run();
-        if has_run {
-            let len       = self.containers.len();
-            let len_bytes = (Self::SERIAL_COOKIE | (((len - 1) << 16) as u32)).to_le_bytes();
+    /// Build a dictionary mapping dense 0 based ranks to the values of the bitmap
+    /// 
+    /// # Remarks
+    /// The dictionary borrows the bitmap and precomputes the cardinality preceeding every container 
+    /// so that lookups only need to binary search for the container instead of scanning them all
+    pub fn to_select_dict(&self) -> SelectDict<'_> {
+        let rank_map = self.to_rank_map();
+
+        SelectDict {
+            bitmap: self,
+            prefix: rank_map.prefix
+        }
+    }
 
-            bytes_written += buf.write(&len_bytes)?;
+    /// Get an iterator over the values of the bitmap that lie outside of `range` in sorted order
+    /// 
+    /// # Remarks
+    /// Containers entirely within the range are skipped without being visited,
+    /// only the containers at the boundaries of the range are filtered
+    pub fn iter_outside_range<R: RangeBounds<u32>>(&self, range: R) -> impl Iterator<Item=u32> + '_ {
+        let (start, end) = range.into_bound();
+
+        // Containers before `head` and from `tail` onward may hold values outside the range
+        let (head, tail) = {
+            if start < end {
+                let key_start = (start >> 16) as u16;
+                let key_end = ((end - 1) >> 16) as u16;
+                let indices = self.get_index_range(key_start, key_end);
+
+                let head = {
+                    if indices.start < indices.end && self.keys[indices.start] == key_start {
+                        indices.start + 1
+                    }
+                    else {
+                        indices.start
+                    }
+                };
 
-            let s = (len + 7) / 8;
-            let mut bitmap: Vec<u8> = vec![0; s];
+                let tail = {
+                    if head < indices.end && self.keys[indices.end - 1] == key_end {
+                        indices.end - 1
+                    }
+                    else {
+                        indices.end
+                    }
+                };
 
-            for (i, c) in self.containers.iter().enumerate() {
-                if c.is_run() {
-                    bitmap[i / 8] |= 1 << (i % 8);
-                }
+                (head, tail)
             }
+            else {
+                (self.containers.len(), self.containers.len())
+            }
+        };
 
-            bytes_written += buf.write(&bitmap)?;
+        (0..head).chain(tail..self.containers.len())
+            .flat_map(move |i| {
+                let key = u32::from(self.keys[i]) << 16;
 
-            if (len as u32) < Self::NO_OFFSET_THRESHOLD {
-                start_offset = 4 + 4 * len + s;
+                self.containers[i].iter()
+                    .map(move |low| key | u32::from(low))
+            })
+            .filter(move |value| u64::from(*value) < start || u64::from(*value) >= end)
+    }
+
+    /// Get an iterator over the values within `range` that are not in the bitmap in sorted order
+    /// 
+    /// # Remarks
+    /// The values of the bitmap within the range are walked and the gaps between them are emitted,
+    /// the complement is never built so the memory used doesn't depend on the size of the range
+    pub fn iter_gaps_in_range<R: RangeBounds<u32>>(&self, range: R) -> impl Iterator<Item=u32> + '_ {
+        let (start, end) = range.into_bound();
+
+        let indices = {
+            if start < end {
+                self.get_index_range((start >> 16) as u16, ((end - 1) >> 16) as u16)
             }
             else {
-                start_offset = 4 + 8 * len + s;
+                0..0
             }
-        }
-        else {
-            bytes_written += buf.write(&Self::SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes())?;
-            bytes_written += buf.write(&(self.containers.len() as u32).to_le_bytes())?;
+        };
 
-            let len = self.containers.len();
-            start_offset = 4 + 4 + 4 * len + 4 * len;
-        }
+        // The end of the range closes the last gap
+        let end = end.max(start);
+        let values = indices
+            .flat_map(move |i| {
+                let key = u64::from(self.keys[i]) << 16;
+
+                self.containers[i].iter()
+                    .map(move |low| key | u64::from(low))
+            })
+            .filter(move |value| *value >= start && *value < end)
+            .chain(std::iter::once(end));
+
+        let mut next = start;
+        values
+            .flat_map(move |value| {
+                let gap = next..value;
+                next = value + 1;
+                gap
+            })
+            .map(|value| value as u32)
+    }
 
-        let pass = self.keys.iter()
-            .zip(self.containers.iter());
+    /// Export the bitmap as a dense bitvector of `ceil(universe / 8)` bytes where bit `i` 
+    /// is set if and only if `i` is in the bitmap
+    /// 
+    /// # Remarks
+    /// Bits are numbered from the least significant bit of the first byte.
+    /// Values of at least `universe` are left out
+    pub fn to_dense_bytes(&self, universe: u32) -> Vec<u8> {
+        // Each container spans 8KB of the output
+        const CONTAINER_BYTES: usize = (1 << 16) / 8;
+
+        let len = (universe as usize).div_ceil(8);
+        let mut bytes = vec![0; len];
+
+        for (key, container) in self.keys.iter().zip(self.containers.iter()) {
+            let start = usize::from(*key) * CONTAINER_BYTES;
+            if start >= len {
+                break;
+            }
 
-        // Write the keys and cardinality
-        for (key, c) in pass {
-            bytes_written += buf.write(&key.to_le_bytes())?;
-            bytes_written += buf.write(&((c.cardinality() - 1) as u16).to_le_bytes())?;
+            let end = (start + CONTAINER_BYTES).min(len);
+            container.write_dense(&mut bytes[start..end]);
         }
 
-        // Write the container offsets if there's no run containers or we're above the no offset threshold
-        if !has_run || (self.containers.len() as u32) >= Self::NO_OFFSET_THRESHOLD {
-            for c in self.containers.iter() {
-                bytes_written += buf.write(&(start_offset as u32).to_le_bytes())?;
-                start_offset  += c.serialized_size();
-            }
+        // Clear any bits past the universe in the last byte
+        if !universe.is_multiple_of(8) {
+            bytes[len - 1] &= (1 << (universe % 8)) - 1;
         }
 
-        for c in self.containers.iter() {
-            bytes_written += c.serialize(buf)?;
+        bytes
+    }
+
+    /// Create a bitmap from a dense bitvector where bit `i` is set if `i` is in the bitmap
+    /// 
+    /// # Remarks
+    /// Bits are numbered from the least significant bit of the first byte, the inverse of [`to_dense_bytes`].
+    /// Each 64K block is read as 64 bit words and stored as whichever container suits it's population
+    /// 
+    /// # Panics
+    /// Panics if `bytes` holds more than `2^32` bits
+    /// 
+    /// [`to_dense_bytes`]: RoaringBitmap::to_dense_bytes
+    pub fn from_dense_bytes(bytes: &[u8]) -> Self {
+        const CONTAINER_BYTES: usize = (1 << 16) / 8;
+
+        assert!(bytes.len() as u64 <= (1 << 32) / 8, "Dense bitvector exceeds the u32 range");
+
+        let mut bitmap = Self::with_capacity(bytes.len().div_ceil(CONTAINER_BYTES));
+        let mut words = Vec::with_capacity(CONTAINER_BYTES / 8);
+
+        for (key, block) in bytes.chunks(CONTAINER_BYTES).enumerate() {
+            words.clear();
+            words.extend(block.chunks(8).map(|chunk| {
+                let mut word = [0; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+
+                u64::from_le_bytes(word)
+            }));
+
+            if let Some(container) = Container::from_dense_words(&words) {
+                bitmap.keys.push(key as u16);
+                bitmap.containers.push(container);
+            }
         }
 
-        Ok(bytes_written)
+        bitmap
     }
-    
-    /// Deserialize a bitmap from a stream. The stream must be little endian encoded
-    ///
-    /// # Returns
-    /// The deserialized bitmap
-    #[cfg(target_endian = "little")]
-    pub fn deserialize<R: Read + Seek>(buf: &mut R) -> Result<Self, DeserializeError> {
-        // Read out the cookie and number of containers
-        let (cookie, size) = {
-            let mut cookie_bytes = [0; mem::size_of::<u32>()];
-            buf.read_exact(&mut cookie_bytes)
-                .map_err(DeserializeError::IoError)?;
 
-            let cookie = u32::from_le_bytes(cookie_bytes);
+    /// Create a bitmap from a dense bitvector of 64 bit words where bit `i` is the value `base + i`
+    /// 
+    /// # Remarks
+    /// Bits are numbered from the least significant bit of the first word. Zero words are skipped
+    /// and the values of each container are collected and added in a single batch,
+    /// so `base` doesn't need to be aligned to a word or container boundary
+    /// 
+    /// # Panics
+    /// Panics if any bit of `words` would be past `u32::MAX`
+    pub fn from_word_slice(words: &[u64], base: u32) -> Self {
+        assert!(u64::from(base) + words.len() as u64 * 64 <= 1 << 32, "Bitvector exceeds the u32 range");
 
-            // Validate cookie
-            if (cookie & 0xFFFF) != Self::SERIAL_COOKIE && cookie != Self::SERIAL_COOKIE_NO_RUNCONTAINER {
-                return Err(DeserializeError::InvalidCookie(cookie));
-            }
+        let mut bitmap = Self::new();
+        let mut values = Vec::new();
 
-            // Deserialize the size
-            let size = {
-                if cookie & 0xFFFF == Self::SERIAL_COOKIE {
-                    (cookie >> 16) + 1
-                }
-                else {
-                    let mut size_bytes = [0; mem::size_of::<u32>()];
-                    buf.read_exact(&mut size_bytes)
-                        .map_err(DeserializeError::IoError)?;
+        for (i, word) in words.iter().enumerate().filter(|(_i, word)| **word != 0) {
+            let start = base + (i as u32) * 64;
+            let mut word = *word;
 
-                    u32::from_le_bytes(size_bytes)
+            while word != 0 {
+                let value = start + word.trailing_zeros();
+
+                // Flush the values of the previous container
+                if values.last().is_some_and(|last: &u32| last >> 16 != value >> 16) {
+                    bitmap.add_slice(&values);
+                    values.clear();
                 }
-            };
 
-            // Validate size
-            if size > (1 << 16) {
-                return Err(DeserializeError::InvalidContainerCount(size));
+                values.push(value);
+                word &= word - 1;
             }
+        }
 
-            (cookie, size)
-        };
-
-        // Read out the bitmap if present
-        let mut bitmap: Vec<u8> = Vec::new();
-        
-        let has_run = (cookie & 0xFFFF) == Self::SERIAL_COOKIE;
-        if has_run {
-            let s = ((size + 7) / 8) as usize;
+        bitmap.add_slice(&values);
+        bitmap
+    }
 
-            // Reserve the space for the data to be deserialized into
-            unsafe {
-                bitmap.reserve_exact(s);
-                bitmap.set_len(s);
+    /// Decode values of the bitmap into `out` in ascending order, starting from `cursor`
+    /// 
+    /// # Remarks
+    /// As many values as fit are written and `cursor` is advanced past them,
+    /// so repeated calls with the same cursor decode the whole bitmap in chunks.
+    /// Array containers are copied directly with the high bits added
+    /// 
+    /// # Returns
+    /// The number of values written, which is only less than `out.len()` once the bitmap is exhausted
+    pub fn decode_into(&self, out: &mut [u32], cursor: &mut DecodeCursor) -> usize {
+        let mut written = 0;
+
+        while written < out.len() && cursor.index < self.containers.len() {
+            let high = u32::from(self.keys[cursor.index]) << 16;
+            let container = &self.containers[cursor.index];
+            let (count, next) = container.decode_into(cursor.next, high, &mut out[written..]);
+
+            written += count;
+
+            match next {
+                Some(next) => cursor.next = next,
+                None => {
+                    cursor.index += 1;
+                    cursor.next = 0;
+                }
             }
-
-            buf.read(&mut bitmap)
-                .map_err(DeserializeError::IoError)?;
         }
 
-        // Setup the resulting bitmap
-        let mut result = Self::with_capacity(size as usize);
-
-        // Read out the keys into the bitmap and save the cards for later
-        let mut cards = Vec::with_capacity(size as usize);
-        for _i in 0..size {
-            let mut key_bytes  = [0; mem::size_of::<u16>()];
-            let mut card_bytes = [0; mem::size_of::<u16>()];
+        written
+    }
 
-            buf.read_exact(&mut key_bytes)
-                .map_err(DeserializeError::IoError)?;
-            buf.read_exact(&mut card_bytes)
-                .map_err(DeserializeError::IoError)?;
+    /// Get an iterator over the values of the bitmap paired with their 0 based rank
+    /// 
+    /// # Remarks
+    /// Ranks are computed incrementally, no per element `rank` query is done
+    pub fn iter_ranked(&self) -> impl Iterator<Item=(u32, usize)> + '_ {
+        self.iter()
+            .enumerate()
+            .map(|(rank, value)| (value, rank))
+    }
 
+    /// Get an iterator over the first value of each 64K block holding data, `(key as u32) << 16` for each container
+    /// 
+    /// # Remarks
+    /// Blocks are visited in ascending order, useful for skipping over empty blocks when scanning
+    pub fn iter_keys(&self) -> impl ExactSizeIterator<Item=u32> + '_ {
+        self.keys.iter().map(|key| u32::from(*key) << 16)
+    }
 
-            let key  = u16::from_le_bytes(key_bytes);
-            let card = u16::from_le_bytes(card_bytes);
+    /// Get an iterator over the high 16 bits of each container paired with an iterator
+    /// over the low 16 bits of the values within it
+    /// 
+    /// # Remarks
+    /// Useful for processing the bitmap in batches without reconstructing and splitting 32 bit values.
+    /// Containers are visited in ascending key order and each yields it's values in ascending order
+    pub fn iter_by_container(&self) -> impl Iterator<Item=(u16, impl Iterator<Item=u16> + '_)> + '_ {
+        self.keys.iter()
+            .zip(self.containers.iter())
+            .map(|(key, container)| (*key, container.iter()))
+    }
 
-            result.keys.push(key);
-            cards.push(card);
+    /// Get every value in the bitmap split into it's high and low 16 bits, in ascending order
+    /// 
+    /// # Remarks
+    /// The pairs are read directly from the containers so no 32 bit values are built and split again
+    pub fn to_key_value_pairs(&self) -> Vec<(u16, u16)> {
+        let mut pairs = Vec::with_capacity(self.cardinality());
+        for (key, values) in self.iter_by_container() {
+            pairs.extend(values.map(|low| (key, low)));
         }
 
-        // Bypass the offset header if necessary. 
-        // This implementation doesn't support container streaming
-        // so the offset header is ignored
-        if !has_run || size >= Self::NO_OFFSET_THRESHOLD {
-            let offset_header = i64::from(size * 4);
+        pairs
+    }
 
-            buf.seek(SeekFrom::Current(offset_header))
-                .map_err(DeserializeError::IoError)?;
-        }
+    /// Get a bitmap containing every `n`th value of this bitmap by rank, starting with the minimum
+    /// 
+    /// # Remarks
+    /// Containers that don't hold a sampled rank are skipped using their cardinality,
+    /// no per element `select` query is done
+    /// 
+    /// # Panics
+    /// Panics if `n` is zero
+    pub fn sample_every(&self, n: u32) -> RoaringBitmap {
+        assert!(n > 0, "Sample interval must be non zero");
 
-        // Load in the containers
-        for i in 0..(size as usize) {
-            let card = (cards[i] as usize) + 1;
-            let (is_bitset, is_run) = {
-                if has_run && bitmap[i / 8] & (1 << (i % 8)) != 0 {
-                    (false, true)
-                }
-                else {
-                    (card > DEFAULT_MAX_SIZE, false)
-                }
-            };
+        let n = n as usize;
+        let mut result = RoaringBitmap::new();
 
-            // Container is a bitset
-            if is_bitset {
-                let bitset = BitsetContainer::deserialize(buf)
-                    .map_err(DeserializeError::IoError)?;
+        // The number of values to pass over before the next sample
+        let mut skip = 0;
 
-                result.containers.push(Container::Bitset(bitset));
-            }
-            // Container is a run container
-            else if is_run {
-                let run = RunContainer::deserialize(buf)
-                    .map_err(DeserializeError::IoError)?;
-                
-                result.containers.push(Container::Run(run));
+        let pass = self.keys.iter()
+            .zip(self.containers.iter());
+
+        for (key, container) in pass {
+            let cardinality = container.cardinality();
+            if skip >= cardinality {
+                skip -= cardinality;
+                continue;
             }
-            // Container is an array
-            else {
-                let array = ArrayContainer::deserialize(card, buf)
-                    .map_err(DeserializeError::IoError)?;
 
-                result.containers.push(Container::Array(array));
+            let remaining = cardinality - skip;
+            let mut samples = ArrayContainer::with_capacity(remaining.div_ceil(n));
+            for value in container.iter().skip(skip).step_by(n) {
+                samples.push(value);
             }
+
+            result.keys.push(*key);
+            result.containers.push(Container::Array(samples).optimize());
+
+            skip = n - 1 - (remaining - 1) % n;
         }
 
-        Ok(result)
+        result
     }
 
-    /// Calculate the size of the serialized header for the bitmap
-    fn header_size(&self) -> usize {
-        let contains_run = self.has_run();
-        
-        let len = self.containers.len();
-        if contains_run {
-            if len < (Self::NO_OFFSET_THRESHOLD as usize) {
-                4 + (len + 7) / 8 + 4 * len
+    /// Get a bitmap with `delta` added to every value of this bitmap
+    /// 
+    /// # Remarks
+    /// Values that would fall outside of `[0, u32::MAX]` are dropped.
+    /// When `delta` is a multiple of `65536` only the keys are adjusted and the containers are
+    /// copied as is, otherwise each container is split across the two keys its shifted values land in
+    pub fn shift(&self, delta: i64) -> RoaringBitmap {
+        let key_delta = delta.div_euclid(1 << 16);
+        let offset = delta.rem_euclid(1 << 16) as u32;
+
+        let mut result = RoaringBitmap::new();
+
+        // Append a container to the result if its key is in bounds,
+        // a key may be written twice by the high half of one container and the low half of the next
+        let push = |result: &mut RoaringBitmap, key: i64, container: Container| {
+            if key < 0 || key > i64::from(u16::MAX) || container.is_empty() {
+                return;
+            }
+
+            let key = key as u16;
+            if result.keys.last() == Some(&key) {
+                let last = result.containers.last_mut().unwrap();
+                last.inplace_or(&container);
             }
             else {
-                4 + (len + 7) / 8 + 8 * len
+                result.keys.push(key);
+                result.containers.push(container);
             }
-        }
-        else {
-            4 + 4 + 8 * len
-        }
-    }
+        };
 
-    /// Check if the bitmap contains any run containers
-    fn has_run(&self) -> bool {
-        for c in self.containers.iter() {
-            if c.is_run() {
-                return true;
+        let pass = self.keys.iter()
+            .zip(self.containers.iter());
+
+        for (key, container) in pass {
+            let key = i64::from(*key) + key_delta;
+
+            if offset == 0 {
+                push(&mut result, key, container.clone());
+                continue;
             }
+
+            // Values at or above the split wrap into the next key
+            let split = (1 << 16) - offset;
+            let mut low = ArrayContainer::new();
+            let mut high = ArrayContainer::new();
+            for value in container.iter() {
+                let value = u32::from(value);
+                if value < split {
+                    low.push((value + offset) as u16);
+                }
+                else {
+                    high.push((value - split) as u16);
+                }
+            }
+
+            push(&mut result, key, Container::Array(low).optimize());
+            push(&mut result, key + 1, Container::Array(high).optimize());
         }
-        
-        false
+
+        result
     }
-}
 
-/// An iterator over a roaring bitmap
-pub struct Iter<'a> {
-    /// The containers we're iterating
-    containers: &'a Vec<Container>,
-    
-    /// The keys we're iterating
-    keys: &'a Vec<u16>,
-    
-    /// The container iterator we're currently iterating
-    iter: container::Iter<'a>,
-    
-    /// The index of the container we're iterating
-    index: usize
-}
+    /// Get a bitmap with every value `v` of this bitmap mapped to `universe_max - v`
+    /// 
+    /// # Remarks
+    /// Values greater than `universe_max` have no reflection and are dropped.
+    /// Containers are visited from the highest key down so the reflected values
+    /// are appended in ascending order one container at a time
+    pub fn reflect(&self, universe_max: u32) -> RoaringBitmap {
+        let max_key = (universe_max >> 16) as u16;
 
-impl<'a> Iterator for Iter<'a> {
-    type Item = u32;
-    
-    fn next(&mut self) -> Option<Self::Item> {
-        // Try to advance the container iterator
-        let mut item = self.iter.next();
+        let mut result = RoaringBitmap::new();
+        let mut reflected = Vec::new();
 
-        // If we reached the end of the iterator try to advance to the next container
-        if item.is_none() {
-            let next_index = self.index + 1;
-            if next_index < self.containers.len() {
-                // Advance the index
-                self.index = next_index;
-                self.iter = self.containers[next_index].iter();
+        let pass = self.keys.iter()
+            .zip(self.containers.iter())
+            .rev()
+            .filter(|(key, _container)| **key <= max_key);
+
+        for (key, container) in pass {
+            let high = u32::from(*key) << 16;
+
+            reflected.clear();
+            reflected.extend(container.iter()
+                .map(|value| high | u32::from(value))
+                .take_while(|value| *value <= universe_max)
+                .map(|value| universe_max - value));
+
+            reflected.reverse();
+            result.add_slice(&reflected);
+        }
+
+        result
+    }
+
+    /// Get an iterator over the values of both `a` and `b` in sorted order, tagged with which
+    /// of the bitmaps contain them
+    /// 
+    /// # Remarks
+    /// Both bitmaps are traversed once in lockstep. The union, intersection and differences
+    /// of the bitmaps can all be recovered by filtering on the [`JoinSide`]
+    pub fn merge_join<'a>(a: &'a Self, b: &'a Self) -> impl Iterator<Item=(u32, JoinSide)> + 'a {
+        let mut iter_a = a.iter().peekable();
+        let mut iter_b = b.iter().peekable();
+
+        std::iter::from_fn(move || {
+            match (iter_a.peek().copied(), iter_b.peek().copied()) {
+                (Some(va), Some(vb)) => {
+                    if va < vb {
+                        iter_a.next();
+                        Some((va, JoinSide::Left))
+                    }
+                    else if vb < va {
+                        iter_b.next();
+                        Some((vb, JoinSide::Right))
+                    }
+                    else {
+                        iter_a.next();
+                        iter_b.next();
+                        Some((va, JoinSide::Both))
+                    }
+                },
+                (Some(va), None) => {
+                    iter_a.next();
+                    Some((va, JoinSide::Left))
+                },
+                (None, Some(vb)) => {
+                    iter_b.next();
+                    Some((vb, JoinSide::Right))
+                },
+                (None, None) => None
+            }
+        })
+    }
+
+    /// Compare the bitmaps lexicographically by their sorted values
+    /// 
+    /// # Remarks
+    /// The first differing value determines the order and a bitmap that is a prefix of another compares less.
+    /// Both bitmaps are walked in lockstep and the walk stops at the first difference.
+    /// 
+    /// This is deliberately not an `Ord` impl, `Ord::min` and `Ord::max` would shadow the inherent
+    /// `min` and `max`. Use it with `sort_by`, or wrap the bitmap in an [`OrderedBitmap`] to key sorted collections
+    pub fn cmp_values(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+
+    /// Find the smallest value present in exactly one of `self` and `other`. 
+    /// Returns `None` if the bitmaps are equal
+    /// 
+    /// # Remarks
+    /// Both bitmaps are walked in lockstep and the walk stops at the first difference
+    pub fn first_difference(&self, other: &Self) -> Option<u32> {
+        Self::merge_join(self, other)
+            .find(|(_value, side)| *side != JoinSide::Both)
+            .map(|(value, _side)| value)
+    }
+
+    /// Compute a stable 64 bit fingerprint of the contents of the bitmap
+    /// 
+    /// # Remarks
+    /// Equal sets always have equal fingerprints regardless of how their containers are stored,
+    /// and the fingerprint doesn't change between processes, builds or platforms.
+    /// 
+    /// Each container contributes its key, cardinality and number of runs. Containers of up to 
+    /// `FINGERPRINT_SAMPLE_SIZE` values contribute every value, larger containers contribute a fixed 
+    /// number of values sampled by rank. Distinct large sets are therefore more likely to collide
+    /// than small ones, but the cost is independent of the size of the containers
+    pub fn fingerprint(&self) -> u64 {
+        const FINGERPRINT_SAMPLE_SIZE: usize = 64;
+
+        // FxHash style mixing with fixed constants so the result never depends on the process
+        let mix = |hash: u64, word: u64| (hash.rotate_left(5) ^ word).wrapping_mul(0x517c_c1b7_2722_0a95);
+
+        let mut hash = mix(0, self.containers.len() as u64);
+
+        let pass = self.keys.iter()
+            .zip(self.containers.iter());
+
+        for (key, container) in pass {
+            let cardinality = container.cardinality();
+
+            hash = mix(hash, u64::from(*key));
+            hash = mix(hash, cardinality as u64);
+            hash = mix(hash, container.num_runs() as u64);
+
+            if cardinality <= FINGERPRINT_SAMPLE_SIZE {
+                hash = container.fold(hash, |hash, value| mix(hash, u64::from(value)));
+            }
+            else {
+                for i in 0..FINGERPRINT_SAMPLE_SIZE {
+                    let rank = (i * (cardinality - 1) / (FINGERPRINT_SAMPLE_SIZE - 1)) as u32;
+                    let value = container.select(rank, &mut 0)
+                        .expect("Rank is within the container");
+
+                    hash = mix(hash, u64::from(value));
+                }
+            }
+        }
+
+        // Finalize with the splitmix64 avalanche so similar sets spread across the whole range
+        hash ^= hash >> 30;
+        hash = hash.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash ^= hash >> 27;
+        hash = hash.wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^ (hash >> 31)
+    }
+}
+
+/// Which of the bitmaps in a [`merge_join`] contain a value
+/// 
+/// [`merge_join`]: RoaringBitmap::merge_join
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinSide {
+    /// The value is only in the left bitmap
+    Left,
+
+    /// The value is only in the right bitmap
+    Right,
+
+    /// The value is in both bitmaps
+    Both
+}
+
+/// Statistics about the runs of consecutive values in a bitmap
+/// 
+/// # Remarks
+/// Runs never span containers, values on either side of a container boundary are counted as separate runs
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RunStats {
+    /// The total number of runs across all containers
+    pub total_runs: usize,
+
+    /// The average number of values in a run
+    pub mean_run_length: f64,
+
+    /// The number of values in the longest run
+    pub longest_run: usize,
+
+    /// The number of containers that would be smaller if stored as runs
+    pub containers_better_as_run: usize
+}
+
+/// An error that occured while selecting an element by rank
+#[derive(Debug, PartialEq, Eq)]
+pub enum SelectError {
+    /// The bitmap contains no elements
+    Empty,
+
+    /// The rank exceeds the number of elements in the bitmap. Contains the cardinality of the bitmap
+    OutOfRange { cardinality: usize }
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SelectError::Empty => write!(f, "Bitmap is empty"),
+            SelectError::OutOfRange { cardinality } => write!(f, "Rank out of range for cardinality: {}", cardinality)
+        }
+    }
+}
+
+/// An opaque container holding the low 16 bits of the values sharing a key in a bitmap
+/// 
+/// # Remarks
+/// Used with [`into_parts`] and [`from_parts`] to construct bitmaps from precomputed containers
+/// 
+/// [`into_parts`]: RoaringBitmap::into_parts
+/// [`from_parts`]: RoaringBitmap::from_parts
+#[derive(Clone, Debug)]
+pub struct ContainerPart(Container);
+
+impl ContainerPart {
+    /// Create a container from a slice of values
+    pub fn from_slice(values: &[u16]) -> Self {
+        let mut container = Container::new();
+        for value in values.iter() {
+            container.add(*value);
+        }
+
+        ContainerPart(container.optimize())
+    }
+
+    /// Create a container holding every value in the range
+    /// 
+    /// # Remarks
+    /// The range is wider than `u16` so that a full container `0..(1 << 16)` can be expressed
+    /// 
+    /// # Panics
+    /// Panics if the range ends past `1 << 16`
+    pub fn from_range(range: Range<u32>) -> Self {
+        assert!(range.end <= (1 << 16), "Range must be within a single container");
+
+        if range.is_empty() {
+            return ContainerPart(Container::new());
+        }
+
+        ContainerPart(Container::from_range(range).optimize())
+    }
+
+    /// The number of values in the container
+    #[inline]
+    pub fn cardinality(&self) -> usize {
+        self.0.cardinality()
+    }
+
+    /// Check whether the container is empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Check whether the container holds `value`
+    #[inline]
+    pub fn contains(&self, value: u16) -> bool {
+        self.0.contains(value)
+    }
+
+    /// Get an iterator over the values of the container
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=u16> + '_ {
+        self.0.iter()
+    }
+}
+
+/// An error that occured while assembling a bitmap from it's parts
+#[derive(Debug, PartialEq, Eq)]
+pub enum PartsError {
+    /// The number of keys differs from the number of containers
+    LengthMismatch { keys: usize, containers: usize },
+
+    /// The keys are not sorted or contain a duplicate. Contains the index of the offending key
+    UnsortedKeys(usize),
+
+    /// A container holds no values. Contains the index of the container
+    EmptyContainer(usize)
+}
+
+impl fmt::Display for PartsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PartsError::LengthMismatch { keys, containers } => write!(f, "Found {} keys for {} containers", keys, containers),
+            PartsError::UnsortedKeys(index) => write!(f, "Keys are not sorted and unique at index: {}", index),
+            PartsError::EmptyContainer(index) => write!(f, "Empty container at index: {}", index)
+        }
+    }
+}
+
+/// How the container vectors of a bitmap grow when new containers are inserted
+/// 
+/// # Remarks
+/// Write heavy workloads can trade memory for fewer reallocations by over reserving
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrowthPolicy {
+    /// Grow the capacity to the next power of two of the required length
+    #[default]
+    PowerOfTwo,
+
+    /// Grow the capacity to exactly the required length
+    Exact
+}
+
+impl GrowthPolicy {
+    /// Reserve space for `additional` more elements in `vec` following the policy
+    fn reserve<T>(self, vec: &mut Vec<T>, additional: usize) {
+        let required = vec.len() + additional;
+        if required <= vec.capacity() {
+            return;
+        }
+
+        let capacity = match self {
+            GrowthPolicy::PowerOfTwo => required.next_power_of_two(),
+            GrowthPolicy::Exact => required
+        };
+
+        vec.reserve_exact(capacity - vec.len());
+    }
+}
+
+/// The lazily computed bounds of a bitmap
+/// 
+/// # Remarks
+/// Each bound is stored as the value, `EMPTY` if the bitmap has no values, or `UNKNOWN` if it hasn't been
+/// computed since the bitmap was last modified. Atomics let `min` and `max` fill the cache through a 
+/// shared reference while keeping bitmaps `Sync`. The cache never affects equality
+#[derive(Debug)]
+struct BoundsCache {
+    min: AtomicU64,
+    max: AtomicU64
+}
+
+impl BoundsCache {
+    /// The bound hasn't been computed
+    const UNKNOWN: u64 = u64::MAX;
+
+    /// The bitmap is empty and has no bounds
+    const EMPTY: u64 = 1 << 32;
+
+    /// Create a cache with both bounds unknown
+    fn new() -> Self {
+        Self {
+            min: AtomicU64::new(Self::UNKNOWN),
+            max: AtomicU64::new(Self::UNKNOWN)
+        }
+    }
+
+    /// Get the bound stored in `slot`, computing and storing it with `f` if it's unknown
+    fn get<F>(slot: &AtomicU64, f: F) -> Option<u32>
+        where F: FnOnce() -> Option<u32>
+    {
+        match slot.load(AtomicOrdering::Relaxed) {
+            Self::UNKNOWN => {
+                let bound = f();
+                slot.store(bound.map_or(Self::EMPTY, u64::from), AtomicOrdering::Relaxed);
+                bound
+            },
+            Self::EMPTY => None,
+            bound => Some(bound as u32)
+        }
+    }
+
+    /// Forget both bounds, called whenever the contents of the bitmap change
+    #[inline]
+    fn invalidate(&mut self) {
+        *self.min.get_mut() = Self::UNKNOWN;
+        *self.max.get_mut() = Self::UNKNOWN;
+    }
+}
+
+impl Clone for BoundsCache {
+    fn clone(&self) -> Self {
+        Self {
+            min: AtomicU64::new(self.min.load(AtomicOrdering::Relaxed)),
+            max: AtomicU64::new(self.max.load(AtomicOrdering::Relaxed))
+        }
+    }
+}
+
+// Serialization
+
+/// The layout used when serializing a bitmap
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SerializationFormat {
+    /// The layout described by the roaring format spec, readable by other roaring implementations
+    #[default]
+    Portable,
+
+    /// A more compact layout without a container offset header. 
+    /// Only readable by this crate, intended for caches and other in-process storage
+    Native
+}
+
+/// An error that occured while deserializing a bitmap
+#[derive(Debug)]
+pub enum DeserializeError {
+    /// An invalid cookie was detected. This is likely not a bitmap. Contains the found value
+    InvalidCookie(u32),
+
+    /// An invalid container count was detected. Contains the value found
+    InvalidContainerCount(u32),
+
+    /// A container offset was out of bounds or didn't follow the preceeding container. Contains the value found
+    InvalidOffset(u32),
+
+    /// A container's data doesn't match its declared cardinality. Contains the declared cardinality
+    InvalidCardinality(u32),
+
+    /// A framed bitmap didn't fill the length declared by its frame. Contains the declared length
+    InvalidFrameLength(u32),
+
+    /// An IO error occured during deserialization, Contains the underlying error
+    IoError(io::Error)
+}
+
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeserializeError::InvalidCookie(cookie) => write!(f, "Invalid cookie: {}", cookie),
+            DeserializeError::InvalidContainerCount(count) => write!(f, "Invalid container count: {}", count),
+            DeserializeError::InvalidOffset(offset) => write!(f, "Invalid container offset: {}", offset),
+            DeserializeError::InvalidCardinality(card) => write!(f, "Invalid container cardinality: {}", card),
+            DeserializeError::InvalidFrameLength(len) => write!(f, "Invalid frame length: {}", len),
+            DeserializeError::IoError(err) => write!(f, "{}", err)
+        }
+    }
+}
+
+/// The header of a serialized bitmap, everything preceeding the container data
+#[derive(Debug)]
+struct SerialHeader {
+    /// The cookie identifying the format
+    cookie: u32,
+
+    /// The keys of every container in the bitmap
+    keys: Vec<u16>,
+
+    /// The cardinality of every container in the bitmap, less one
+    cards: Vec<u16>,
+
+    /// One bit per container set if the container is a run container
+    run_flags: Vec<u8>,
+
+    /// The declared offset of every container, empty if the bitmap has no offset header
+    offsets: Vec<u32>,
+
+    /// The number of bytes in the header
+    len: usize
+}
+
+impl SerialHeader {
+    /// Read the header of a serialized bitmap in either the portable or native format from `buf`
+    fn read<R: Read>(buf: &mut R) -> Result<Self, DeserializeError> {
+        let mut read_u32 = || {
+            let mut bytes = [0; mem::size_of::<u32>()];
+            buf.read_exact(&mut bytes)
+                .map_err(DeserializeError::IoError)?;
+
+            Ok(u32::from_le_bytes(bytes))
+        };
+
+        // Read out the cookie and number of containers
+        let cookie = read_u32()?;
+        if !RoaringBitmap::is_valid_cookie(cookie) {
+            return Err(DeserializeError::InvalidCookie(cookie));
+        }
+
+        let (size, mut len) = {
+            if cookie & 0xFFFF == RoaringBitmap::SERIAL_COOKIE {
+                ((cookie >> 16) + 1, 4)
+            }
+            else {
+                (read_u32()?, 8)
+            }
+        };
+
+        if size > (1 << 16) {
+            return Err(DeserializeError::InvalidContainerCount(size));
+        }
+
+        let size = size as usize;
+
+        // Read out the run flags if present
+        let mut run_flags = Vec::new();
+        if RoaringBitmap::has_run_flags(cookie) {
+            run_flags.resize(size.div_ceil(8), 0);
+
+            buf.read_exact(&mut run_flags)
+                .map_err(DeserializeError::IoError)?;
+
+            len += run_flags.len();
+        }
+
+        // Read out the keys and cardinalities
+        let mut descriptive = vec![0; size * 2 * mem::size_of::<u16>()];
+        buf.read_exact(&mut descriptive)
+            .map_err(DeserializeError::IoError)?;
+
+        len += descriptive.len();
+
+        let (keys, cards) = descriptive.chunks_exact(4)
+            .map(|c| (u16::from_le_bytes([c[0], c[1]]), u16::from_le_bytes([c[2], c[3]])))
+            .unzip();
+
+        // Read out the offset header so each container can be checked against it.
+        // Bitmaps without run containers always have offsets regardless of their size
+        let mut offsets = Vec::new();
+        if RoaringBitmap::has_offsets(cookie, size) {
+            let mut header = vec![0; size * mem::size_of::<u32>()];
+            buf.read_exact(&mut header)
+                .map_err(DeserializeError::IoError)?;
+
+            len += header.len();
+
+            offsets = header.chunks_exact(4)
+                .map(|c| u32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect();
+        }
+
+        Ok(Self {
+            cookie,
+            keys,
+            cards,
+            run_flags,
+            offsets,
+            len
+        })
+    }
+
+    /// Check if the container count is packed into the upper bits of the cookie
+    fn has_packed_count(&self) -> bool {
+        self.cookie & 0xFFFF == RoaringBitmap::SERIAL_COOKIE
+    }
+
+    /// Check if container `i` is a run container
+    fn is_run(&self, i: usize) -> bool {
+        !self.run_flags.is_empty() && self.run_flags[i / 8] & (1 << (i % 8)) != 0
+    }
+
+    /// The cardinality of container `i`
+    fn cardinality(&self, i: usize) -> usize {
+        usize::from(self.cards[i]) + 1
+    }
+}
+
+/// An incremental deserializer reading the containers of a serialized bitmap one at a time
+/// 
+/// # Remarks
+/// The header is read once when the reader is created and each call to [`next_container`] reads
+/// the next container from the stream, so the whole bitmap is never held in memory at once.
+/// Both the portable and native formats are accepted. Each container is checked against the
+/// header as it's read, an offset that doesn't match the data read so far or a run container
+/// that doesn't cover it's declared cardinality fails the read
+/// 
+/// [`next_container`]: BitmapReader::next_container
+#[derive(Debug)]
+pub struct BitmapReader<R> {
+    /// The stream containers are read from
+    buf: R,
+
+    /// The header read when the reader was created
+    header: SerialHeader,
+
+    /// The number of bytes of the bitmap read so far
+    position: usize,
+
+    /// The index of the next container to read
+    next: usize,
+
+    /// Set once a container fails to read, the position in the stream is unknown after that
+    failed: bool
+}
+
+#[cfg(target_endian = "little")]
+impl<R: Read> BitmapReader<R> {
+    /// Create a reader by reading the header of the bitmap from `buf`
+    pub fn new(mut buf: R) -> Result<Self, DeserializeError> {
+        let header = SerialHeader::read(&mut buf)?;
+        let position = header.len;
+
+        Ok(Self {
+            buf,
+            header,
+            position,
+            next: 0,
+            failed: false
+        })
+    }
+
+    /// The number of containers in the bitmap
+    pub fn len(&self) -> usize {
+        self.header.keys.len()
+    }
+
+    /// Check whether the bitmap has no containers
+    pub fn is_empty(&self) -> bool {
+        self.header.keys.is_empty()
+    }
+
+    /// Read the next container from the stream
+    /// 
+    /// # Returns
+    /// The key and container, `None` once every container has been read or after an error
+    pub fn next_container(&mut self) -> Option<Result<(u16, ContainerPart), DeserializeError>> {
+        if self.failed || self.next >= self.header.keys.len() {
+            return None;
+        }
+
+        let i = self.next;
+        self.next += 1;
+
+        let card = self.header.cardinality(i);
+        let is_run = self.header.is_run(i);
+
+        // The container must start right where the previous one ended
+        if let Some(&declared) = self.header.offsets.get(i) {
+            if declared as usize != self.position {
+                self.failed = true;
+                return Some(Err(DeserializeError::InvalidOffset(declared)));
+            }
+        }
+
+        let container = {
+            if is_run {
+                RunContainer::deserialize(&mut self.buf)
+                    .map_err(DeserializeError::IoError)
+                    .and_then(|run| {
+                        // The runs must cover exactly the declared cardinality
+                        if run.cardinality() != card {
+                            return Err(DeserializeError::InvalidCardinality(card as u32));
+                        }
+
+                        self.position += RunContainer::serialized_size(run.num_runs());
+
+                        Ok(Container::Run(run))
+                    })
+            }
+            else if card > DEFAULT_MAX_SIZE {
+                self.position += BitsetContainer::serialized_size();
+
+                BitsetContainer::deserialize(&mut self.buf)
+                    .map(Container::Bitset)
+                    .map_err(DeserializeError::IoError)
+            }
+            else {
+                self.position += ArrayContainer::serialized_size(card);
+
+                ArrayContainer::deserialize(card, &mut self.buf)
+                    .map(Container::Array)
+                    .map_err(DeserializeError::IoError)
+            }
+        };
+
+        match container {
+            Ok(container) => Some(Ok((self.header.keys[i], ContainerPart(container)))),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+
+    /// Get the underlying stream back, positioned after the last container read
+    pub fn into_inner(self) -> R {
+        self.buf
+    }
+}
+
+impl RoaringBitmap {
+    // Constants denoted by the roaring bitmap format spec.
+    // See https://github.com/RoaringBitmap/RoaringFormatSpec for details
+    const SERIAL_COOKIE_NO_RUNCONTAINER: u32 = 12346;
+    const SERIAL_COOKIE: u32 = 12347;
+    const NO_OFFSET_THRESHOLD: u32 = 4;
+
+    // Cookie for the native format, this is not part of the spec
+    // and is chosen to not collide with either of the portable cookies
+    const SERIAL_COOKIE_NATIVE: u32 = 12348;
+
+    /// Get the serialized size of the bitmap in the portable format
+    pub fn serialized_size(&self) -> usize {
+        self.serialized_size_with(SerializationFormat::Portable)
+    }
+
+    /// Get the serialized size of the bitmap in the given format
+    pub fn serialized_size_with(&self, format: SerializationFormat) -> usize {
+        let mut count = self.header_size(format);
+        
+        for c in self.containers.iter() {
+            count += c.serialized_size();
+        }
+        
+        count
+    }
+    
+    /// Serialize the bitmap to a stream in the portable format. The serialized bitmap is little endian encoded.
+    ///
+    /// # Returns
+    /// The number of bytes written to the buffer
+    #[cfg(target_endian = "little")]
+    pub fn serialize<W: Write>(&self, buf: &mut W) -> io::Result<usize> {
+        self.serialize_with(buf, SerializationFormat::Portable)
+    }
+
+    /// Serialize the bitmap to a stream in the given format. The serialized bitmap is little endian encoded.
+    ///
+    /// # Remarks
+    /// The format is recorded in the cookie so `deserialize` detects it automatically
+    ///
+    /// # Returns
+    /// The number of bytes written to the buffer
+    #[cfg(target_endian = "little")]
+    pub fn serialize_with<W: Write>(&self, buf: &mut W, format: SerializationFormat) -> io::Result<usize> {
+        match format {
+            SerializationFormat::Portable => self.serialize_portable(buf),
+            SerializationFormat::Native => self.serialize_native(buf)
+        }
+    }
+
+    /// Serialize the bitmap following the roaring format spec
+    #[cfg(target_endian = "little")]
+    fn serialize_portable<W: Write>(&self, buf: &mut W) -> io::Result<usize> {
+        let mut start_offset;
+        let mut bytes_written = 0;
+
+        // Write the header
+        let has_run = self.has_run();
+        if has_run {
+            let len       = self.containers.len();
+            let len_bytes = (Self::SERIAL_COOKIE | (((len - 1) << 16) as u32)).to_le_bytes();
+
+            bytes_written += buf.write(&len_bytes)?;
+
+            let s = (len + 7) / 8;
+            bytes_written += buf.write(&self.run_flags())?;
+
+            if (len as u32) < Self::NO_OFFSET_THRESHOLD {
+                start_offset = 4 + 4 * len + s;
+            }
+            else {
+                start_offset = 4 + 8 * len + s;
+            }
+        }
+        else {
+            bytes_written += buf.write(&Self::SERIAL_COOKIE_NO_RUNCONTAINER.to_le_bytes())?;
+            bytes_written += buf.write(&(self.containers.len() as u32).to_le_bytes())?;
+
+            let len = self.containers.len();
+            start_offset = 4 + 4 + 4 * len + 4 * len;
+        }
+
+        let pass = self.keys.iter()
+            .zip(self.containers.iter());
+
+        // Write the keys and cardinality
+        for (key, c) in pass {
+            bytes_written += buf.write(&key.to_le_bytes())?;
+            bytes_written += buf.write(&((c.cardinality() - 1) as u16).to_le_bytes())?;
+        }
+
+        // Write the container offsets if there's no run containers or we're above the no offset threshold
+        if !has_run || (self.containers.len() as u32) >= Self::NO_OFFSET_THRESHOLD {
+            for c in self.containers.iter() {
+                bytes_written += buf.write(&(start_offset as u32).to_le_bytes())?;
+                start_offset  += c.serialized_size();
+            }
+        }
+
+        for c in self.containers.iter() {
+            bytes_written += c.serialize(buf)?;
+        }
+
+        Ok(bytes_written)
+    }
+
+    /// Serialize the bitmap in the native format.
+    /// 
+    /// # Remarks
+    /// This is the portable layout with the container count always stored after the cookie,
+    /// the run flags always present and the container offsets omitted
+    #[cfg(target_endian = "little")]
+    fn serialize_native<W: Write>(&self, buf: &mut W) -> io::Result<usize> {
+        let mut bytes_written = 0;
+
+        bytes_written += buf.write(&Self::SERIAL_COOKIE_NATIVE.to_le_bytes())?;
+        bytes_written += buf.write(&(self.containers.len() as u32).to_le_bytes())?;
+        bytes_written += buf.write(&self.run_flags())?;
+
+        let pass = self.keys.iter()
+            .zip(self.containers.iter());
+
+        for (key, c) in pass {
+            bytes_written += buf.write(&key.to_le_bytes())?;
+            bytes_written += buf.write(&((c.cardinality() - 1) as u16).to_le_bytes())?;
+        }
+
+        for c in self.containers.iter() {
+            bytes_written += c.serialize(buf)?;
+        }
+
+        Ok(bytes_written)
+    }
+    
+    /// Deserialize a bitmap from a stream. The stream must be little endian encoded
+    ///
+    /// # Remarks
+    /// Both the portable and native formats are accepted, the format is detected from the cookie.
+    /// Containers are validated as they're read so only the bitmap itself is consumed from the stream
+    ///
+    /// # Returns
+    /// The deserialized bitmap
+    #[cfg(target_endian = "little")]
+    pub fn deserialize<R: Read>(buf: &mut R) -> Result<Self, DeserializeError> {
+        let mut reader = BitmapReader::new(buf)?;
+
+        let mut result = Self::with_capacity(reader.len());
+        while let Some(part) = reader.next_container() {
+            let (key, ContainerPart(container)) = part?;
+
+            result.keys.push(key);
+            result.containers.push(container);
+        }
+
+        Ok(result)
+    }
+
+    /// Serialize a sequence of bitmaps to a stream as one archive
+    /// 
+    /// # Remarks
+    /// The archive starts with the number of bitmaps as a little endian `u32`. 
+    /// Each bitmap follows in the portable format, prefixed by it's serialized size as a little endian `u32` 
+    /// so readers can skip over bitmaps without decoding them
+    /// 
+    /// # Returns
+    /// The number of bytes written to the buffer
+    #[cfg(target_endian = "little")]
+    pub fn serialize_many<W: Write>(bitmaps: &[RoaringBitmap], buf: &mut W) -> io::Result<usize> {
+        assert!(bitmaps.len() <= u32::MAX as usize, "Too many bitmaps for a single archive");
+
+        buf.write_all(&(bitmaps.len() as u32).to_le_bytes())?;
+        let mut bytes_written = mem::size_of::<u32>();
+
+        for bitmap in bitmaps.iter() {
+            let size = bitmap.serialized_size();
+            let frame_len = u32::try_from(size)
+                .map_err(|_err| io::Error::new(io::ErrorKind::InvalidInput, "Bitmap is too large for an archive frame"))?;
+
+            buf.write_all(&frame_len.to_le_bytes())?;
+
+            let written = bitmap.serialize(buf)?;
+            debug_assert_eq!(written, size);
+
+            bytes_written += mem::size_of::<u32>() + written;
+        }
+
+        Ok(bytes_written)
+    }
+
+    /// Deserialize a sequence of bitmaps written by [`serialize_many`]
+    /// 
+    /// # Remarks
+    /// Each bitmap is validated against the bounds of it's own frame
+    /// 
+    /// # Returns
+    /// The deserialized bitmaps in the order they were written
+    /// 
+    /// [`serialize_many`]: RoaringBitmap::serialize_many
+    #[cfg(target_endian = "little")]
+    pub fn deserialize_many<R: Read>(buf: &mut R) -> Result<Vec<Self>, DeserializeError> {
+        let read_u32 = |buf: &mut R| {
+            let mut bytes = [0; mem::size_of::<u32>()];
+            buf.read_exact(&mut bytes)
+                .map_err(DeserializeError::IoError)?;
+
+            Ok(u32::from_le_bytes(bytes))
+        };
+
+        let count = read_u32(buf)?;
+
+        let mut bitmaps = Vec::new();
+        for _ in 0..count {
+            let len = read_u32(buf)?;
+
+            // Deserialize straight from the stream, `take` stops the bitmap reading past it's frame
+            let mut frame = (&mut *buf).take(u64::from(len));
+            let bitmap = Self::deserialize(&mut frame)?;
+
+            if frame.limit() != 0 {
+                return Err(DeserializeError::InvalidFrameLength(len));
+            }
+
+            bitmaps.push(bitmap);
+        }
+
+        Ok(bitmaps)
+    }
+
+    /// Write every value in the bitmap to a stream as a flat array of little endian `u32`s in ascending order
+    /// 
+    /// # Remarks
+    /// This is not a roaring format and can't be read back with `deserialize`, 
+    /// it's intended for consumers expecting a plain sorted integer array.
+    /// Values are decoded and written in fixed size chunks
+    /// 
+    /// # Returns
+    /// The number of bytes written to the buffer
+    pub fn write_values_le<W: Write>(&self, buf: &mut W) -> io::Result<usize> {
+        const CHUNK_SIZE: usize = 1024;
+
+        let mut values = [0; CHUNK_SIZE];
+        let mut bytes = Vec::with_capacity(CHUNK_SIZE * mem::size_of::<u32>());
+        let mut cursor = DecodeCursor::new();
+        let mut written = 0;
+
+        loop {
+            let count = self.decode_into(&mut values, &mut cursor);
+            if count == 0 {
+                break;
+            }
+
+            bytes.clear();
+            for value in values[..count].iter() {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+
+            buf.write_all(&bytes)?;
+            written += bytes.len();
+        }
+
+        Ok(written)
+    }
+
+    /// Check that `data` contains a well formed serialized bitmap without deserializing it
+    /// 
+    /// # Remarks
+    /// The header, container offsets and container sizes are checked for consistency
+    /// and to ensure they fit within `data`. No containers are allocated, 
+    /// making this a cheap check before accepting untrusted input
+    pub fn validate_bytes(data: &[u8]) -> Result<(), DeserializeError> {
+        let eof = || DeserializeError::IoError(io::Error::from(io::ErrorKind::UnexpectedEof));
+        let read_u16 = |offset: usize| -> Result<u16, DeserializeError> {
+            let bytes = data.get(offset..(offset + 2)).ok_or_else(eof)?;
+
+            Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+        };
+
+        let header = SerialHeader::read(&mut &data[..])?;
+
+        // Walk the containers checking that each one fits and follows the last
+        let mut offset = header.len;
+        for i in 0..header.keys.len() {
+            let card = header.cardinality(i) as u32;
+
+            if let Some(&declared) = header.offsets.get(i) {
+                if declared as usize != offset {
+                    return Err(DeserializeError::InvalidOffset(declared));
+                }
+            }
+
+            let is_run = header.is_run(i);
+            let container_size = {
+                if is_run {
+                    let num_runs = read_u16(offset)? as usize;
+                    let runs = offset + 2;
+
+                    // The runs must cover exactly the declared cardinality
+                    let mut run_card = 0;
+                    for j in 0..num_runs {
+                        run_card += u32::from(read_u16(runs + 4 * j + 2)?) + 1;
+                    }
+
+                    if run_card != card {
+                        return Err(DeserializeError::InvalidCardinality(card));
+                    }
+
+                    RunContainer::serialized_size(num_runs)
+                }
+                else if card as usize > DEFAULT_MAX_SIZE {
+                    BitsetContainer::serialized_size()
+                }
+                else {
+                    ArrayContainer::serialized_size(card as usize)
+                }
+            };
+
+            offset += container_size;
+            if offset > data.len() {
+                return Err(eof());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serialize the bitmap and format the bytes as annotated hex for troubleshooting
+    /// 
+    /// # Remarks
+    /// Each line contains the byte offset, the bytes and a description of the field they encode.
+    /// Container data is split into lines of 16 bytes with the container described on the first line
+    #[cfg(target_endian = "little")]
+    pub fn hex_dump(&self) -> String {
+        let mut bytes = Vec::with_capacity(self.serialized_size());
+        self.serialize(&mut bytes)
+            .expect("Serializing into a vec can't fail");
+
+        let header = SerialHeader::read(&mut bytes.as_slice())
+            .expect("A serialized bitmap has a valid header");
+
+        let mut dump = String::new();
+        let size = header.keys.len();
+
+        // Cookie and container count
+        let mut offset = {
+            if header.has_packed_count() {
+                Self::hex_dump_line(&mut dump, 0, &bytes[0..4], &format!(
+                    "cookie {}, containers {}", 
+                    header.cookie & 0xFFFF, 
+                    size
+                ));
+
+                4
+            }
+            else {
+                Self::hex_dump_line(&mut dump, 0, &bytes[0..4], &format!("cookie {}", header.cookie));
+                Self::hex_dump_line(&mut dump, 4, &bytes[4..8], &format!("containers {}", size));
+
+                8
+            }
+        };
+
+        // Run container flags
+        if !header.run_flags.is_empty() {
+            let s = header.run_flags.len();
+            Self::hex_dump_line(&mut dump, offset, &bytes[offset..(offset + s)], "run flags");
+            offset += s;
+        }
+
+        // Keys and cardinalities
+        for (i, key) in header.keys.iter().enumerate() {
+            let note = format!("key {}, cardinality {}", key, header.cardinality(i));
+            Self::hex_dump_line(&mut dump, offset, &bytes[offset..(offset + 4)], &note);
+            offset += 4;
+        }
+
+        // Container offsets
+        for declared in header.offsets.iter() {
+            let note = format!("offset {}", declared);
+            Self::hex_dump_line(&mut dump, offset, &bytes[offset..(offset + 4)], &note);
+            offset += 4;
+        }
+
+        debug_assert_eq!(offset, header.len);
+
+        // Container data
+        for (i, c) in self.containers.iter().enumerate() {
+            let kind = match c {
+                Container::Run(_) => "run",
+                Container::None => "none",
+                _ if c.serializes_as_bitset() => "bitset",
+                _ => "array"
+            };
+
+            let end = offset + c.serialized_size();
+            let note = format!("container {} ({})", i, kind);
+
+            for (j, chunk) in bytes[offset..end].chunks(16).enumerate() {
+                let note = if j == 0 { note.as_str() } else { "" };
+                Self::hex_dump_line(&mut dump, offset + j * 16, chunk, note);
+            }
+
+            offset = end;
+        }
+
+        dump
+    }
+
+    /// Append a line to a hex dump containing the offset, bytes and an annotation
+    fn hex_dump_line(dump: &mut String, offset: usize, bytes: &[u8], note: &str) {
+        let hex: Vec<String> = bytes.iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        let line = format!("{:08x}  {:<48} {}", offset, hex.join(" "), note);
+
+        dump.push_str(line.trim_end());
+        dump.push('\n');
+    }
+
+    /// Check if `cookie` identifies any of the supported formats
+    fn is_valid_cookie(cookie: u32) -> bool {
+        (cookie & 0xFFFF) == Self::SERIAL_COOKIE 
+            || cookie == Self::SERIAL_COOKIE_NO_RUNCONTAINER 
+            || cookie == Self::SERIAL_COOKIE_NATIVE
+    }
+
+    /// Check if the format identified by `cookie` includes the run container flags
+    fn has_run_flags(cookie: u32) -> bool {
+        (cookie & 0xFFFF) == Self::SERIAL_COOKIE || cookie == Self::SERIAL_COOKIE_NATIVE
+    }
+
+    /// Check if the format identified by `cookie` includes the container offset header
+    fn has_offsets(cookie: u32, size: usize) -> bool {
+        if cookie == Self::SERIAL_COOKIE_NATIVE {
+            false
+        }
+        else {
+            !Self::has_run_flags(cookie) || size >= Self::NO_OFFSET_THRESHOLD as usize
+        }
+    }
+
+    /// Build the run container flags, one bit per container
+    fn run_flags(&self) -> Vec<u8> {
+        let mut flags: Vec<u8> = vec![0; self.containers.len().div_ceil(8)];
+
+        for (i, c) in self.containers.iter().enumerate() {
+            if c.is_run() {
+                flags[i / 8] |= 1 << (i % 8);
+            }
+        }
+
+        flags
+    }
+
+    /// Calculate the size of the serialized header for the bitmap
+    fn header_size(&self, format: SerializationFormat) -> usize {
+        let len = self.containers.len();
+        if format == SerializationFormat::Native {
+            4 + 4 + len.div_ceil(8) + 4 * len
+        }
+        else {
+            Self::portable_header_size(len, self.has_run())
+        }
+    }
+
+    /// Get the size of the portable header for `len` containers
+    fn portable_header_size(len: usize, contains_run: bool) -> usize {
+        if contains_run {
+            if len < (Self::NO_OFFSET_THRESHOLD as usize) {
+                4 + (len + 7) / 8 + 4 * len
+            }
+            else {
+                4 + (len + 7) / 8 + 8 * len
+            }
+        }
+        else {
+            4 + 4 + 8 * len
+        }
+    }
+
+    /// Check if the bitmap contains any run containers
+    fn has_run(&self) -> bool {
+        for c in self.containers.iter() {
+            if c.is_run() {
+                return true;
+            }
+        }
+        
+        false
+    }
+}
+
+/// A bitmap ordered lexicographically by it's sorted values, for use as a key in sorted collections
+/// 
+/// # Remarks
+/// The ordering is [`cmp_values`]. It's kept on a wrapper rather than implemented for [`RoaringBitmap`]
+/// directly so `Ord::min` and `Ord::max` don't shadow the bitmap's own `min` and `max`.
+/// The cached bounds are interior mutable but never take part in the ordering,
+/// so clippy's `mutable_key_type` lint is a false positive for collections keyed by it
+/// 
+/// [`cmp_values`]: RoaringBitmap::cmp_values
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OrderedBitmap(pub RoaringBitmap);
+
+impl OrderedBitmap {
+    /// Get the wrapped bitmap back
+    #[inline]
+    pub fn into_inner(self) -> RoaringBitmap {
+        self.0
+    }
+}
+
+impl From<RoaringBitmap> for OrderedBitmap {
+    fn from(bitmap: RoaringBitmap) -> Self {
+        OrderedBitmap(bitmap)
+    }
+}
+
+impl AsRef<RoaringBitmap> for OrderedBitmap {
+    fn as_ref(&self) -> &RoaringBitmap {
+        &self.0
+    }
+}
+
+impl PartialOrd for OrderedBitmap {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrderedBitmap {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp_values(&other.0)
+    }
+}
+
+/// The position to resume from when decoding a bitmap in chunks with [`decode_into`]
+/// 
+/// [`decode_into`]: RoaringBitmap::decode_into
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DecodeCursor {
+    /// The index of the container being decoded
+    index: usize,
+
+    /// The lowest value of the container that hasn't been decoded
+    next: u16
+}
+
+impl DecodeCursor {
+    /// Create a cursor positioned at the start of a bitmap
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// An iterator over a roaring bitmap
+pub struct Iter<'a> {
+    /// The containers we're iterating
+    containers: &'a Vec<Container>,
+    
+    /// The keys we're iterating
+    keys: &'a Vec<u16>,
+    
+    /// The container iterator we're currently iterating
+    iter: container::Iter<'a>,
+    
+    /// The index of the container we're iterating
+    index: usize
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = u32;
+    
+    fn next(&mut self) -> Option<Self::Item> {
+        // Try to advance the container iterator
+        let mut item = self.iter.next();
+
+        // If we reached the end of the iterator try to advance to the next container
+        if item.is_none() {
+            let next_index = self.index + 1;
+            if next_index < self.containers.len() {
+                // Advance the index
+                self.index = next_index;
+                self.iter = self.containers[next_index].iter();
                 
                 // Get the next value
                 item = self.iter.next();
             }
         }
 
-        item.map(|low| {
-            let key = self.keys[self.index];
-            
-            (u32::from(key) << 16) | u32::from(low)
-        })
+        item.map(|low| {
+            let key = self.keys[self.index];
+            
+            (u32::from(key) << 16) | u32::from(low)
+        })
+    }
+
+    /// Counted from the current container iterator and the containers after it, 
+    /// creating the iterator doesn't need the cardinality of the whole bitmap
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let following = self.containers.get((self.index + 1)..)
+            .unwrap_or(&[]);
+
+        let remaining = following.iter()
+            .fold(self.iter.len(), |sum, c| sum + c.cardinality());
+
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> { }
+
+/// Accumulates the union of many bitmaps, deferring all container merges until [`finish`]
+/// 
+/// # Remarks
+/// Pushing a bitmap only records it. On [`finish`] the containers of all bitmaps are merged
+/// per key in a single pass, accumulating shared keys into a bitset that is optimized once at the end
+/// 
+/// [`finish`]: LazyUnion::finish
+#[derive(Default)]
+pub struct LazyUnion<'a> {
+    /// The bitmaps to union
+    bitmaps: Vec<&'a RoaringBitmap>
+}
+
+impl<'a> LazyUnion<'a> {
+    /// Create a new empty accumulator
+    pub fn new() -> Self {
+        Self {
+            bitmaps: Vec::new()
+        }
+    }
+
+    /// Add a bitmap to the union
+    pub fn push(&mut self, bitmap: &'a RoaringBitmap) {
+        if !bitmap.is_empty() {
+            self.bitmaps.push(bitmap);
+        }
+    }
+
+    /// Compute the union of all pushed bitmaps
+    pub fn finish(self) -> RoaringBitmap {
+        match self.bitmaps.len() {
+            0 => return RoaringBitmap::new(),
+            1 => return self.bitmaps[0].clone(),
+            _ => ()
+        }
+
+        // Gather every container and group them by key, the sort is stable so pushes retain their order
+        let mut entries: Vec<(u16, &Container)> = self.bitmaps.iter()
+            .flat_map(|b| b.keys.iter().cloned().zip(b.containers.iter()))
+            .collect();
+
+        entries.sort_by_key(|(key, _)| *key);
+
+        let mut result = RoaringBitmap::new();
+
+        let mut i = 0;
+        while i < entries.len() {
+            let key = entries[i].0;
+            let end = i + entries[i..].iter()
+                .take_while(|(k, _)| *k == key)
+                .count();
+
+            let container = {
+                if end - i == 1 {
+                    entries[i].1.clone()
+                }
+                else {
+                    let mut bitset = BitsetContainer::new();
+                    for (_, c) in entries[i..end].iter() {
+                        c.or_into_bitset(&mut bitset);
+                    }
+
+                    Container::Bitset(bitset).optimize()
+                }
+            };
+
+            result.keys.push(key);
+            result.containers.push(container);
+
+            i = end;
+        }
+
+        result
+    }
+}
+
+/// Allocates the smallest unused ids, reusing ids once they are freed
+/// 
+/// # Remarks
+/// The allocator tracks the smallest free id and searches forward from it,
+/// so repeated allocations don't rescan the bitmap for the first gap
+#[derive(Clone, Debug)]
+pub struct IdAllocator {
+    /// The ids currently allocated
+    bitmap: RoaringBitmap,
+
+    /// The smallest unallocated id, `None` if every id is allocated
+    next: Option<u32>
+}
+
+impl Default for IdAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdAllocator {
+    /// Create a new allocator with no ids allocated
+    pub fn new() -> Self {
+        Self {
+            bitmap: RoaringBitmap::new(),
+            next: Some(0)
+        }
+    }
+
+    /// Allocate the smallest unused id
+    /// 
+    /// # Panics
+    /// Panics if every id is allocated
+    pub fn alloc(&mut self) -> u32 {
+        let id = self.next.expect("Every id is allocated");
+
+        self.bitmap.add(id);
+        self.next = id.checked_add(1);
+        self.advance();
+
+        id
+    }
+
+    /// Free an id so that it may be allocated again
+    pub fn free(&mut self, id: u32) {
+        self.bitmap.remove(id);
+
+        match self.next {
+            Some(next) if next <= id => (),
+            _ => self.next = Some(id)
+        }
+    }
+
+    /// Check whether an id is allocated
+    #[inline]
+    pub fn is_allocated(&self, id: u32) -> bool {
+        self.bitmap.contains(id)
+    }
+
+    /// Get the bitmap of allocated ids
+    #[inline]
+    pub fn allocated(&self) -> &RoaringBitmap {
+        &self.bitmap
+    }
+
+    /// Move `next` forward to the first unallocated id
+    /// 
+    /// # Remarks
+    /// Every id below `next` is allocated, so the smallest value from `next` missing from the
+    /// container holding it is the first unallocated id unless the rest of the container is full.
+    /// The search starts at `next` so the ids already allocated before it aren't rescanned
+    fn advance(&mut self) {
+        while let Some(next) = self.next {
+            let key = (next >> 16) as u16;
+            let container = match self.bitmap.get_index(key) {
+                Ok(index) => &self.bitmap.containers[index],
+                Err(_) => return
+            };
+
+            if !container.contains(next as u16) {
+                return;
+            }
+
+            self.next = match container.min_excluded_from(next as u16) {
+                Some(low) => Some((u32::from(key) << 16) | u32::from(low)),
+                None if key == u16::MAX => None,
+                None => Some((u32::from(key) + 1) << 16)
+            };
+        }
+    }
+}
+
+/// A dictionary mapping values of a bitmap to their dense 0 based rank
+pub struct RankDict<'a> {
+    /// The bitmap being mapped
+    bitmap: &'a RoaringBitmap,
+
+    /// The cardinality of all containers preceeding the container at each index
+    prefix: Vec<usize>
+}
+
+impl<'a> RankDict<'a> {
+    /// Get the 0 based rank of `value`. Returns `None` if the value is not in the bitmap
+    pub fn get(&self, value: u32) -> Option<usize> {
+        let key = (value >> 16) as u16;
+        let index = self.bitmap.get_index(key).ok()?;
+        let container = &self.bitmap.containers[index];
+
+        if container.contains(value as u16) {
+            Some(self.prefix[index] + container.rank(value as u16) - 1)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Get the number of values in the dictionary
+    pub fn len(&self) -> usize {
+        match self.prefix.last() {
+            Some(prefix) => prefix + self.bitmap.containers[self.prefix.len() - 1].cardinality(),
+            None => 0
+        }
+    }
+
+    /// Check whether the dictionary is empty
+    pub fn is_empty(&self) -> bool {
+        self.prefix.is_empty()
+    }
+}
+
+/// A dictionary mapping dense 0 based ranks to the values of a bitmap
+pub struct SelectDict<'a> {
+    /// The bitmap being mapped
+    bitmap: &'a RoaringBitmap,
+
+    /// The cardinality of all containers preceeding the container at each index
+    prefix: Vec<usize>
+}
+
+impl<'a> SelectDict<'a> {
+    /// Get the value with the 0 based `rank`. Returns `None` if the rank exceeds the cardinality of the bitmap
+    pub fn get(&self, rank: u32) -> Option<u32> {
+        // Find the last container that starts at or before `rank`, an empty container shares
+        // it's prefix with the container after it so the last match is the one holding `rank`
+        let index = self.prefix.partition_point(|prefix| *prefix <= rank as usize)
+            .checked_sub(1)?;
+
+        let mut start_rank = self.prefix[index] as u32;
+        let low = self.bitmap.containers[index].select(rank, &mut start_rank)?;
+        let key = self.bitmap.keys[index];
+
+        Some((u32::from(key) << 16) | u32::from(low))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use crate::{RoaringBitmap, OrderedBitmap, SelectError, JoinSide, LazyUnion, IdAllocator, ContainerPart, PartsError, RunStats, SerializationFormat, DecodeCursor, GrowthPolicy, DeserializeError, BitmapReader};
+    use crate::test::*;
+
+    impl TestShim<u32> for RoaringBitmap {
+        fn from_data(data: &[u32]) -> Self {
+            Self::from_slice(data)
+        }
+
+        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=u32> + 'a> {
+            Box::new(self.iter())
+        }
+
+        fn card(&self) -> usize {
+            self.cardinality()
+        }
+    }
+
+    #[test]
+    fn iter_len() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..(1 << 20), 5_000));
+        bitmap.add_interval(300_000, 400_000);
+
+        let mut iter = bitmap.iter();
+        let mut expected = bitmap.cardinality();
+        assert_eq!(iter.len(), expected);
+
+        while iter.next().is_some() {
+            expected -= 1;
+            assert_eq!(iter.len(), expected);
+        }
+
+        assert_eq!(RoaringBitmap::new().iter().len(), 0);
+    }
+
+    #[test]
+    fn from_range() {
+        let bitmap = RoaringBitmap::from_range(0..std::u32::MAX);
+
+        assert_eq!(bitmap.cardinality(), std::u32::MAX as usize);
+    }
+
+    #[test]
+    fn from_slice() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let bitmap = RoaringBitmap::from_slice(&input);
+
+        assert_eq!(bitmap.len(), input.len());
+
+        for (found, expected) in bitmap.iter().zip(input.iter()) {
+            assert_eq!(found, *expected);
+        }
+    }
+
+    #[test]
+    fn copy_from() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let bitmap_a = RoaringBitmap::from_slice(&input);
+
+        let mut bitmap_b = RoaringBitmap::new();
+        bitmap_b.copy_from(&bitmap_a);
+
+        assert_eq!(bitmap_a.len(), bitmap_b.len());
+
+        let iter = bitmap_a.iter()
+            .zip(bitmap_b.iter());
+
+        for (a, b) in iter {
+            assert_eq!(a, b);
+        }
+    }
+
+    #[test]
+    fn add() {
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add(10);
+        bitmap.add(255678);
+
+        assert_eq!(bitmap.cardinality(), 2);
+        assert!(bitmap.contains(10));
+        assert!(bitmap.contains(255678));
+    }
+
+    #[test]
+    fn add_range() {
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_range(0..1_000_000);
+
+        assert_eq!(bitmap.cardinality(), 1_000_000);
+    }
+
+    #[test]
+    fn extract_container() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..(1 << 22), 30_000));
+        bitmap.add_interval(5 << 16, (6 << 16) + 100);
+
+        for key in [0u16, 3, 5, 6, 63].iter() {
+            let extracted = bitmap.extract_container(*key).unwrap();
+            let expected = bitmap.iter().filter(|v| (v >> 16) as u16 == *key);
+
+            assert_eq!(extracted.keys, vec![*key]);
+            assert!(extracted.iter().all(|v| (v >> 16) as u16 == *key));
+            assert!(extracted.iter().eq(expected));
+        }
+
+        assert!(bitmap.extract_container(64).is_none());
+        assert!(RoaringBitmap::new().extract_container(0).is_none());
+    }
+
+    #[test]
+    fn append_bitmap() {
+        let mut a = RoaringBitmap::from_slice(&generate_data(0..1_000_000, 20_000));
+        a.add(1_000_100);
+
+        // The first container of `b` shares a key with the last container of `a`
+        let mut b = RoaringBitmap::from_slice(&generate_seeded_data(1_000_200..3_000_000, 20_000, 1));
+        b.add_range(4_000_000..4_200_000);
+
+        let expected = a.or(&b);
+
+        let mut found = a.clone();
+        found.append_bitmap(&b);
+        assert_eq!(found.cardinality(), expected.cardinality());
+        assert!(found.iter().eq(expected.iter()));
+        assert_eq!(found.keys, expected.keys);
+
+        // Appending onto or from empty bitmaps
+        let mut empty = RoaringBitmap::new();
+        empty.append_bitmap(&b);
+        assert!(empty.iter().eq(b.iter()));
+
+        found.append_bitmap(&RoaringBitmap::new());
+        assert!(found.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn run_stats() {
+        let mut bitmap = RoaringBitmap::new();
+
+        // Two short runs stored in an array
+        bitmap.add_range(0..10);
+        bitmap.add_range(20..25);
+
+        // A single full run
+        bitmap.add_interval(1 << 16, (2 << 16) - 1);
+
+        // 10,000 isolated values stored in a bitset
+        for i in 0..10_000 {
+            bitmap.add((2 << 16) + i * 2);
+        }
+
+        let stats = bitmap.run_stats();
+        assert_eq!(stats.total_runs, 10_003);
+        assert_eq!(stats.longest_run, 1 << 16);
+        assert_eq!(stats.containers_better_as_run, 2);
+        assert!((stats.mean_run_length - 75_551.0 / 10_003.0).abs() < 1e-9);
+
+        assert_eq!(RoaringBitmap::new().run_stats(), RunStats::default());
+    }
+
+    #[test]
+    fn would_run_optimize_help() {
+        // Contiguous values added one at a time are stored in arrays and bitsets
+        let mut contiguous = RoaringBitmap::new();
+        for i in 0..200_000 {
+            contiguous.add(i);
+        }
+
+        assert!(contiguous.would_run_optimize_help());
+
+        let mut compacted = contiguous.clone();
+        compacted.compact();
+        assert!(compacted.serialized_size() < contiguous.serialized_size());
+        assert!(!compacted.would_run_optimize_help());
+
+        // Isolated values gain nothing from runs
+        let mut scattered = RoaringBitmap::new();
+        for i in 0..100_000 {
+            scattered.add(i * 3);
+        }
+
+        assert!(!scattered.would_run_optimize_help());
+        assert!(!RoaringBitmap::new().would_run_optimize_help());
+    }
+
+    #[test]
+    fn max_array_size() {
+        let kinds = |bitmap: &RoaringBitmap| -> Vec<bool> {
+            bitmap.containers.iter().map(|c| matches!(c, crate::container::Container::Array(_))).collect()
+        };
+
+        // A larger threshold keeps values in an array past the default size
+        let mut large = RoaringBitmap::with_max_array_size(10_000);
+        let mut default = RoaringBitmap::new();
+        for value in (0..20_000).step_by(2) {
+            large.add(value);
+            default.add(value);
+
+            let expected = (value / 2) < 10_000;
+            assert_eq!(kinds(&large), vec![expected]);
+        }
+
+        assert_eq!(kinds(&default), vec![false]);
+        assert_eq!(large.max_array_size(), 10_000);
+
+        // A smaller threshold promotes to a bitset earlier and demotes when shrinking below it
+        let mut small = RoaringBitmap::with_max_array_size(64);
+        for value in 0..64 {
+            small.add(value * 3);
+        }
+
+        assert_eq!(kinds(&small), vec![true]);
+        small.add(1);
+        assert_eq!(kinds(&small), vec![false]);
+
+        small.remove(1);
+        assert_eq!(kinds(&small), vec![false]);
+        small.remove(0);
+        assert_eq!(kinds(&small), vec![true]);
+        assert_eq!(small.cardinality(), 63);
+
+        // The serialized format is unaffected by the threshold
+        for bitmap in [large, small].iter() {
+            let mut buffer = Vec::new();
+            bitmap.serialize(&mut buffer).unwrap();
+            assert_eq!(buffer.len(), bitmap.serialized_size());
+
+            let result = RoaringBitmap::deserialize(&mut std::io::Cursor::new(&buffer)).unwrap();
+            assert!(result.iter().eq(bitmap.iter()));
+        }
+    }
+
+    #[test]
+    fn max_array_size_mutations() {
+        let kinds = |bitmap: &RoaringBitmap| -> Vec<bool> {
+            bitmap.containers.iter().map(|c| matches!(c, crate::container::Container::Array(_))).collect()
+        };
+
+        let evens = |range: std::ops::Range<u32>, size: usize| {
+            let mut bitmap = RoaringBitmap::with_max_array_size(size);
+            for value in range.step_by(2) {
+                bitmap.add(value);
+            }
+            bitmap
+        };
+
+        // Set operation results keep the threshold of the left hand side
+        let a = evens(0..10_000, 10_000);
+        let b = evens(10_000..20_000, 10_000);
+        for result in [a.or(&b), a.xor(&b), a.or_with_cardinality(&b).0].iter() {
+            assert_eq!(result.max_array_size(), 10_000);
+            assert_eq!(kinds(result), vec![true]);
+            assert_eq!(result.cardinality(), 10_000);
+        }
+
+        let mut inplace = a.clone();
+        inplace.inplace_or(&b);
+        assert_eq!(kinds(&inplace), vec![true]);
+
+        let mut owned = a.clone();
+        owned.inplace_xor_owned(b.clone());
+        assert_eq!(kinds(&owned), vec![true]);
+
+        let a = evens(0..128, 64);
+        let b = evens(128..256, 64);
+        assert_eq!(kinds(&a.or(&b)), vec![false]);
+
+        let mut inplace = a.clone();
+        inplace.inplace_or(&b);
+        assert_eq!(kinds(&inplace), vec![false]);
+
+        // Range mutations respect the threshold
+        let mut ranged = RoaringBitmap::with_max_array_size(64);
+        ranged.add_range(0..100);
+        assert_eq!(kinds(&ranged), vec![false]);
+        ranged.remove_range(10..100);
+        assert_eq!(kinds(&ranged), vec![true]);
+
+        let mut flipped = RoaringBitmap::with_max_array_size(64);
+        flipped.inplace_not(0..100);
+        assert_eq!(kinds(&flipped), vec![false]);
+
+        // Deserialized bitmaps use the default until the threshold is restored
+        let large = evens(0..20_000, 10_000);
+        let mut buffer = Vec::new();
+        large.serialize(&mut buffer).unwrap();
+
+        let mut result = RoaringBitmap::deserialize(&mut std::io::Cursor::new(&buffer)).unwrap();
+        assert_eq!(kinds(&result), vec![false]);
+        result.set_max_array_size(10_000);
+        assert_eq!(kinds(&result), vec![true]);
+        assert!(result.iter().eq(large.iter()));
+
+        // An array holding exactly `DEFAULT_MAX_SIZE` values isn't full
+        let mut bitmap = evens(0..8192, crate::container::DEFAULT_MAX_SIZE);
+        bitmap.inplace_or(&RoaringBitmap::from_slice(&[1]));
+        assert!(bitmap.contains(1));
+        assert_eq!(bitmap.cardinality(), crate::container::DEFAULT_MAX_SIZE + 1);
+    }
+
+    #[test]
+    fn parts() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..(1 << 22), 30_000));
+        bitmap.add_interval(5 << 16, (6 << 16) + 100);
+
+        let (keys, containers) = bitmap.clone().into_parts();
+        assert_eq!(keys, bitmap.keys);
+        assert_eq!(containers.iter().map(|c| c.cardinality()).sum::<usize>(), bitmap.cardinality());
+
+        let rebuilt = RoaringBitmap::from_parts(keys, containers).unwrap();
+        assert!(rebuilt.iter().eq(bitmap.iter()));
+
+        // Precomputed containers
+        let parts = vec![ContainerPart::from_slice(&[1, 5, 9]), ContainerPart::from_range(0..40_000)];
+        let built = RoaringBitmap::from_parts(vec![2, 7], parts.clone()).unwrap();
+        let expected = [1, 5, 9].iter().map(|v| (2 << 16) | v)
+            .chain((0..40_000).map(|v| (7 << 16) | v));
+
+        assert!(built.iter().eq(expected));
+
+        // A container may hold every value
+        let full = ContainerPart::from_range(0..(1 << 16));
+        assert_eq!(full.cardinality(), 1 << 16);
+        assert!(full.contains(0) && full.contains(u16::MAX));
+        assert!(ContainerPart::from_range(10..10).is_empty());
+
+        let built = RoaringBitmap::from_parts(vec![u16::MAX], vec![full]).unwrap();
+        assert_eq!(built.min(), Some(u32::MAX - u32::from(u16::MAX)));
+        assert_eq!(built.max(), Some(u32::MAX));
+
+        // Invalid inputs
+        assert_eq!(
+            RoaringBitmap::from_parts(vec![2], parts.clone()).unwrap_err(), 
+            PartsError::LengthMismatch { keys: 1, containers: 2 }
+        );
+        assert_eq!(
+            RoaringBitmap::from_parts(vec![7, 2], parts.clone()).unwrap_err(),
+            PartsError::UnsortedKeys(1)
+        );
+        assert_eq!(
+            RoaringBitmap::from_parts(vec![2, 2], parts.clone()).unwrap_err(),
+            PartsError::UnsortedKeys(1)
+        );
+        assert_eq!(
+            RoaringBitmap::from_parts(vec![2, 7], vec![parts[0].clone(), ContainerPart::from_slice(&[])]).unwrap_err(),
+            PartsError::EmptyContainer(1)
+        );
+    }
+
+    #[test]
+    fn min_excluded() {
+        let bitmap = RoaringBitmap::from_slice(&[0, 1, 2, 4]);
+        assert_eq!(bitmap.min_excluded(), Some(3));
+
+        assert_eq!(RoaringBitmap::new().min_excluded(), Some(0));
+        assert_eq!(RoaringBitmap::from_slice(&[1, 2]).min_excluded(), Some(0));
+
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_range(0..=100);
+        assert_eq!(bitmap.min_excluded(), Some(101));
+
+        // Gaps spanning entire containers and full run or bitset containers
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_interval(0, (3 << 16) - 1);
+        bitmap.add(5 << 16);
+        assert_eq!(bitmap.min_excluded(), Some(3 << 16));
+
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_range(0..(1 << 16));
+        bitmap.add_range((1 << 16)..((1 << 16) + 10_000));
+        assert_eq!(bitmap.min_excluded(), Some((1 << 16) + 10_000));
+
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_interval(0, (2 << 16) - 1);
+        assert_eq!(bitmap.min_excluded(), Some(2 << 16));
+
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_interval(0, u32::MAX);
+        assert_eq!(bitmap.min_excluded(), None);
+    }
+
+    #[test]
+    fn add_ranges() {
+        let ranges = vec![
+            500_000..600_000,
+            10..100,
+            50..200,
+            200..300,
+            65_000..70_000,
+            1_000..1_000,
+            69_999..140_000,
+            300_000..300_005,
+            300_010..300_020,
+            5_000_000..5_000_001
+        ];
+
+        let data = generate_data(0..(1 << 21), 10_000);
+
+        let mut expected = RoaringBitmap::from_slice(&data);
+        for range in ranges.iter() {
+            expected.add_range(range.clone());
+        }
+
+        let mut found = RoaringBitmap::from_slice(&data);
+        found.add_ranges(ranges.iter().cloned());
+
+        assert_eq!(found.cardinality(), expected.cardinality());
+        assert!(found.iter().eq(expected.iter()));
+        assert_eq!(found.keys, expected.keys);
+
+        // Sorted ranges on an empty bitmap
+        let mut sorted = ranges.clone();
+        sorted.sort_by_key(|r| r.start);
+
+        let mut expected = RoaringBitmap::new();
+        for range in sorted.iter() {
+            expected.add_range(range.clone());
+        }
+
+        let mut found = RoaringBitmap::new();
+        found.add_ranges(sorted);
+
+        assert!(found.iter().eq(expected.iter()));
+        assert_eq!(found.keys, expected.keys);
+    }
+
+    #[test]
+    fn add_interval() {
+        let mut bitmap = RoaringBitmap::new();
+
+        // Overlapping intervals
+        bitmap.add_interval(10, 100);
+        bitmap.add_interval(50, 200);
+
+        // Adjacent intervals spanning several containers
+        bitmap.add_interval(60_000, 131_071);
+        bitmap.add_interval(131_072, 200_000);
+
+        // An interval at the top of the universe
+        bitmap.add_interval(u32::MAX - 10, u32::MAX);
+
+        let mut expected: Vec<u32> = (10..=200).collect();
+        expected.extend(60_000..=200_000);
+        expected.extend((u32::MAX - 10)..=u32::MAX);
+
+        let found: Vec<u32> = bitmap.iter()
+            .collect();
+
+        assert_eq!(found, expected);
+        assert_eq!(bitmap.cardinality(), expected.len());
+
+        // Completely covered containers are stored as runs
+        let index = bitmap.get_index(1).unwrap();
+        assert!(bitmap.containers[index].is_full());
+        assert!(bitmap.containers[index].is_run());
+
+        // Filling the rest of a partially covered container also converts it to a run
+        bitmap.add_interval(0, 65_535);
+        let index = bitmap.get_index(0).unwrap();
+        assert!(bitmap.containers[index].is_run());
+        assert!(bitmap.containers[index].is_full());
+    }
+
+    #[test]
+    fn add_slice() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_slice(&input);
+
+        assert_eq!(bitmap.len(), input.len());
+
+        for (found, expected) in bitmap.iter().zip(input.iter()) {
+            assert_eq!(found, *expected);
+        }
+    }
+
+    #[test]
+    fn add_slice_sorted() {
+        let individually = |initial: &RoaringBitmap, values: &[u32]| {
+            let mut bitmap = initial.clone();
+            for value in values.iter() {
+                bitmap.add(*value);
+            }
+
+            bitmap
+        };
+
+        let mut existing = RoaringBitmap::from_slice(&generate_seeded_data(0..4_000_000, 20_000, 1));
+        existing.add_interval(2_000_000, 2_100_000);
+
+        // Sorted with duplicates, dense enough to produce bitsets
+        let mut sorted = generate_seeded_data(0..4_000_000, 150_000, 2);
+        sorted.extend_from_slice(&[10, 10, 10]);
+        sorted.sort_unstable();
+
+        // Groups split up by values from other containers, some out of order
+        let mut unsorted = sorted.clone();
+        unsorted.swap(1, 2);
+        unsorted.reverse();
+        unsorted[1_000..50_000].sort_unstable();
+
+        for initial in [RoaringBitmap::new(), existing.clone(), RoaringBitmap::with_max_array_size(64)].iter() {
+            for values in [&sorted, &unsorted].iter() {
+                let mut bitmap = initial.clone();
+                bitmap.add_slice(values);
+
+                let expected = individually(initial, values);
+                assert_eq!(bitmap.cardinality(), expected.cardinality());
+                assert!(bitmap.iter().eq(expected.iter()));
+                assert!(bitmap.keys.windows(2).all(|w| w[0] < w[1]));
+
+                let arrays_within_bound = bitmap.containers.iter()
+                    .all(|c| match c {
+                        crate::container::Container::Array(a) => a.cardinality() <= bitmap.max_array_size(),
+                        _ => true
+                    });
+                assert!(arrays_within_bound);
+            }
+        }
+    }
+
+    #[test]
+    fn merge_sorted() {
+        let mut existing = RoaringBitmap::from_slice(&generate_seeded_data(0..4_000_000, 20_000, 1));
+        existing.add_interval(2_000_000, 2_100_000);
+        existing.add(u32::MAX);
+
+        // Sorted with duplicates, dense enough to produce bitsets and landing between existing containers
+        let mut sorted = generate_seeded_data(0..8_000_000, 150_000, 2);
+        sorted.extend_from_slice(&[10, 10, 10, u32::MAX - 1]);
+        sorted.sort_unstable();
+
+        let mut unsorted = sorted.clone();
+        unsorted.reverse();
+        unsorted[1_000..50_000].sort_unstable();
+
+        for initial in [RoaringBitmap::new(), existing.clone(), RoaringBitmap::with_max_array_size(64)].iter() {
+            for values in [&sorted, &unsorted].iter() {
+                let mut bitmap = initial.clone();
+                bitmap.merge_sorted(values.iter().cloned());
+
+                let mut expected = initial.clone();
+                for value in values.iter() {
+                    expected.add(*value);
+                }
+
+                assert_eq!(bitmap.cardinality(), expected.cardinality());
+                assert!(bitmap.iter().eq(expected.iter()));
+                assert!(bitmap.keys.windows(2).all(|w| w[0] < w[1]));
+                assert_eq!(bitmap.max(), expected.max());
+            }
+        }
+
+        let mut bitmap = existing.clone();
+        bitmap.merge_sorted(std::iter::empty());
+        assert!(bitmap.iter().eq(existing.iter()));
+    }
+
+    #[test]
+    fn remove() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_slice(&input);
+        
+        // Remove the first 100 elements from the bitmap
+        for i in 0..100 {
+            bitmap.remove(input[i]);
+        }
+
+        assert_eq!(bitmap.len(), input.len() - 100);
+
+        for (found, expected) in bitmap.iter().zip(input[100..].iter()) {
+            assert_eq!(found, *expected);
+        }
+    }
+
+    #[test]
+    fn remove_range_bitset() {
+        // Clearing a bitset container completely removes it
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..(3 << 16), 30_000));
+        bitmap.remove_range(65_536..131_072);
+
+        assert_eq!(bitmap.keys, vec![0, 2]);
+        assert!(bitmap.containers.iter().all(|c| !c.is_empty()));
+    }
+
+    #[test]
+    fn remove_range() {
+        const MIN: u32 = 5_000_000;
+        const MAX: u32 = 10_000_000;
+
+        let input = generate_data(0..20_000_000, 500_000);
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_slice(&input);
+
+        // Remove the first all elements in [min-max)
+        bitmap.remove_range(MIN..MAX);
+
+        let mut result = Vec::with_capacity(input.len());
+        for value in input.iter() {
+            if *value < MIN || *value >= MAX {
+                result.push(*value);
+            }
+        }
+
+        assert_eq!(bitmap.cardinality(), result.len());
+
+        let iter = bitmap.iter()
+            .zip(result.iter());
+
+        for (found, expected) in iter {
+            assert_eq!(
+                found,
+                *expected,
+                "Missmatched elements. Found {:?}, expected {:?}",
+                found,
+                *expected
+            );
+        }
+    }
+
+    #[test]
+    fn keep_outside_range() {
+        let mut input = RoaringBitmap::from_slice(&generate_data(0..20_000_000, 500_000));
+        input.add_range(3_000_000..3_200_000);
+        input.add_range((u32::MAX - 100)..=u32::MAX);
+
+        let ranges = [5_000_000..10_000_000, 0..1, 3_100_000..3_100_001, 0..0, 65_536..131_072];
+        for range in ranges.iter() {
+            let mut bitmap = input.clone();
+            bitmap.keep_outside_range(range.clone());
+
+            let expected = input.iter().filter(|value| !range.contains(value));
+            assert!(bitmap.iter().eq(expected), "Failed for {:?}", range);
+        }
+
+        let mut bitmap = input.clone();
+        bitmap.keep_outside_range(3_000_000..);
+        assert!(bitmap.iter().eq(input.iter().filter(|value| *value < 3_000_000)));
+    }
+
+    #[test]
+    fn keep_inside_range() {
+        let mut input = RoaringBitmap::from_slice(&generate_data(0..20_000_000, 500_000));
+        input.add_range(3_000_000..3_200_000);
+        input.add_range((u32::MAX - 100)..=u32::MAX);
+
+        let ranges = [5_000_000..10_000_000, 0..1, 3_100_000..3_100_001, 0..0, 65_536..131_072, 30_000_000..40_000_000];
+        for range in ranges.iter() {
+            let mut bitmap = input.clone();
+            bitmap.keep_inside_range(range.clone());
+
+            let expected = input.iter().filter(|value| range.contains(value));
+            assert!(bitmap.iter().eq(expected), "Failed for {:?}", range);
+            assert!(bitmap.containers.iter().all(|c| !c.is_empty()), "Failed for {:?}", range);
+        }
+
+        let mut bitmap = input.clone();
+        bitmap.keep_inside_range((u32::MAX - 50)..);
+        assert!(bitmap.iter().eq((u32::MAX - 50)..=u32::MAX));
+
+        let mut bitmap = input.clone();
+        bitmap.keep_inside_range(..);
+        assert!(bitmap.iter().eq(input.iter()));
+    }
+
+    #[test]
+    fn remove_slice() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_slice(&input);
+        
+        // Remove the first 100 elements from the bitmap
+        bitmap.remove_slice(&input[..100]);
+
+        assert_eq!(bitmap.len(), input.len() - 100);
+
+        for (found, expected) in bitmap.iter().zip(input[100..].iter()) {
+            assert_eq!(found, *expected);
+        }
+
+        // Emptying a container in the middle removes it along with it's key
+        let mut bitmap = RoaringBitmap::from_slice(&[1, 2, 70_000, 70_001, 200_000]);
+        bitmap.remove_slice(&[70_000, 70_001]);
+
+        assert_eq!(bitmap.keys, vec![0, 3]);
+        assert_eq!(bitmap.containers.len(), 2);
+        assert!(bitmap.iter().eq([1, 2, 200_000].iter().cloned()));
+    }
+
+    #[test]
+    fn contains() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_slice(&input);
+
+        for value in &input[..100] {
+            assert!(bitmap.contains(*value));
+        }
+    }
+
+    #[test]
+    fn not_cardinality() {
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_range(100..200);
+        bitmap.add_range(300_000..500_000);
+        bitmap.add(u32::MAX);
+
+        // Bounded ranges
+        assert_eq!(bitmap.not_cardinality(0..100), 100);
+        assert_eq!(bitmap.not_cardinality(100..200), 0);
+        assert_eq!(bitmap.not_cardinality(150..=250), 51);
+        assert_eq!(bitmap.not_cardinality(5..5), 0);
+        assert_eq!(bitmap.not_cardinality(0..1_000_000), 1_000_000 - 100 - 200_000);
+
+        // The full universe
+        let card = bitmap.cardinality() as u64;
+        assert_eq!(bitmap.not_cardinality(..), (1 << 32) - card);
+        assert_eq!(bitmap.not_cardinality(0..=u32::MAX), (1 << 32) - card);
+        assert_eq!(bitmap.not_cardinality(u32::MAX..), 0);
+        assert_eq!(RoaringBitmap::new().not_cardinality(..), 1 << 32);
+        assert_eq!(RoaringBitmap::new().not_cardinality(u32::MAX..), 1);
+    }
+
+    #[test]
+    fn or_range_cardinality() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..2_000_000, 10_000));
+        bitmap.add_range(300_000..500_000);
+        bitmap.add(u32::MAX);
+        let card = bitmap.cardinality() as u64;
+
+        let check = |start: u32, end: u32| {
+            let mut added = bitmap.clone();
+            added.add_range(start..end);
+
+            assert_eq!(bitmap.or_range_cardinality(start..end), added.cardinality() as u64, "{}..{}", start, end);
+        };
+
+        // Disjoint
+        check(3_000_000, 3_100_000);
+        check(5, 5);
+
+        // Overlapping and contained
+        check(250_000, 350_000);
+        check(400_000, 450_000);
+        check(0, 2_000_000);
+        check(100, 1_000_000);
+
+        // Ranges reaching the largest value
+        assert_eq!(bitmap.or_range_cardinality(..), 1 << 32);
+        assert_eq!(bitmap.or_range_cardinality(u32::MAX..), card);
+        assert_eq!(bitmap.or_range_cardinality((u32::MAX - 9)..=u32::MAX), card + 9);
+        assert_eq!(RoaringBitmap::new().or_range_cardinality(0..=u32::MAX), 1 << 32);
+    }
+
+    #[test]
+    fn iter_outside_range() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..4_000_000, 40_000));
+        bitmap.add_range(1_000_000..1_300_000);
+        bitmap.add(0);
+        bitmap.add(u32::MAX);
+
+        let check = |start: u32, end: u32| {
+            let expected = bitmap.iter().filter(|v| !(start..end).contains(v));
+            assert!(bitmap.iter_outside_range(start..end).eq(expected), "Failed for {}..{}", start, end);
+        };
+
+        check(0, 0);
+        check(5, 3);
+        check(0, 1);
+        check(100_000, 200_000);
+        check(65_536, 131_072);
+        check(65_000, 3_000_000);
+        check(1_100_000, 1_100_001);
+        check(3_999_000, u32::MAX);
+
+        // Unbounded and inclusive ranges reach the largest value
+        assert!(bitmap.iter_outside_range(..).next().is_none());
+        assert!(bitmap.iter_outside_range(1..=u32::MAX).eq(std::iter::once(0)));
+        assert!(bitmap.iter_outside_range(..u32::MAX).eq(std::iter::once(u32::MAX)));
+        assert!(RoaringBitmap::new().iter_outside_range(10..20).next().is_none());
+    }
+
+    #[test]
+    fn iter_gaps_in_range() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..4_000_000, 40_000));
+        bitmap.add_range(1_000_000..1_300_000);
+        bitmap.add(0);
+        bitmap.add(u32::MAX);
+
+        let check = |start: u32, end: u32| {
+            let expected = (start..end).filter(|v| !bitmap.contains(*v));
+            assert!(bitmap.iter_gaps_in_range(start..end).eq(expected), "Failed for {}..{}", start, end);
+        };
+
+        check(0, 0);
+        check(5, 3);
+        check(0, 1);
+        check(0, 100);
+        check(100_000, 200_000);
+        check(65_536, 131_072);
+        check(900_000, 1_400_000);
+        check(1_100_000, 1_100_001);
+        check(3_999_000, 4_100_000);
+        check(u32::MAX - 100, u32::MAX);
+
+        // Inclusive ranges reach the largest value
+        assert!(bitmap.iter_gaps_in_range((u32::MAX - 3)..=u32::MAX).eq((u32::MAX - 3)..u32::MAX));
+        assert!(bitmap.iter_gaps_in_range(u32::MAX..).next().is_none());
+        assert!(RoaringBitmap::new().iter_gaps_in_range(10..20).eq(10..20));
+        assert!(RoaringBitmap::new().iter_gaps_in_range((u32::MAX - 1)..).eq((u32::MAX - 1)..=u32::MAX));
+    }
+
+    #[test]
+    fn density() {
+        // A contiguous range is completely dense and compresses to less than a bit per value
+        let mut dense = RoaringBitmap::new();
+        dense.add_interval(1_000, 1_000_000);
+        assert_eq!(dense.density(), 1.0);
+        assert!(dense.bits_per_value() < 1.0);
+
+        // Every 1000th value is sparse and stored in arrays costing at least 16 bits per value
+        let sparse = RoaringBitmap::from_slice(&(0..10_000).map(|v| v * 1_000).collect::<Vec<u32>>());
+        assert!((sparse.density() - 10_000.0 / 9_999_001.0).abs() < 1e-12);
+        assert!(sparse.bits_per_value() >= 16.0);
+        assert!(sparse.bits_per_value() < 32.0);
+
+        let single = RoaringBitmap::from_slice(&[42]);
+        assert_eq!(single.density(), 1.0);
+
+        let empty = RoaringBitmap::new();
+        assert_eq!(empty.density(), 0.0);
+        assert_eq!(empty.bits_per_value(), 0.0);
+    }
+
+    #[test]
+    fn clone_compact() {
+        let mut bitmap = RoaringBitmap::with_capacity(64);
+
+        // Leave slack in the key and container vectors as well as the array containers
+        let data = generate_data(0..(1 << 22), 100_000);
+        bitmap.add_slice(&data);
+        for value in data.iter().step_by(2) {
+            bitmap.remove(*value);
+        }
+
+        bitmap.add_range(5_000_000..5_300_000);
+
+        let compact = bitmap.clone_compact();
+        assert!(compact.mem_size() <= bitmap.clone().mem_size());
+        assert!(compact.mem_size() < bitmap.mem_size());
+        assert_eq!(compact.keys.capacity(), compact.keys.len());
+        assert_eq!(compact.containers.capacity(), compact.containers.len());
+
+        assert!(compact == bitmap);
+        assert_eq!(compact.max_array_size(), bitmap.max_array_size());
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::RoaringBitmap;
-    use crate::test::*;
+    #[test]
+    fn compact() {
+        let mut bitmap = RoaringBitmap::new();
 
-    impl TestShim<u32> for RoaringBitmap {
-        fn from_data(data: &[u32]) -> Self {
-            Self::from_slice(data)
+        // Grow and then thin out several sparse containers leaving excess capacity behind
+        let data = generate_data(0..(1 << 22), 100_000);
+        for _ in 0..3 {
+            for value in data.iter() {
+                bitmap.add(*value);
+            }
+
+            for value in data.iter().step_by(3) {
+                bitmap.remove(*value);
+            }
         }
 
-        fn iter<'a>(&'a self) -> Box<dyn Iterator<Item=u32> + 'a> {
-            Box::new(self.iter())
+        // Values added one at a time end up in a bitset that is better stored as a run
+        for value in (10 << 16)..(12 << 16) {
+            bitmap.add(value);
         }
 
-        fn card(&self) -> usize {
-            self.cardinality()
+        let expected: Vec<u32> = bitmap.iter().collect();
+        let before = bitmap.mem_size();
+
+        bitmap.compact();
+
+        assert!(bitmap.mem_size() < before);
+        assert!(bitmap.iter().eq(expected.iter().cloned()));
+        assert_eq!(bitmap.cardinality(), expected.len());
+
+        // Compacting again changes nothing
+        let compacted = bitmap.mem_size();
+        bitmap.compact();
+        assert_eq!(bitmap.mem_size(), compacted);
+    }
+
+    #[test]
+    fn growth_policy() {
+        let mut pow2 = RoaringBitmap::with_growth_policy(GrowthPolicy::PowerOfTwo);
+        let mut exact = RoaringBitmap::with_growth_policy(GrowthPolicy::Exact);
+        assert_eq!(RoaringBitmap::new().growth_policy(), GrowthPolicy::PowerOfTwo);
+        assert_eq!(exact.growth_policy(), GrowthPolicy::Exact);
+
+        // Individual values in new containers
+        for key in 0..5u32 {
+            pow2.add(key << 16);
+            exact.add(key << 16);
         }
+
+        assert_eq!(pow2.containers.capacity(), 8);
+        assert_eq!(pow2.keys.capacity(), 8);
+        assert_eq!(exact.containers.capacity(), 5);
+        assert_eq!(exact.keys.capacity(), 5);
+
+        // Ranges spanning new containers
+        pow2.add_range((10 << 16)..(14 << 16));
+        exact.add_range((10 << 16)..(14 << 16));
+
+        assert_eq!(pow2.containers.capacity(), 16);
+        assert_eq!(exact.containers.capacity(), 9);
+
+        // Containers copied in from another bitmap, both between and after the existing ones
+        let other = RoaringBitmap::from_slice(&[(7 << 16), (20 << 16), (21 << 16), (22 << 16), (23 << 16)]);
+        pow2.inplace_or(&other);
+        exact.inplace_or(&other);
+
+        assert_eq!(pow2.containers.capacity(), 16);
+        assert_eq!(exact.containers.capacity(), 14);
+        assert_eq!(exact.keys.capacity(), 14);
+
+        assert!(pow2.iter().eq(exact.iter()));
+        assert_eq!(exact.cardinality(), 5 + (4 << 16) + 5);
     }
 
     #[test]
-    fn from_range() {
-        let bitmap = RoaringBitmap::from_range(0..std::u32::MAX);
+    fn trim() {
+        use crate::container::Container;
 
-        assert_eq!(bitmap.cardinality(), std::u32::MAX as usize);
+        let parts = vec![
+            ContainerPart::from_slice(&[1, 2, 3]),
+            ContainerPart::from_range(100..5_000),
+            ContainerPart::from_slice(&[7])
+        ];
+        let mut bitmap = RoaringBitmap::from_parts(vec![1, 4, 9], parts).unwrap();
+        let expected: Vec<u32> = bitmap.iter().collect();
+
+        // Nothing to remove from a well formed bitmap
+        assert_eq!(bitmap.trim(), 0);
+
+        // `from_parts` rejects empty containers so introduce them behind it's back
+        bitmap.keys.insert(0, 0);
+        bitmap.containers.insert(0, Container::new());
+        bitmap.keys.insert(3, 6);
+        bitmap.containers.insert(3, Container::None);
+        bitmap.keys.push(12);
+        bitmap.containers.push(Container::new());
+
+        assert_eq!(bitmap.trim(), 3);
+        assert_eq!(bitmap.keys, [1, 4, 9]);
+        assert!(bitmap.iter().eq(expected.iter().cloned()));
+
+        // The trimmed bitmap passes validation again
+        let mut bytes = Vec::new();
+        bitmap.serialize(&mut bytes).unwrap();
+        assert!(RoaringBitmap::validate_bytes(&bytes).is_ok());
+
+        let (keys, containers) = bitmap.into_parts();
+        assert!(RoaringBitmap::from_parts(keys, containers).is_ok());
+
+        // Bounds cached while the empty containers were present must not survive the trim
+        let parts = vec![ContainerPart::from_slice(&[1, 2, 3]), ContainerPart::from_slice(&[7])];
+        let mut bitmap = RoaringBitmap::from_parts(vec![1, 9], parts).unwrap();
+        bitmap.keys.insert(0, 0);
+        bitmap.containers.insert(0, Container::new());
+        bitmap.keys.push(12);
+        bitmap.containers.push(Container::new());
+
+        assert_eq!(bitmap.min(), None);
+        assert_eq!(bitmap.max(), None);
+
+        assert_eq!(bitmap.trim(), 2);
+        assert_eq!(bitmap.min(), Some((1 << 16) | 1));
+        assert_eq!(bitmap.max(), Some((9 << 16) | 7));
     }
 
     #[test]
-    fn from_slice() {
-        let input = generate_data(0..20_000_000, 500_000);
-        let bitmap = RoaringBitmap::from_slice(&input);
+    fn to_intervals() {
+        let mut bitmap = RoaringBitmap::from_slice(&[1, 2, 3, 5, 100_000]);
+
+        // Spans an array, a full run container and a bitset
+        bitmap.add_range(60_000..70_000);
+        bitmap.add_interval(131_072, 196_607);
+        bitmap.add_range(196_608..210_000);
+        bitmap.add_range(300_000..300_002);
+        bitmap.add(7_000_000);
+
+        let expected = vec![
+            1..=3,
+            5..=5,
+            60_000..=69_999,
+            100_000..=100_000,
+            131_072..=209_999,
+            300_000..=300_001,
+            7_000_000..=7_000_000
+        ];
 
-        assert_eq!(bitmap.len(), input.len());
+        assert_eq!(bitmap.to_intervals(), expected);
+        assert!(RoaringBitmap::new().to_intervals().is_empty());
 
-        for (found, expected) in bitmap.iter().zip(input.iter()) {
-            assert_eq!(found, *expected);
+        // The largest value is kept in the last interval
+        let mut top = RoaringBitmap::new();
+        top.add_range((u32::MAX - 10)..u32::MAX);
+        top.add(u32::MAX);
+        assert_eq!(top.to_intervals(), vec![(u32::MAX - 10)..=u32::MAX]);
+
+        assert_eq!(RoaringBitmap::from_slice(&[u32::MAX]).to_intervals(), vec![u32::MAX..=u32::MAX]);
+
+        // Bitmaps reaching the end of the universe round trip through their intervals
+        let mut tail = RoaringBitmap::from_range((u32::MAX - 200_000)..=u32::MAX);
+        tail.compact();
+        assert_eq!(tail.to_intervals(), vec![(u32::MAX - 200_000)..=u32::MAX]);
+
+        for bitmap in [top, tail, RoaringBitmap::from_slice(&[7, u32::MAX])].iter() {
+            let rebuilt = RoaringBitmap::from_intervals(bitmap.to_intervals());
+
+            assert_eq!(rebuilt.cardinality(), bitmap.cardinality());
+            assert_eq!(rebuilt.max(), Some(u32::MAX));
+            assert!(rebuilt.iter().eq(bitmap.iter()));
         }
     }
 
     #[test]
-    fn copy_from() {
-        let input = generate_data(0..20_000_000, 500_000);
-        let bitmap_a = RoaringBitmap::from_slice(&input);
+    fn from_intervals() {
+        let mut with_runs = RoaringBitmap::from_slice(&generate_data(0..4_000_000, 30_000));
+        with_runs.add_interval(5_000_000, 5_300_000);
+        with_runs.add_range(6_000_000..6_010_000);
+
+        let bitmaps = [
+            RoaringBitmap::new(),
+            RoaringBitmap::from_slice(&[0, 1, 2, 65_535, 65_536, 65_537]),
+            RoaringBitmap::from_slice(&generate_data(0..100_000, 60_000)),
+            with_runs
+        ];
 
-        let mut bitmap_b = RoaringBitmap::new();
-        bitmap_b.copy_from(&bitmap_a);
+        for bitmap in bitmaps.iter() {
+            let rebuilt = RoaringBitmap::from_intervals(bitmap.to_intervals());
 
-        assert_eq!(bitmap_a.len(), bitmap_b.len());
+            assert_eq!(rebuilt.cardinality(), bitmap.cardinality());
+            assert!(rebuilt.iter().eq(bitmap.iter()));
+        }
 
-        let iter = bitmap_a.iter()
-            .zip(bitmap_b.iter());
+        // Unsorted, overlapping and empty intervals
+        let bitmap = RoaringBitmap::from_intervals(vec![500..600, 10..20, 15..30, 40..40, 30..35]);
+        assert_eq!(bitmap.to_intervals(), vec![10..=34, 500..=599]);
+    }
 
-        for (a, b) in iter {
-            assert_eq!(a, b);
+    #[test]
+    fn fingerprint() {
+        // The same set stored as a bitset and an array, and as runs
+        let mut bitmap = RoaringBitmap::new();
+        for value in (1_000..20_000).chain(200_000..200_010) {
+            bitmap.add(value);
         }
+
+        let mut runs = bitmap.clone();
+        runs.compact();
+
+        assert!(bitmap.containers.iter().all(|c| !c.is_run()));
+        assert!(runs.containers.iter().all(|c| c.is_run()));
+        assert_eq!(bitmap.fingerprint(), runs.fingerprint());
+
+        // Changing, adding or removing a value changes the fingerprint
+        let mut moved = bitmap.clone();
+        moved.remove(200_005);
+        assert_ne!(moved.fingerprint(), bitmap.fingerprint());
+        moved.add(200_011);
+        assert_ne!(moved.fingerprint(), bitmap.fingerprint());
+
+        let mut extra = bitmap.clone();
+        extra.add(5_000_000);
+        assert_ne!(extra.fingerprint(), bitmap.fingerprint());
+
+        // Fingerprints are fixed across runs and builds
+        assert_eq!(RoaringBitmap::new().fingerprint(), 0);
+        assert_eq!(RoaringBitmap::from_slice(&[1, 2, 3, 100_000]).fingerprint(), 0x6812_961d_8f4b_fb0b);
+        assert_eq!(bitmap.fingerprint(), 0x8b29_6a17_2c78_214f);
     }
 
     #[test]
-    fn add() {
-        let mut bitmap = RoaringBitmap::new();
-        bitmap.add(10);
-        bitmap.add(255678);
+    fn first_difference() {
+        let mut a = RoaringBitmap::from_slice(&generate_data(0..2_000_000, 50_000));
+        a.add_range(3_000_000..3_100_000);
+
+        let b = a.clone();
+        assert_eq!(a.first_difference(&b), None);
+        assert_eq!(RoaringBitmap::new().first_difference(&RoaringBitmap::new()), None);
+
+        // Removing a value from either side is found
+        let mut c = a.clone();
+        c.remove(3_050_000);
+        assert_eq!(a.first_difference(&c), Some(3_050_000));
+        assert_eq!(c.first_difference(&a), Some(3_050_000));
+
+        // The smallest of several differences is reported
+        c.add(1_999_999);
+        c.add(2_500_000);
+        let expected = if a.contains(1_999_999) { 2_500_000 } else { 1_999_999 };
+        assert_eq!(a.first_difference(&c), Some(expected));
+
+        // One bitmap being a prefix of the other
+        let min = a.min().unwrap();
+        assert_eq!(a.first_difference(&RoaringBitmap::new()), Some(min));
+    }
 
-        assert_eq!(bitmap.cardinality(), 2);
-        assert!(bitmap.contains(10));
-        assert!(bitmap.contains(255678));
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn cmp_values() {
+        use std::cmp::Ordering;
+
+        let mut a = RoaringBitmap::from_slice(&generate_data(0..2_000_000, 50_000));
+        a.add_range(3_000_000..3_100_000);
+
+        // Equal contents in different representations
+        let mut b = a.clone();
+        b.compact();
+        assert_eq!(a.cmp_values(&b), Ordering::Equal);
+        assert!(a == b);
+
+        // A shared prefix with a shorter set compares less
+        let mut prefix = a.clone();
+        prefix.remove_range(3_050_000..);
+        assert_eq!(prefix.cmp_values(&a), Ordering::Less);
+        assert_eq!(a.cmp_values(&prefix), Ordering::Greater);
+        assert_eq!(RoaringBitmap::new().cmp_values(&prefix), Ordering::Less);
+
+        // The first differing value decides the order regardless of length
+        let mut smaller = prefix.clone();
+        smaller.add(3_050_000);
+        let mut larger = prefix.clone();
+        larger.add(3_060_000);
+        assert_eq!(smaller.cmp_values(&larger), Ordering::Less);
+        assert_eq!(a.cmp_values(&larger), Ordering::Less);
+
+        let mut low = RoaringBitmap::from_slice(&[1, 2, 3]);
+        let high = RoaringBitmap::from_slice(&[1, 5]);
+        assert_eq!(low.cmp_values(&high), Ordering::Less);
+        low.add_range(10..1_000_000);
+        assert_eq!(low.cmp_values(&high), Ordering::Less);
+
+        // Usable for sorting
+        let mut sorted = vec![high.clone(), low.clone(), high.clone(), RoaringBitmap::new()];
+        sorted.sort_by(RoaringBitmap::cmp_values);
+        sorted.dedup();
+
+        assert!(sorted.into_iter().eq([RoaringBitmap::new(), low, high].iter().cloned()));
+
+        // The inherent bounds accessors work on owned bitmaps
+        let owned = RoaringBitmap::from_slice(&[4, 9]);
+        assert_eq!(owned.min(), Some(4));
+        assert_eq!(owned.max(), Some(9));
+
+        // Usable as a key in sorted collections through the wrapper
+        let mut run = RoaringBitmap::from_range(1..3);
+        run.compact();
+
+        let set: BTreeSet<OrderedBitmap> = [&[1, 5][..], &[1, 2, 3], &[], &[1, 2], &[1, 5]]
+            .iter()
+            .map(|values| OrderedBitmap::from(RoaringBitmap::from_slice(values)))
+            .chain(Some(OrderedBitmap(run)))
+            .collect();
+
+        let found: Vec<Vec<u32>> = set.into_iter()
+            .map(|bitmap| bitmap.into_inner().iter().collect())
+            .collect();
+
+        assert_eq!(found, vec![vec![], vec![1, 2], vec![1, 2, 3], vec![1, 5]]);
     }
 
     #[test]
-    fn add_range() {
+    fn eq() {
+        let mut a = RoaringBitmap::from_slice(&generate_data(0..2_000_000, 50_000));
+        a.add_range(3_000_000..3_100_000);
+
+        // Equal regardless of representation
+        let mut b = a.clone();
+        b.compact();
+        assert!(a == b && b == a);
+
+        let mut c = a.clone();
+        c.remove(3_099_999);
+        assert!(a != c && c != a);
+
+        c.add(3_099_999);
+        c.add(5_000_000);
+        assert!(a != c);
+
+        c.remove(5_000_000);
+        assert!(a == c);
+        assert!(RoaringBitmap::new() == RoaringBitmap::new());
+        assert!(RoaringBitmap::new() != a);
+    }
+
+    #[test]
+    fn contains_all_any() {
         let mut bitmap = RoaringBitmap::new();
-        bitmap.add_range(0..1_000_000);
+        bitmap.add_range(100..200);
+        bitmap.add_range(300_000..500_000);
+
+        // Empty ranges
+        assert!(bitmap.contains_all(5..5));
+        assert!(!bitmap.contains_any(5..5));
+        assert!(RoaringBitmap::new().contains_all(5..5));
+
+        // Full coverage
+        assert!(bitmap.contains_all(100..200));
+        assert!(bitmap.contains_all(100..=199));
+        assert!(bitmap.contains_all(300_000..500_000));
+        assert!(bitmap.contains_any(100..200));
+        assert!(bitmap.contains_any(350_000..360_000));
+
+        // Partial coverage
+        assert!(!bitmap.contains_all(100..=200));
+        assert!(!bitmap.contains_all(50..150));
+        assert!(bitmap.contains_any(50..150));
+        assert!(bitmap.contains_any(199..=200));
+        assert!(bitmap.contains_any(0..));
+        assert!(bitmap.contains_any(200_000..300_001));
+        assert!(!bitmap.contains_all(100..500_000));
+
+        // No coverage
+        assert!(!bitmap.contains_any(200..300_000));
+        assert!(!bitmap.contains_any(500_000..));
+        assert!(!bitmap.contains_all(200..300));
+        assert!(!RoaringBitmap::new().contains_any(..));
+    }
 
-        assert_eq!(bitmap.cardinality(), 1_000_000);
+    #[test]
+    fn contains_range() {
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_range(0..10_000);
+
+        assert!(bitmap.contains_range(1000..6000));
     }
 
     #[test]
-    fn add_slice() {
-        let input = generate_data(0..20_000_000, 500_000);
+    fn contains_range_multi_container() {
         let mut bitmap = RoaringBitmap::new();
-        bitmap.add_slice(&input);
+        bitmap.add_range(60_000..300_000);
 
-        assert_eq!(bitmap.len(), input.len());
+        assert!(bitmap.contains_range(60_000..300_000));
+        assert!(bitmap.contains_range(65_536..131_072));
+        assert!(bitmap.contains_range(100_000..=299_999));
+        assert!(!bitmap.contains_range(59_999..70_000));
+        assert!(!bitmap.contains_range(100_000..300_001));
 
-        for (found, expected) in bitmap.iter().zip(input.iter()) {
-            assert_eq!(found, *expected);
+        bitmap.remove(200_000);
+        assert!(!bitmap.contains_range(100_000..250_000));
+    }
+
+    #[test]
+    fn contains_range_single_value() {
+        let bitmap = RoaringBitmap::from_slice(&[5, 1 << 16, u32::MAX]);
+
+        for value in [5, 1 << 16, u32::MAX].iter().cloned() {
+            assert!(bitmap.contains_range(value..=value));
+            assert!(!bitmap.contains_range(value - 1..=value));
         }
+
+        assert!(bitmap.contains_range(5..6));
+        assert!(!bitmap.contains_range(6..7));
+        assert!(!bitmap.contains_range(6..=6));
+        assert!(!bitmap.contains_range(4..=4));
+
+        // Every bitmap contains the empty range
+        assert!(bitmap.contains_range(5..5));
+        assert!(bitmap.contains_range(6..6));
+        assert!(RoaringBitmap::new().contains_range(5..5));
+        assert!(!RoaringBitmap::new().contains_range(5..=5));
     }
 
     #[test]
-    fn remove() {
+    fn get_index_range() {
+        let bitmap = RoaringBitmap::from_slice(&[
+            1 << 16, 
+            (3 << 16) + 5, 
+            (5 << 16) + 9
+        ]);
+
+        // Boundary keys present
+        assert_eq!(bitmap.get_index_range(1, 5), 0..3);
+        assert_eq!(bitmap.get_index_range(3, 3), 1..2);
+
+        // Boundary keys absent
+        assert_eq!(bitmap.get_index_range(0, 2), 0..1);
+        assert_eq!(bitmap.get_index_range(2, 4), 1..2);
+        assert_eq!(bitmap.get_index_range(4, 10), 2..3);
+
+        // No keys in range
+        assert!(bitmap.get_index_range(6, 10).is_empty());
+        assert!(bitmap.get_index_range(2, 2).is_empty());
+        assert!(RoaringBitmap::new().get_index_range(0, 10).is_empty());
+    }
+
+    #[test]
+    fn key_histogram() {
+        let mut bitmap = RoaringBitmap::from_slice(&[1, 2, 3, 70_000, (5 << 16) + 9]);
+        bitmap.add_range((9 << 16)..(10 << 16));
+
+        assert_eq!(bitmap.key_histogram(), vec![(0, 3), (1, 1), (5, 1), (9, 65536)]);
+        assert!(RoaringBitmap::new().key_histogram().is_empty());
+    }
+
+    #[test]
+    fn cardinality_range() {
         let input = generate_data(0..20_000_000, 500_000);
+        let bitmap = RoaringBitmap::from_slice(&input);
+
+        let ranges = [0..20_000_000, 70_000..140_000, 5_000_000..5_000_100, 65_536..131_072, 10..10];
+        for range in ranges.iter() {
+            let expected = input.iter()
+                .filter(|v| range.contains(*v))
+                .count();
+
+            assert_eq!(bitmap.cardinality_range(range.clone()), expected);
+        }
+    }
+
+    #[test]
+    fn cardinality() {
         let mut bitmap = RoaringBitmap::new();
-        bitmap.add_slice(&input);
+        bitmap.add_range(0..5_000);
+
+        assert_eq!(bitmap.cardinality(), 5000);
+    }
+
+    #[test]
+    fn select() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let bitmap = RoaringBitmap::from_slice(&input);
+
+        let exp_value = input[100];
+        let rank = 100;
         
-        // Remove the first 100 elements from the bitmap
-        for i in 0..100 {
-            bitmap.remove(input[i]);
+        // Oddly they're not self consistent. Rank and Select should be the exact inverse
+        // of each other but select is about 1 ahead mirroring their implementation
+        let value = bitmap.select(rank);
+
+        assert!(value.is_some());
+        assert_eq!(value.unwrap(), exp_value);
+    }
+
+    #[test]
+    fn try_select() {
+        let bitmap = RoaringBitmap::new();
+        assert_eq!(bitmap.try_select(0), Err(SelectError::Empty));
+
+        let input = generate_data(0..20_000_000, 500_000);
+        let bitmap = RoaringBitmap::from_slice(&input);
+
+        assert_eq!(bitmap.try_select(0), Ok(input[0]));
+        assert_eq!(bitmap.try_select(100), Ok(input[100]));
+        assert_eq!(bitmap.try_select((input.len() - 1) as u32), Ok(input[input.len() - 1]));
+        assert_eq!(
+            bitmap.try_select(input.len() as u32), 
+            Err(SelectError::OutOfRange { cardinality: input.len() })
+        );
+    }
+
+    #[test]
+    fn to_dense_bytes() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..3_000_000, 20_000));
+        bitmap.add_range(200_000..300_000);
+        bitmap.add_interval(520_000, 720_000);
+        bitmap.add(2_999_999);
+        bitmap.compact();
+        bitmap.add_range(1_000_000..1_010_000);
+
+        for universe in [0, 1, 13, 65_536, 200_003, 1_000_005, 3_000_000, 4_000_000].iter() {
+            let bytes = bitmap.to_dense_bytes(*universe);
+            assert_eq!(bytes.len(), (*universe as usize).div_ceil(8));
+
+            // Rebuild the set from the dense bits
+            let mut rebuilt = RoaringBitmap::new();
+            for (i, byte) in bytes.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (1 << bit) != 0 {
+                        rebuilt.add((i * 8 + bit) as u32);
+                    }
+                }
+            }
+
+            let expected = bitmap.iter().take_while(|v| v < universe);
+            assert!(rebuilt.iter().eq(expected), "Failed for universe {}", universe);
         }
 
-        assert_eq!(bitmap.len(), input.len() - 100);
+        assert!(RoaringBitmap::new().to_dense_bytes(100).iter().all(|b| *b == 0));
+    }
 
-        for (found, expected) in bitmap.iter().zip(input[100..].iter()) {
-            assert_eq!(found, *expected);
+    #[test]
+    fn from_word_slice() {
+        let words: Vec<u64> = generate_data(0..u64::MAX, 3_000)
+            .into_iter()
+            .enumerate()
+            .map(|(i, word)| if i % 7 == 0 { 0 } else { word })
+            .collect();
+
+        for base in [0, 1, 63, 64, 65_500, 1 << 20, u32::MAX - (words.len() as u32 * 64) + 1].iter() {
+            let mut expected = RoaringBitmap::new();
+            for (i, word) in words.iter().enumerate() {
+                for bit in 0..64 {
+                    if word & (1 << bit) != 0 {
+                        expected.add(base + (i as u32) * 64 + bit);
+                    }
+                }
+            }
+
+            let found = RoaringBitmap::from_word_slice(&words, *base);
+            assert!(found.iter().eq(expected.iter()), "Failed for base {}", base);
+            assert_eq!(found.keys, expected.keys);
         }
+
+        // Full words become a single run
+        let full = RoaringBitmap::from_word_slice(&[u64::MAX; 1024], 0);
+        assert!(full.iter().eq(0..(1 << 16)));
+
+        assert!(RoaringBitmap::from_word_slice(&[], 5).is_empty());
+        assert!(RoaringBitmap::from_word_slice(&[0, 0], 5).is_empty());
     }
 
     #[test]
-    fn remove_range() {
-        const MIN: u32 = 5_000_000;
-        const MAX: u32 = 10_000_000;
+    fn from_dense_bytes() {
+        let mut runs = RoaringBitmap::new();
+        runs.add_interval(70_000, 500_000);
+        runs.compact();
+
+        let mut mixed = RoaringBitmap::from_slice(&generate_data(0..3_000_000, 40_000));
+        mixed.add_range(1_000_000..1_010_000);
+        mixed.add(2_999_999);
+
+        let bitmaps = [
+            RoaringBitmap::new(),
+            RoaringBitmap::from_slice(&[0, 7, 8, 63, 64, 65_535, 65_536]),
+            RoaringBitmap::from_range(0..(1 << 16)),
+            runs,
+            mixed
+        ];
 
-        let input = generate_data(0..20_000_000, 500_000);
-        let mut bitmap = RoaringBitmap::new();
-        bitmap.add_slice(&input);
+        for bitmap in bitmaps.iter() {
+            let universe = bitmap.max().map_or(0, |max| max + 1);
 
-        // Remove the first all elements in [min-max)
-        bitmap.remove_range(MIN..MAX);
+            for universe in [universe, universe + 9, universe + (1 << 16)].iter() {
+                let rebuilt = RoaringBitmap::from_dense_bytes(&bitmap.to_dense_bytes(*universe));
 
-        let mut result = Vec::with_capacity(input.len());
-        for value in input.iter() {
-            if *value < MIN || *value >= MAX {
-                result.push(*value);
+                assert!(rebuilt.iter().eq(bitmap.iter()));
+                assert_eq!(rebuilt.keys, bitmap.keys);
             }
         }
 
-        assert_eq!(bitmap.cardinality(), result.len());
+        // Container types follow the population of each block
+        let rebuilt = RoaringBitmap::from_dense_bytes(&bitmaps[4].to_dense_bytes(3_000_000));
+        for container in rebuilt.containers.iter() {
+            let card = container.cardinality();
+            match container {
+                crate::container::Container::Array(_) => assert!(card <= 4096),
+                crate::container::Container::Bitset(_) => assert!(card > 4096),
+                _ => assert!(container.is_run())
+            }
+        }
 
-        let iter = bitmap.iter()
-            .zip(result.iter());
+        let rebuilt = RoaringBitmap::from_dense_bytes(&bitmaps[3].to_dense_bytes(500_001));
+        assert!(rebuilt.containers.iter().all(|c| c.is_run()));
+        assert!(RoaringBitmap::from_dense_bytes(&[0; 100_000]).is_empty());
+    }
 
-        for (found, expected) in iter {
-            assert_eq!(
-                found,
-                *expected,
-                "Missmatched elements. Found {:?}, expected {:?}",
-                found,
-                *expected
-            );
+    #[test]
+    fn iter_by_container() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..(1 << 22), 50_000));
+        bitmap.add_range(5_000_000..5_200_000);
+        bitmap.add_interval(7_000_000, 7_300_000);
+        bitmap.compact();
+
+        let mut rebuilt = Vec::new();
+        let mut keys = Vec::new();
+        for (key, values) in bitmap.iter_by_container() {
+            keys.push(key);
+            rebuilt.extend(values.map(|low| (u32::from(key) << 16) | u32::from(low)));
         }
+
+        assert_eq!(keys, bitmap.keys);
+        assert!(bitmap.iter().eq(rebuilt.iter().cloned()));
+        assert!(RoaringBitmap::new().iter_by_container().next().is_none());
     }
 
     #[test]
-    fn remove_slice() {
+    fn to_key_value_pairs() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..(1 << 22), 50_000));
+        bitmap.add_range(5_000_000..5_200_000);
+        bitmap.add_range((u32::MAX - 5)..=u32::MAX);
+        bitmap.compact();
+
+        let pairs = bitmap.to_key_value_pairs();
+        assert_eq!(pairs.len(), bitmap.cardinality());
+
+        let rebuilt = pairs.iter()
+            .map(|(key, low)| (u32::from(*key) << 16) | u32::from(*low));
+
+        assert!(bitmap.iter().eq(rebuilt));
+        assert!(RoaringBitmap::new().to_key_value_pairs().is_empty());
+    }
+
+    #[test]
+    fn iter_keys() {
+        let data = generate_data(0..u32::MAX, 5_000);
+        let bitmap = RoaringBitmap::from_slice(&data);
+
+        let mut expected: Vec<u32> = data.iter().map(|value| value & !0xFFFF).collect();
+        expected.dedup();
+
+        assert!(bitmap.iter_keys().eq(expected.into_iter()));
+        assert_eq!(bitmap.iter_keys().len(), bitmap.containers.len());
+
+        let edges = RoaringBitmap::from_slice(&[0, 65_535, 65_536, u32::MAX]);
+        assert!(edges.iter_keys().eq([0, 1 << 16, 0xFFFF_0000].iter().cloned()));
+        assert!(RoaringBitmap::new().iter_keys().is_empty());
+    }
+
+    #[test]
+    fn iter_ranked() {
         let input = generate_data(0..20_000_000, 500_000);
-        let mut bitmap = RoaringBitmap::new();
-        bitmap.add_slice(&input);
-        
-        // Remove the first 100 elements from the bitmap
-        bitmap.remove_slice(&input[..100]);
+        let bitmap = RoaringBitmap::from_slice(&input);
 
-        assert_eq!(bitmap.len(), input.len() - 100);
+        let ranked: Vec<(u32, usize)> = bitmap.iter_ranked()
+            .collect();
 
-        for (found, expected) in bitmap.iter().zip(input[100..].iter()) {
-            assert_eq!(found, *expected);
+        assert_eq!(ranked.len(), bitmap.cardinality());
+
+        for (i, (value, rank)) in ranked.iter().enumerate() {
+            assert_eq!(*rank, i);
+            assert_eq!(*value, input[i]);
         }
     }
 
     #[test]
-    fn contains() {
-        let input = generate_data(0..20_000_000, 500_000);
-        let mut bitmap = RoaringBitmap::new();
-        bitmap.add_slice(&input);
+    fn decode_into() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..4_000_000, 200_000));
+        bitmap.add_range(5_000_000..5_100_000);
+        bitmap.add_interval(6_000_000, 6_200_000);
+        bitmap.add(u32::MAX);
+
+        let expected: Vec<u32> = bitmap.iter().collect();
+        for chunk in [1usize, 7, 1_000, 4_096, 65_536, 1_000_000].iter() {
+            let mut out = vec![0; *chunk];
+            let mut cursor = DecodeCursor::new();
+            let mut found = Vec::new();
+
+            loop {
+                let count = bitmap.decode_into(&mut out, &mut cursor);
+                found.extend_from_slice(&out[..count]);
+
+                if count < out.len() {
+                    break;
+                }
+            }
 
-        for value in &input[..100] {
-            assert!(bitmap.contains(*value));
+            assert_eq!(found, expected, "Failed for chunk size {}", chunk);
+            assert_eq!(bitmap.decode_into(&mut out, &mut cursor), 0);
         }
+
+        // An empty output makes no progress
+        let mut cursor = DecodeCursor::new();
+        assert_eq!(bitmap.decode_into(&mut [], &mut cursor), 0);
+        assert_eq!(cursor, DecodeCursor::new());
     }
 
     #[test]
-    fn contains_range() {
-        let mut bitmap = RoaringBitmap::new();
-        bitmap.add_range(0..10_000);
+    fn sample_every() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..4_000_000, 200_000));
+        bitmap.add_range(5_000_000..5_300_000);
 
-        assert!(bitmap.contains_range(1000..6000));
+        let values: Vec<u32> = bitmap.iter().collect();
+        for n in [1u32, 2, 3, 1_000, 65_537, 1_000_000].iter() {
+            let sample = bitmap.sample_every(*n);
+
+            assert_eq!(sample.cardinality(), values.len().div_ceil(*n as usize), "Failed for {}", n);
+            assert!(sample.iter().eq(values.iter().cloned().step_by(*n as usize)), "Failed for {}", n);
+        }
+
+        assert!(bitmap.sample_every(1).iter().eq(bitmap.iter()));
+        assert!(RoaringBitmap::new().sample_every(7).is_empty());
     }
 
     #[test]
-    fn cardinality() {
-        let mut bitmap = RoaringBitmap::new();
-        bitmap.add_range(0..5_000);
+    fn shift() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..4_000_000, 200_000));
+        bitmap.add_range(5_000_000..5_300_000);
+        bitmap.add_range(0..10);
+        bitmap.add_range((u32::MAX - 10)..=u32::MAX);
+
+        let expected = |delta: i64| {
+            bitmap.iter()
+                .map(|value| i64::from(value) + delta)
+                .filter(|value| *value >= 0 && *value <= i64::from(u32::MAX))
+                .map(|value| value as u32)
+                .collect::<Vec<u32>>()
+        };
+
+        // Positive, negative and container aligned shifts
+        for delta in [0i64, 1, 7, 65_535, 1_000_003, -1, -5, -65_535, -1_000_003, 65_536, -65_536, 3 << 16, -(3 << 16)].iter() {
+            let shifted = bitmap.shift(*delta);
+            let expected = expected(*delta);
+
+            assert_eq!(shifted.cardinality(), expected.len(), "Failed for {}", delta);
+            assert!(shifted.iter().eq(expected.iter().cloned()), "Failed for {}", delta);
+            assert!(shifted.keys.windows(2).all(|w| w[0] < w[1]), "Failed for {}", delta);
+        }
+
+        // Aligned shifts keep the container types
+        let aligned = bitmap.shift(2 << 16);
+        for (a, b) in aligned.containers.iter().zip(bitmap.containers.iter()) {
+            assert_eq!(a.is_run(), b.is_run());
+        }
+
+        // Shifting out of the universe drops everything
+        assert!(bitmap.shift(1 << 32).is_empty());
+        assert!(bitmap.shift(-(1 << 32)).is_empty());
+        assert!(RoaringBitmap::new().shift(12).is_empty());
+    }
+
+    #[test]
+    fn reflect() {
+        let bitmap = RoaringBitmap::from_slice(&[0, 1, 5, 99, 100, 101, 70_000, 200_000]);
+
+        let reflected = bitmap.reflect(100);
+        assert!(reflected.iter().eq([0u32, 1, 95, 99, 100].iter().cloned()));
+
+        // Values spanning several containers land back in ascending order
+        let reflected = bitmap.reflect(200_000);
+        let mut expected: Vec<u32> = bitmap.iter().map(|value| 200_000 - value).collect();
+        expected.reverse();
+        assert!(reflected.iter().eq(expected.iter().cloned()));
+        assert!(reflected.reflect(200_000).iter().eq(bitmap.iter()));
+
+        // The full universe reflects including the extremes
+        let mut edges = RoaringBitmap::from_range(0..10);
+        edges.add_range((u32::MAX - 3)..=u32::MAX);
+        let expected: Vec<u32> = (0..4).chain((u32::MAX - 9)..=u32::MAX).collect();
+        assert!(edges.reflect(u32::MAX).iter().eq(expected.iter().cloned()));
+
+        assert!(RoaringBitmap::new().reflect(10).is_empty());
+        assert!(RoaringBitmap::from_slice(&[11, 12]).reflect(10).is_empty());
+    }
+
+    #[test]
+    fn to_rank_map() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let bitmap = RoaringBitmap::from_slice(&input);
+        let dict = bitmap.to_rank_map();
+
+        assert_eq!(dict.len(), input.len());
+
+        for i in (0..input.len()).step_by(997) {
+            assert_eq!(dict.get(input[i]), Some(i));
+        }
 
-        assert_eq!(bitmap.cardinality(), 5000);
+        assert_eq!(dict.get(input[input.len() - 1]), Some(input.len() - 1));
+
+        // Absent values have no rank
+        let absent = (0..20_000_000)
+            .find(|v| input.binary_search(v).is_err())
+            .unwrap();
+
+        assert_eq!(dict.get(absent), None);
+        assert_eq!(dict.get(30_000_000), None);
+        assert!(RoaringBitmap::new().to_rank_map().is_empty());
     }
 
     #[test]
-    fn select() {
-        let input = generate_data(0..20_000_000, 500_000);
+    fn to_select_dict() {
+        let input = generate_data(0..10_000_000, 100_000);
         let bitmap = RoaringBitmap::from_slice(&input);
+        let dict = bitmap.to_select_dict();
 
-        let exp_value = input[100];
-        let rank = 100;
-        
-        // Oddly they're not self consistent. Rank and Select should be the exact inverse
-        // of each other but select is about 1 ahead mirroring their implementation
-        let value = bitmap.select(rank);
+        for rank in 0..(input.len() as u32 + 10) {
+            assert_eq!(dict.get(rank), bitmap.select(rank));
+        }
 
-        assert!(value.is_some());
-        assert_eq!(value.unwrap(), exp_value);
+        assert_eq!(RoaringBitmap::new().to_select_dict().get(0), None);
+
+        // Empty containers don't hide the container sharing their prefix
+        let mut sparse = RoaringBitmap::from_slice(&[1, (2 << 16) | 5, (4 << 16) | 7]);
+        sparse.keys.insert(2, 3);
+        sparse.containers.insert(2, crate::container::Container::new());
+        sparse.keys.insert(1, 1);
+        sparse.containers.insert(1, crate::container::Container::new());
+
+        let dict = sparse.to_select_dict();
+        assert_eq!(dict.get(0), Some(1));
+        assert_eq!(dict.get(1), Some((2 << 16) | 5));
+        assert_eq!(dict.get(2), Some((4 << 16) | 7));
+        assert_eq!(dict.get(3), None);
     }
 
     #[test]
@@ -1791,6 +6276,72 @@ mod test {
         assert_eq!(max.unwrap(), 5_000);
     }
 
+    #[test]
+    fn cached_bounds() {
+        let mut bitmap = RoaringBitmap::from_slice(&[100, 200, 300_000]);
+
+        // Query before and after every mutation so a stale cache would be observed
+        let check = |bitmap: &RoaringBitmap| {
+            let expected = (bitmap.iter().next(), bitmap.iter().last());
+            assert_eq!((bitmap.min(), bitmap.max()), expected);
+            assert_eq!((bitmap.min(), bitmap.max()), expected);
+            assert_eq!((bitmap.find_min(), bitmap.find_max()), expected);
+        };
+
+        check(&bitmap);
+
+        bitmap.add(5);
+        check(&bitmap);
+        bitmap.add(u32::MAX);
+        check(&bitmap);
+        bitmap.remove(5);
+        check(&bitmap);
+        bitmap.remove(u32::MAX);
+        check(&bitmap);
+
+        bitmap.add_range(10..20);
+        check(&bitmap);
+        bitmap.add_range(1_000_000..2_000_000);
+        check(&bitmap);
+        bitmap.remove_range(1_500_000..);
+        check(&bitmap);
+        bitmap.remove_range(..150);
+        check(&bitmap);
+        bitmap.add_slice(&[1, 2_000_000]);
+        check(&bitmap);
+        bitmap.remove_slice(&[1, 2_000_000]);
+        check(&bitmap);
+        bitmap.inplace_not(0..10);
+        check(&bitmap);
+        bitmap.keep_inside_range(5..1_200_000);
+        check(&bitmap);
+
+        // Set operations and clones
+        let other = RoaringBitmap::from_slice(&[0, 9, 3_000_000]);
+        let cloned = bitmap.clone();
+        bitmap.inplace_or(&other);
+        check(&bitmap);
+        check(&cloned);
+        bitmap.inplace_and_not(&other);
+        check(&bitmap);
+        bitmap.inplace_xor(&other);
+        check(&bitmap);
+        bitmap.inplace_and(&other);
+        check(&bitmap);
+        bitmap.inplace_or_owned(cloned.clone());
+        check(&bitmap);
+
+        bitmap.clear();
+        check(&bitmap);
+        assert_eq!(bitmap.min(), None);
+
+        bitmap.copy_from(&cloned);
+        check(&bitmap);
+        bitmap.append_bitmap(&RoaringBitmap::from_slice(&[4_000_000]));
+        check(&bitmap);
+        assert_eq!(bitmap.max(), Some(4_000_000));
+    }
+
     #[test]
     fn subset_of() {
         let input = generate_data(0..20_000_000, 500_000);
@@ -1804,6 +6355,46 @@ mod test {
         assert!(!a.subset_of(&b));
     }
 
+    #[test]
+    fn contains_all_of() {
+        let input = generate_data(0..20_000_000, 500_000);
+        let a = RoaringBitmap::from_slice(&input);
+        let half = RoaringBitmap::from_slice(&input[0..(input.len() / 2)]);
+
+        // Full coverage
+        assert!(a.contains_all_of(&a));
+        assert!(a.contains_all_of(&half));
+
+        // Partial coverage
+        assert!(!half.contains_all_of(&a));
+
+        let absent = (a.min().unwrap()..a.max().unwrap())
+            .find(|value| !a.contains(*value))
+            .unwrap();
+
+        let mut missing = half.clone();
+        missing.add(absent);
+        assert!(!a.contains_all_of(&missing));
+
+        // Out of bounds and larger than self
+        let mut past_max = half.clone();
+        past_max.add(30_000_000);
+        assert!(!a.contains_all_of(&past_max));
+
+        let mut range = RoaringBitmap::new();
+        range.add_range(0..10);
+        let mut wide = RoaringBitmap::new();
+        wide.add_range(0..11);
+        assert!(!range.contains_all_of(&wide));
+        assert!(wide.contains_all_of(&range));
+
+        // The empty set is always covered
+        let empty = RoaringBitmap::new();
+        assert!(a.contains_all_of(&empty));
+        assert!(empty.contains_all_of(&empty));
+        assert!(!empty.contains_all_of(&a));
+    }
+
     #[test]
     fn round_trip_serialize() {
         let input = generate_data(0..20_000_000, 500_000);
@@ -1833,6 +6424,162 @@ mod test {
         }
     } 
 
+    #[test]
+    fn bitmap_reader() {
+        let mut with_runs = RoaringBitmap::from_slice(&generate_data(0..2_000_000, 20_000));
+        with_runs.add_range(3_000_000..3_200_000);
+        with_runs.add_range(4_000_000..4_000_500);
+        with_runs.compact();
+
+        let mut bitset = RoaringBitmap::from_slice(&generate_data(0..65_536, 20_000));
+        bitset.add(u32::MAX);
+
+        let mut few_runs = RoaringBitmap::from_range(10..1_000);
+        few_runs.add(500_000);
+
+        let no_runs = RoaringBitmap::from_slice(&generate_data(0..20_000_000, 50_000));
+
+        let empty = RoaringBitmap::new();
+        let bitmaps = [&with_runs, &bitset, &few_runs, &no_runs, &empty];
+        let formats = [SerializationFormat::Portable, SerializationFormat::Native];
+
+        let cases = bitmaps.iter()
+            .flat_map(|bitmap| formats.iter().map(move |format| (bitmap, format)));
+
+        for (bitmap, format) in cases {
+            let mut data = Vec::new();
+            bitmap.serialize_with(&mut data, *format).unwrap();
+            data.extend_from_slice(&[0xAB; 4]);
+
+            let expected = RoaringBitmap::deserialize(&mut std::io::Cursor::new(&data)).unwrap();
+
+            // Stream the containers one at a time and reassemble them
+            let mut reader = BitmapReader::new(data.as_slice()).unwrap();
+            assert_eq!(reader.len(), expected.keys.len());
+
+            let mut keys = Vec::new();
+            let mut parts = Vec::new();
+            while let Some(part) = reader.next_container() {
+                let (key, container) = part.unwrap();
+                keys.push(key);
+                parts.push(container);
+            }
+
+            assert!(reader.next_container().is_none());
+            assert_eq!(reader.into_inner(), &[0xAB; 4]);
+
+            let rebuilt = RoaringBitmap::from_parts(keys, parts).unwrap();
+            assert!(rebuilt.iter().eq(expected.iter()), "Failed for {:?}", format);
+            assert!(rebuilt.iter().eq(bitmap.iter()), "Failed for {:?}", format);
+        }
+
+        // A truncated stream fails on the container that is cut off and then stops
+        let mut data = Vec::new();
+        no_runs.serialize(&mut data).unwrap();
+
+        let mut reader = BitmapReader::new(&data[..(data.len() - 1)]).unwrap();
+        let results: Vec<_> = std::iter::from_fn(|| reader.next_container()).collect();
+        assert_eq!(results.len(), no_runs.keys.len());
+        assert!(results[..(results.len() - 1)].iter().all(|r| r.is_ok()));
+        assert!(results[results.len() - 1].is_err());
+
+        assert!(BitmapReader::new(&[1u8, 2, 3, 4, 5][..]).is_err());
+    }
+
+    #[test]
+    fn serialize_many() {
+        let mut runs = RoaringBitmap::new();
+        runs.add_range(0..100_000);
+        runs.add_range(1_000_000..1_500_000);
+        runs.compact();
+
+        let mut bitset = RoaringBitmap::from_slice(&generate_data(0..65_536, 20_000));
+        bitset.add(u32::MAX);
+
+        let bitmaps = [
+            RoaringBitmap::from_slice(&generate_data(0..20_000_000, 50_000)),
+            RoaringBitmap::new(),
+            runs,
+            bitset,
+            RoaringBitmap::from_slice(&[7])
+        ];
+
+        let mut data = Vec::new();
+        let written = RoaringBitmap::serialize_many(&bitmaps, &mut data).unwrap();
+        assert_eq!(written, data.len());
+
+        let found = RoaringBitmap::deserialize_many(&mut std::io::Cursor::new(&data)).unwrap();
+        assert_eq!(found.len(), bitmaps.len());
+        for (found, expected) in found.iter().zip(bitmaps.iter()) {
+            assert!(found.iter().eq(expected.iter()));
+        }
+
+        // An empty archive and a truncated archive
+        let mut empty = Vec::new();
+        RoaringBitmap::serialize_many(&[], &mut empty).unwrap();
+        assert!(RoaringBitmap::deserialize_many(&mut empty.as_slice()).unwrap().is_empty());
+        assert!(RoaringBitmap::deserialize_many(&mut &data[..(data.len() - 1)]).is_err());
+
+        // A frame longer than the bitmap it holds is rejected
+        let mut padded = Vec::new();
+        padded.extend_from_slice(&1u32.to_le_bytes());
+        let size = bitmaps[4].serialized_size();
+        padded.extend_from_slice(&(size as u32 + 2).to_le_bytes());
+        bitmaps[4].serialize(&mut padded).unwrap();
+        padded.extend_from_slice(&[0, 0]);
+
+        match RoaringBitmap::deserialize_many(&mut padded.as_slice()) {
+            Err(DeserializeError::InvalidFrameLength(len)) => assert_eq!(len as usize, size + 2),
+            _ => panic!("Expected an invalid frame length")
+        }
+
+        // A frame shorter than the bitmap it holds stops the read at the end of the frame
+        let mut short = Vec::new();
+        short.extend_from_slice(&2u32.to_le_bytes());
+        short.extend_from_slice(&(size as u32 - 2).to_le_bytes());
+        bitmaps[4].serialize(&mut short).unwrap();
+        RoaringBitmap::serialize_many(&bitmaps[..1], &mut short).unwrap();
+
+        match RoaringBitmap::deserialize_many(&mut short.as_slice()) {
+            Err(DeserializeError::IoError(err)) => assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof),
+            _ => panic!("Expected the frame to end early")
+        }
+
+        // The stream is left right after the archive
+        let mut trailing = data.clone();
+        trailing.extend_from_slice(&[0xAB; 4]);
+        let mut cursor = std::io::Cursor::new(&trailing);
+        RoaringBitmap::deserialize_many(&mut cursor).unwrap();
+        assert_eq!(cursor.position() as usize, data.len());
+    }
+
+    #[test]
+    fn write_values_le() {
+        let mut mixed = RoaringBitmap::from_slice(&generate_data(0..3_000_000, 40_000));
+        mixed.add_range(1_000_000..1_010_000);
+        mixed.add(u32::MAX);
+
+        let bitmaps = [
+            RoaringBitmap::new(),
+            RoaringBitmap::from_slice(&[0, 7, 65_536]),
+            mixed
+        ];
+
+        for bitmap in bitmaps.iter() {
+            let mut bytes = Vec::new();
+            let written = bitmap.write_values_le(&mut bytes).unwrap();
+
+            assert_eq!(written, bytes.len());
+            assert_eq!(written, bitmap.cardinality() * 4);
+
+            let values: Vec<u32> = bytes.chunks(4)
+                .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+
+            assert_eq!(values, bitmap.iter().collect::<Vec<u32>>());
+        }
+    }
+
     #[test]
     fn or() {
         op_test::<RoaringBitmap, RoaringBitmap, u32, _, RoaringBitmap>(
@@ -1868,6 +6615,145 @@ mod test {
         let not_bitmap = bitmap.not(..);
 
         assert_eq!(not_bitmap.cardinality(), (1 << 32) - bitmap.cardinality());
+
+        // An empty range leaves the bitmap unchanged
+        assert_eq!(bitmap.not(1_000..1_000), bitmap);
+        assert_eq!(bitmap.not(u32::MAX..u32::MAX), bitmap);
+
+        let mut inplace = bitmap.clone();
+        inplace.inplace_not(1_000..1_000);
+        assert_eq!(inplace, bitmap);
+    }
+
+    #[test]
+    fn ranges_including_max() {
+        let top = (u32::MAX - 99)..=u32::MAX;
+
+        // from_range
+        let bitmap = RoaringBitmap::from_range(top.clone());
+        assert_eq!(bitmap.cardinality(), 100);
+        assert_eq!(bitmap.max(), Some(u32::MAX));
+        assert_eq!(RoaringBitmap::from_range(..).cardinality(), 1 << 32);
+        assert_eq!(RoaringBitmap::from_range(u32::MAX..=u32::MAX).iter().collect::<Vec<u32>>(), [u32::MAX]);
+
+        // add_range
+        let mut bitmap = RoaringBitmap::from_slice(&[5, 70_000]);
+        bitmap.add_range(top.clone());
+        assert_eq!(bitmap.cardinality(), 102);
+        assert!(bitmap.contains(u32::MAX));
+
+        // contains_range and cardinality_range
+        assert!(bitmap.contains_range(top.clone()));
+        assert!(bitmap.contains_range(u32::MAX..=u32::MAX));
+        assert!(!bitmap.contains_range((u32::MAX - 100)..=u32::MAX));
+        assert!(bitmap.contains_any(u32::MAX..));
+        assert_eq!(bitmap.cardinality_range(top.clone()), 100);
+        assert_eq!(bitmap.cardinality_range(..=u32::MAX), 102);
+        assert_eq!(bitmap.cardinality_range(..), 102);
+
+        // not and inplace_not
+        let flipped = bitmap.not((u32::MAX - 199)..=u32::MAX);
+        assert_eq!(flipped.cardinality(), 102);
+        assert!(!flipped.contains(u32::MAX));
+        assert!(flipped.contains(u32::MAX - 100));
+        assert!(flipped.contains(70_000));
+
+        let mut inplace = bitmap.clone();
+        inplace.inplace_not((u32::MAX - 199)..=u32::MAX);
+        assert!(inplace.iter().eq(flipped.iter()));
+
+        let wide = (u32::MAX - (3 << 16))..=u32::MAX;
+        let mut inplace = bitmap.clone();
+        inplace.inplace_not(wide.clone());
+        assert_eq!(inplace.cardinality(), (3 << 16) + 1 - 100 + 2);
+        assert!(inplace.iter().eq(bitmap.not(wide).iter()));
+
+        // Flipping the two values that remain in a container removes the emptied container
+        let mut single = RoaringBitmap::from_slice(&[u32::MAX - 1, u32::MAX]);
+        single.inplace_not((u32::MAX - 1)..=u32::MAX);
+        assert!(single.is_empty());
+
+        // remove_range
+        bitmap.remove_range((u32::MAX - 9)..=u32::MAX);
+        assert_eq!(bitmap.cardinality(), 92);
+        assert_eq!(bitmap.max(), Some(u32::MAX - 10));
+
+        bitmap.remove_range(..=u32::MAX);
+        assert!(bitmap.is_empty());
+    }
+
+    #[test]
+    fn gaps() {
+        let ranges = [10..20, 30..40, 65_530..65_600, 200_000..200_010, 400_000..400_001];
+        let mut bitmap = RoaringBitmap::new();
+        for range in ranges.iter() {
+            bitmap.add_range(range.clone());
+        }
+
+        let gaps = bitmap.gaps();
+        let expected: Vec<u32> = (10..400_000)
+            .filter(|v| !ranges.iter().any(|r| r.contains(v)))
+            .collect();
+
+        assert_eq!(gaps.cardinality(), expected.len());
+        assert!(gaps.iter().eq(expected.iter().cloned()));
+        assert!(gaps.and(&bitmap).is_empty());
+
+        assert!(RoaringBitmap::new().gaps().is_empty());
+        assert!(RoaringBitmap::from_slice(&[5]).gaps().is_empty());
+        assert!(RoaringBitmap::from_range(100..200_000).gaps().is_empty());
+
+        let gaps = RoaringBitmap::from_slice(&[u32::MAX - 2, u32::MAX]).gaps();
+        assert_eq!(gaps.iter().collect::<Vec<u32>>(), vec![u32::MAX - 1]);
+    }
+
+    #[test]
+    fn merge_join() {
+        let a = RoaringBitmap::from_slice(&generate_seeded_data(0..500_000, 50_000, 1));
+        let b = RoaringBitmap::from_slice(&generate_seeded_data(0..500_000, 50_000, 2));
+
+        let joined: Vec<(u32, JoinSide)> = RoaringBitmap::merge_join(&a, &b).collect();
+        assert!(joined.windows(2).all(|w| w[0].0 < w[1].0));
+
+        let collect = |f: fn(JoinSide) -> bool| -> Vec<u32> {
+            joined.iter()
+                .filter(|(_, side)| f(*side))
+                .map(|(value, _)| *value)
+                .collect()
+        };
+
+        let or = collect(|_| true);
+        let and = collect(|side| side == JoinSide::Both);
+        let and_not = collect(|side| side == JoinSide::Left);
+
+        assert!(a.or(&b).iter().eq(or.into_iter()));
+        assert!(a.and(&b).iter().eq(and.into_iter()));
+        assert!(a.and_not(&b).iter().eq(and_not.into_iter()));
+
+        let empty = RoaringBitmap::new();
+        assert_eq!(RoaringBitmap::merge_join(&empty, &empty).count(), 0);
+        assert!(RoaringBitmap::merge_join(&a, &empty).all(|(_, side)| side == JoinSide::Left));
+    }
+
+    #[test]
+    fn hex_dump() {
+        let mut bitmap = RoaringBitmap::new();
+        bitmap.add_interval(0, 131_071);
+        bitmap.add_slice(&[200_000, 200_005]);
+
+        let dump = bitmap.hex_dump();
+        let lines: Vec<&str> = dump.lines().collect();
+
+        assert!(lines[0].starts_with("00000000  3b 30 02 00"));
+        assert!(lines[0].ends_with("cookie 12347, containers 3"));
+        assert!(dump.contains("run flags"));
+        assert!(dump.contains("key 3, cardinality 2"));
+        assert!(dump.contains("container 1 (run)"));
+        assert!(dump.contains("container 2 (array)"));
+
+        let plain = RoaringBitmap::from_slice(&[1, 2, 3]).hex_dump();
+        assert!(plain.contains("cookie 12346"));
+        assert!(plain.contains("offset 16"));
     }
 
     #[test]
@@ -1898,6 +6784,131 @@ mod test {
         );
     }
 
+    #[test]
+    fn lazy_union() {
+        let mut bitmaps: Vec<RoaringBitmap> = (0..20)
+            .map(|i| RoaringBitmap::from_slice(&generate_seeded_data(0..1_000_000, 5_000, i)))
+            .collect();
+
+        bitmaps.push(RoaringBitmap::from_range(100_000..300_000));
+        bitmaps.push(RoaringBitmap::new());
+        bitmaps.push(RoaringBitmap::from_slice(&[u32::MAX]));
+
+        let mut lazy = LazyUnion::new();
+        let mut expected = RoaringBitmap::new();
+        for bitmap in bitmaps.iter() {
+            lazy.push(bitmap);
+            expected.inplace_or(bitmap);
+        }
+
+        let found = lazy.finish();
+        assert_eq!(found.cardinality(), expected.cardinality());
+        assert!(found.iter().eq(expected.iter()));
+
+        assert!(LazyUnion::new().finish().is_empty());
+
+        let mut single = LazyUnion::new();
+        single.push(&bitmaps[0]);
+        assert!(single.finish().iter().eq(bitmaps[0].iter()));
+    }
+
+    #[test]
+    fn id_allocator() {
+        let mut ids = IdAllocator::new();
+        for expected in 0..100_000 {
+            assert_eq!(ids.alloc(), expected);
+        }
+
+        // Freed ids are reused smallest first
+        for id in [70_000, 5, 42, 65_536, 99_999].iter() {
+            ids.free(*id);
+            assert!(!ids.is_allocated(*id));
+        }
+
+        for expected in [5, 42, 65_536, 70_000, 99_999, 100_000, 100_001].iter() {
+            assert_eq!(ids.alloc(), *expected);
+        }
+
+        // Freeing every id in a container removes it from the bitmap
+        for id in 0..(1 << 16) {
+            ids.free(id);
+        }
+
+        assert_eq!(ids.allocated().cardinality(), 100_002 - (1 << 16));
+        assert_eq!(ids.alloc(), 0);
+        assert_eq!(ids.alloc(), 1);
+    }
+
+    #[test]
+    fn inplace_owned() {
+        let a = RoaringBitmap::from_slice(&generate_seeded_data(0..2_000_000, 20_000, 3));
+        let b = RoaringBitmap::from_slice(&generate_seeded_data(1_000_000..4_000_000, 20_000, 4));
+
+        let check = |f: fn(&mut RoaringBitmap, RoaringBitmap), g: fn(&mut RoaringBitmap, &RoaringBitmap)| {
+            let mut owned = a.clone();
+            f(&mut owned, b.clone());
+
+            let mut borrowed = a.clone();
+            g(&mut borrowed, &b);
+
+            assert!(owned.iter().eq(borrowed.iter()));
+            assert_eq!(owned.keys, borrowed.keys);
+        };
+
+        check(RoaringBitmap::inplace_or_owned, RoaringBitmap::inplace_or);
+        check(RoaringBitmap::inplace_and_owned, RoaringBitmap::inplace_and);
+        check(RoaringBitmap::inplace_and_not_owned, RoaringBitmap::inplace_and_not);
+        check(RoaringBitmap::inplace_xor_owned, RoaringBitmap::inplace_xor);
+
+        // Disjoint containers are moved rather than cloned
+        let low = RoaringBitmap::from_range(0..(1 << 20));
+        let high = RoaringBitmap::from_slice(&generate_data(1 << 20..1 << 24, 50_000));
+
+        let mut owned = low.clone();
+        let other = high.clone();
+        let before = allocation_count();
+        owned.inplace_or_owned(other);
+        let owned_allocations = allocation_count() - before;
+
+        let mut borrowed = low.clone();
+        let before = allocation_count();
+        borrowed.inplace_or(&high);
+        let borrowed_allocations = allocation_count() - before;
+
+        assert!(owned.iter().eq(borrowed.iter()));
+        assert!(owned_allocations <= 2, "Found {} allocations", owned_allocations);
+        assert!(borrowed_allocations >= high.containers.len());
+
+        // Stealing the contents of other into an empty bitmap keeps self's configuration,
+        // moved containers are converted to respect the threshold
+        let is_array = |c: &crate::container::Container| matches!(c, crate::container::Container::Array(_));
+        assert!(high.containers.iter().all(|c| is_array(c) && c.cardinality() > 64));
+
+        let mut owned = RoaringBitmap::with_max_array_size(64);
+        owned.inplace_or_owned(high.clone());
+        assert_eq!(owned.max_array_size(), 64);
+        assert!(owned.iter().eq(high.iter()));
+        assert!(!owned.containers.iter().any(is_array));
+
+        let mut owned = RoaringBitmap::with_max_array_size(64);
+        owned.add(0);
+        owned.add(u32::MAX);
+        owned.inplace_or_owned(high.clone());
+        assert_eq!(owned.cardinality(), high.cardinality() + 2);
+        assert!(owned.containers[1..owned.containers.len() - 1].iter().all(|c| !is_array(c)));
+
+        let mut owned = RoaringBitmap::with_max_array_size(64);
+        owned.add(0);
+        owned.inplace_xor_owned(high.clone());
+        assert_eq!(owned.cardinality(), high.cardinality() + 1);
+        assert!(owned.containers[1..].iter().all(|c| !is_array(c)));
+
+        let mut owned = RoaringBitmap::with_growth_policy(GrowthPolicy::Exact);
+        owned.inplace_or_owned(high.clone());
+        assert_eq!(owned.growth_policy(), GrowthPolicy::Exact);
+        assert_eq!(owned.max(), high.max());
+    }
+
     #[test]
     fn inplace_not() {
         let input = generate_data(0..20_000_000, 500_000);
@@ -1921,6 +6932,175 @@ mod test {
         );
     }
 
+    #[test]
+    fn fold() {
+        let mut bitmap = RoaringBitmap::from_slice(&generate_data(0..(1 << 20), 20_000));
+        bitmap.add_interval(300_000, 400_000);
+        bitmap.add_range(700_000..710_000);
+
+        let expected: u64 = bitmap.iter().map(u64::from).sum();
+        let found = bitmap.fold(0u64, |sum, value| sum + u64::from(value));
+
+        assert_eq!(found, expected);
+        assert_eq!(bitmap.fold(0, |count, _| count + 1), bitmap.cardinality());
+        assert_eq!(RoaringBitmap::new().fold(0, |count, _| count + 1), 0);
+    }
+
+    #[test]
+    fn and_is_empty() {
+        let a = RoaringBitmap::from_slice(&generate_seeded_data(0..4_000_000, 50_000, 1));
+        let b = RoaringBitmap::from_slice(&generate_seeded_data(0..4_000_000, 50_000, 2));
+
+        let mut disjoint = RoaringBitmap::from_slice(&generate_seeded_data(5_000_000..6_000_000, 5_000, 3));
+        disjoint.add_interval(8_000_000, 8_200_000);
+
+        // Shares keys with `a` but no values
+        let shared_keys = RoaringBitmap::from_slice(&a.iter().map(|v| v ^ 1).filter(|v| !a.contains(*v)).collect::<Vec<_>>());
+
+        let pairs = [
+            (&a, &b),
+            (&a, &disjoint),
+            (&a, &shared_keys),
+            (&disjoint, &a),
+            (&a, &a)
+        ];
+
+        for (x, y) in pairs.iter() {
+            assert_eq!(x.and_is_empty(y), x.and(y).is_empty());
+        }
+
+        assert!(a.and_is_empty(&RoaringBitmap::new()));
+        assert!(RoaringBitmap::new().and_is_empty(&a));
+    }
+
+    #[test]
+    fn and_not_is_empty() {
+        let a = RoaringBitmap::from_slice(&generate_seeded_data(0..4_000_000, 50_000, 1));
+        let b = RoaringBitmap::from_slice(&generate_seeded_data(0..4_000_000, 50_000, 2));
+        let superset = a.or(&b);
+
+        let mut missing_one = a.clone();
+        missing_one.remove(a.max().unwrap());
+
+        let pairs = [
+            (&a, &b),
+            (&a, &superset),
+            (&b, &superset),
+            (&superset, &a),
+            (&missing_one, &a),
+            (&a, &missing_one),
+            (&a, &a)
+        ];
+
+        for (x, y) in pairs.iter() {
+            assert_eq!(x.and_not_is_empty(y), x.and_not(y).is_empty());
+        }
+
+        assert!(!a.and_not_is_empty(&RoaringBitmap::new()));
+        assert!(RoaringBitmap::new().and_not_is_empty(&a));
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn par_and() {
+        let mut a = RoaringBitmap::from_slice(&generate_seeded_data(0..(1 << 24), 400_000, 1));
+        let mut b = RoaringBitmap::from_slice(&generate_seeded_data(0..(1 << 24), 400_000, 2));
+        a.add_interval(1 << 23, (1 << 23) + 500_000);
+        b.add_range((1 << 23) + 250_000..(1 << 23) + 800_000);
+
+        // Disjoint containers drop out of the result
+        a.add_range(20_000_000..20_001_000);
+        b.add_range(20_001_000..20_002_000);
+
+        for (x, y) in [(&a, &b), (&b, &a), (&a, &a), (&a, &RoaringBitmap::new())].iter() {
+            let sequential = x.and(y);
+            let parallel = x.par_and(y);
+
+            assert_eq!(parallel.keys, sequential.keys);
+            assert!(parallel.iter().eq(sequential.iter()));
+        }
+    }
+
+    #[test]
+    fn op_with_cardinality() {
+        let mut a = RoaringBitmap::from_slice(&generate_seeded_data(0..(1 << 22), 60_000, 3));
+        let mut b = RoaringBitmap::from_slice(&generate_seeded_data(0..(1 << 22), 60_000, 4));
+        a.add_interval(1 << 21, (1 << 21) + 200_000);
+        b.add_range((1 << 21) + 100_000..(1 << 21) + 300_000);
+        b.add_range(10_000_000..10_100_000);
+        a.compact();
+
+        let empty = RoaringBitmap::new();
+        let pairs = [(&a, &b), (&b, &a), (&a, &a), (&a, &empty), (&empty, &b)];
+
+        type Op = fn(&RoaringBitmap, &RoaringBitmap) -> RoaringBitmap;
+        type CountedOp = fn(&RoaringBitmap, &RoaringBitmap) -> (RoaringBitmap, usize);
+
+        for (x, y) in pairs.iter() {
+            let ops: [(Op, CountedOp); 4] = [
+                (RoaringBitmap::or, RoaringBitmap::or_with_cardinality),
+                (RoaringBitmap::and, RoaringBitmap::and_with_cardinality),
+                (RoaringBitmap::and_not, RoaringBitmap::and_not_with_cardinality),
+                (RoaringBitmap::xor, RoaringBitmap::xor_with_cardinality)
+            ];
+
+            for (op, op_with_cardinality) in ops.iter() {
+                let expected = op(x, y);
+                let (result, cardinality) = op_with_cardinality(x, y);
+
+                assert_eq!(cardinality, result.cardinality());
+                assert_eq!(cardinality, expected.cardinality());
+                assert!(result.iter().eq(expected.iter()));
+            }
+        }
+    }
+
+    #[test]
+    fn and_touched_keys() {
+        let a = RoaringBitmap::from_slice(&generate_seeded_data(0..(1 << 26), 500, 1));
+        let b = RoaringBitmap::from_slice(&generate_seeded_data(0..(1 << 26), 500, 2));
+
+        let expected = a.keys.iter()
+            .filter(|k| b.keys.binary_search(k).is_ok())
+            .count();
+
+        assert_eq!(a.and_touched_keys(&b), expected);
+        assert_eq!(b.and_touched_keys(&a), expected);
+        assert_eq!(a.and_touched_keys(&a), a.keys.len());
+        assert_eq!(a.and_touched_keys(&RoaringBitmap::new()), 0);
+    }
+
+    #[test]
+    fn estimated_and_cardinality() {
+        use rand::prelude::*;
+        use rand::rngs::SmallRng;
+
+        // Independent inputs, the seeded test data is too correlated between seeds
+        let mut rng = SmallRng::seed_from_u64(0x005E_1EC7);
+        for (max, count) in [(10_000_000, 200_000), (500_000, 300_000), (1 << 26, 50_000)].iter() {
+            let mut generate = || {
+                let values: Vec<u32> = (0..*count).map(|_| rng.gen_range(0, *max)).collect();
+                RoaringBitmap::from_slice(&values)
+            };
+
+            let a = generate();
+            let b = generate();
+
+            let estimate = a.estimated_and_cardinality(&b) as f64;
+            let exact = a.and_cardinality(&b) as f64;
+
+            assert!((estimate - exact).abs() <= exact * 0.1 + 16.0, "Estimated {} for {}", estimate, exact);
+            assert_eq!(a.estimated_and_cardinality(&b), b.estimated_and_cardinality(&a));
+        }
+
+        // Full containers are exact
+        let full = RoaringBitmap::from_range(0..(4 << 16));
+        let half = RoaringBitmap::from_range((1 << 16)..(3 << 16));
+        assert_eq!(full.estimated_and_cardinality(&half), 2 << 16);
+
+        assert_eq!(full.estimated_and_cardinality(&RoaringBitmap::new()), 0);
+    }
+
     #[test]
     fn and_not_cardinality() {
         op_card_test::<RoaringBitmap, RoaringBitmap, u32, _>(
@@ -1928,6 +7108,36 @@ mod test {
         );
     }
 
+    #[test]
+    fn and_not_cardinality_by_key() {
+        let mut a = RoaringBitmap::from_slice(&generate_seeded_data(0..5_000_000, 100_000, 1));
+        a.add_range(6_000_000..6_500_000);
+
+        let mut b = RoaringBitmap::from_slice(&generate_seeded_data(2_000_000..8_000_000, 100_000, 2));
+        b.add_range(6_200_000..6_300_000);
+
+        // Cover one block of `a` completely
+        let removed = 6_000_000 >> 16;
+        b.add_range((removed << 16)..((removed + 1) << 16));
+
+        let empty = RoaringBitmap::new();
+        for (x, y) in [(&a, &b), (&b, &a), (&a, &a), (&a, &empty), (&empty, &b)].iter() {
+            let by_key = x.and_not_cardinality_by_key(y);
+
+            assert!(by_key.iter().map(|(key, _)| *key).eq(x.keys.iter().cloned()));
+            assert_eq!(by_key.iter().map(|(_, count)| count).sum::<usize>(), x.and_not_cardinality(y));
+
+            for (key, count) in by_key.iter() {
+                let block = RoaringBitmap::from_range((u32::from(*key) << 16)..=(u32::from(*key) << 16 | 0xFFFF));
+                assert_eq!(*count, x.and(&block).and_not_cardinality(y));
+            }
+        }
+
+        // A block removed entirely by the filter is reported as zero
+        let by_key = a.and_not_cardinality_by_key(&b);
+        assert!(by_key.contains(&(removed as u16, 0)));
+    }
+
     #[test]
     fn xor_cardinality() {
         op_card_test::<RoaringBitmap, RoaringBitmap, u32, _>(
@@ -1935,6 +7145,24 @@ mod test {
         );
     }
 
+    #[test]
+    fn exclusive_cardinalities() {
+        let mut a = RoaringBitmap::from_slice(&generate_seeded_data(0..5_000_000, 100_000, 1));
+        a.add_range(6_000_000..6_500_000);
+
+        let mut b = RoaringBitmap::from_slice(&generate_seeded_data(2_000_000..8_000_000, 100_000, 2));
+        b.add_range(6_200_000..6_300_000);
+        b.add_range(9_000_000..9_100_000);
+
+        let empty = RoaringBitmap::new();
+        for (x, y) in [(&a, &b), (&b, &a), (&a, &a), (&a, &empty), (&empty, &b)].iter() {
+            assert_eq!(
+                x.exclusive_cardinalities(y),
+                (x.and_not_cardinality(y), y.and_not_cardinality(x))
+            );
+        }
+    }
+
     #[test]
     fn external_data() {
         use std::fs::File;
@@ -1973,4 +7201,218 @@ mod test {
             run_test(*test);
         }
     }
+
+    #[test]
+    fn java_no_run_layout() {
+        // Hand constructed to match the Java encoder, which always writes offsets in the no run format
+        fn encode(containers: &[(u16, Vec<u16>)]) -> Vec<u8> {
+            let mut data = Vec::new();
+            data.extend_from_slice(&12346u32.to_le_bytes());
+            data.extend_from_slice(&(containers.len() as u32).to_le_bytes());
+
+            for (key, values) in containers.iter() {
+                data.extend_from_slice(&key.to_le_bytes());
+                data.extend_from_slice(&((values.len() - 1) as u16).to_le_bytes());
+            }
+
+            let size = |values: &Vec<u16>| if values.len() > 4096 { 8192 } else { values.len() * 2 };
+
+            let mut offset = 8 + 8 * containers.len();
+            for (_key, values) in containers.iter() {
+                data.extend_from_slice(&(offset as u32).to_le_bytes());
+                offset += size(values);
+            }
+
+            for (_key, values) in containers.iter() {
+                if values.len() > 4096 {
+                    let mut words = vec![0u64; 1024];
+                    for value in values.iter() {
+                        words[*value as usize / 64] |= 1 << (*value % 64);
+                    }
+
+                    for word in words.iter() {
+                        data.extend_from_slice(&word.to_le_bytes());
+                    }
+                }
+                else {
+                    for value in values.iter() {
+                        data.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+
+            data
+        }
+
+        let fixtures: Vec<Vec<(u16, Vec<u16>)>> = vec![
+            // Fewer containers than the run format offset threshold
+            vec![(0, vec![1, 5, 65535])],
+            vec![(0, vec![7]), (3, (0..5_000).collect())],
+            vec![(1, vec![2, 4]), (2, vec![6]), (9, (0..10_000).map(|v| v * 3).collect())],
+
+            // At and above the threshold
+            (0..6).map(|k| (k * 2, (0..(100 + k)).collect())).collect()
+        ];
+
+        for containers in fixtures.iter() {
+            let data = encode(containers);
+            let expected: Vec<u32> = containers.iter()
+                .flat_map(|(key, values)| values.iter().map(move |v| (u32::from(*key) << 16) | u32::from(*v)))
+                .collect();
+
+            assert!(RoaringBitmap::validate_bytes(&data).is_ok());
+
+            let mut cursor = std::io::Cursor::new(&data);
+            let bitmap = RoaringBitmap::deserialize(&mut cursor).unwrap();
+
+            assert_eq!(cursor.position() as usize, data.len());
+            assert!(bitmap.iter().eq(expected.iter().cloned()));
+
+            // Re-encoding produces the same bytes
+            let mut buffer = Vec::new();
+            bitmap.serialize(&mut buffer).unwrap();
+            assert_eq!(buffer, data);
+        }
+    }
+
+    #[test]
+    fn deserialize_known_runs() {
+        // A single run container under key 5 holding [10, 14], [100, 100] and [65_000, 65_535]
+        let data: [u8; 23] = [
+            0x3B, 0x30, 0, 0,
+            1,
+            5, 0, 0x1D, 0x02,
+            3, 0,
+            10, 0, 4, 0,
+            100, 0, 0, 0,
+            0xE8, 0xFD, 0x17, 0x02
+        ];
+
+        let bitmap = RoaringBitmap::deserialize(&mut std::io::Cursor::new(&data[..])).unwrap();
+        let expected = (10..15).chain(100..101).chain(65_000..=65_535)
+            .map(|value| (5 << 16) | value);
+
+        assert!(bitmap.has_run());
+        assert!(!bitmap.is_empty());
+        assert_eq!(bitmap.cardinality(), 5 + 1 + 536);
+        assert!(bitmap.iter().eq(expected));
+    }
+
+    #[test]
+    fn serialize_formats() {
+        let mut with_runs = RoaringBitmap::from_slice(&generate_data(0..2_000_000, 20_000));
+        with_runs.add_range(3_000_000..3_200_000);
+
+        let fixtures = [
+            RoaringBitmap::new(),
+            RoaringBitmap::from_slice(&[1, 70_000, 140_000]),
+            RoaringBitmap::from_slice(&generate_data(0..4_000_000, 100_000)),
+            with_runs
+        ];
+
+        for bitmap in fixtures.iter() {
+            for format in [SerializationFormat::Portable, SerializationFormat::Native].iter() {
+                let mut data = Vec::new();
+                let written = bitmap.serialize_with(&mut data, *format).unwrap();
+
+                assert_eq!(written, data.len());
+                assert_eq!(written, bitmap.serialized_size_with(*format));
+                assert!(RoaringBitmap::validate_bytes(&data).is_ok());
+
+                let mut cursor = std::io::Cursor::new(&data);
+                let result = RoaringBitmap::deserialize(&mut cursor).unwrap();
+
+                assert_eq!(cursor.position() as usize, data.len());
+                assert!(result.iter().eq(bitmap.iter()), "Failed for {:?}", format);
+            }
+
+            // The portable format is the default and the native format never needs offsets
+            let mut portable = Vec::new();
+            bitmap.serialize(&mut portable).unwrap();
+            assert_eq!(portable.len(), bitmap.serialized_size_with(SerializationFormat::default()));
+
+            if bitmap.containers.len() >= 4 {
+                assert!(bitmap.serialized_size_with(SerializationFormat::Native) < portable.len());
+            }
+        }
+    }
+
+    #[test]
+    fn validate_bytes() {
+        use crate::DeserializeError;
+
+        const FILE_PATHS: [(&str, bool); 9] = [
+            ("res/test_data/no_runs.bin", true),
+            ("res/test_data/with_runs.bin", true),
+            ("res/test_data/crash_prone_0.bin", false),
+            ("res/test_data/crash_prone_1.bin", false),
+            ("res/test_data/crash_prone_2.bin", false),
+            ("res/test_data/crash_prone_3.bin", false),
+            ("res/test_data/crash_prone_4.bin", false),
+            ("res/test_data/crash_prone_5.bin", false),
+            ("res/test_data/crash_prone_6.bin", false)
+        ];
+
+        for (path, valid) in FILE_PATHS.iter() {
+            let data = std::fs::read(path).unwrap();
+            let result = RoaringBitmap::validate_bytes(&data);
+
+            assert_eq!(result.is_ok(), *valid, "Failed on '{}'", path);
+        }
+
+        // Round trip a bitmap with trailing data and ensure the stream is left after the bitmap
+        let mut bitmap = RoaringBitmap::from_slice(&[1, 2, 3, 100_000, 300_000]);
+        for value in (500_000..600_000).step_by(2) {
+            bitmap.add(value);
+        }
+
+        let mut data = Vec::new();
+        bitmap.serialize(&mut data).unwrap();
+        assert!(RoaringBitmap::validate_bytes(&data).is_ok());
+
+        let len = data.len();
+        data.extend_from_slice(&[0xAB; 8]);
+
+        let mut cursor = std::io::Cursor::new(&data);
+        let result = RoaringBitmap::deserialize(&mut cursor).unwrap();
+        assert!(result.iter().eq(bitmap.iter()));
+        assert_eq!(cursor.position() as usize, len);
+
+        // Truncating the data or corrupting an offset must be detected
+        assert!(RoaringBitmap::validate_bytes(&data[..(len - 1)]).is_err());
+
+        // Cookie, container count and key/cardinality pairs precede the offsets
+        assert!(!bitmap.has_run());
+        let offsets = 8 + 4 * bitmap.containers.len();
+
+        let mut corrupt = data.clone();
+        corrupt[offsets + 4] ^= 0xFF;
+        match RoaringBitmap::validate_bytes(&corrupt) {
+            Err(DeserializeError::InvalidOffset(_)) => {},
+            other => panic!("Expected an invalid offset, found {:?}", other)
+        }
+
+        // Deserializing checks the same layout as it reads, without needing to seek
+        match RoaringBitmap::deserialize(&mut &corrupt[..]) {
+            Err(DeserializeError::InvalidOffset(_)) => {},
+            other => panic!("Expected an invalid offset, found {:?}", other)
+        }
+
+        assert!(RoaringBitmap::deserialize(&mut &data[..(len - 1)]).is_err());
+
+        // A run container whose runs don't cover it's declared cardinality must be rejected.
+        // The single container has no offset header, it's cardinality follows the cookie, flags and key
+        let mut runs = RoaringBitmap::from_range(0..100);
+        runs.compact();
+        assert!(runs.has_run());
+
+        let mut data = Vec::new();
+        runs.serialize(&mut data).unwrap();
+        data[4 + 1 + 2] ^= 1;
+
+        match RoaringBitmap::deserialize(&mut &data[..]) {
+            Err(DeserializeError::InvalidCardinality(_)) => {},
+            other => panic!("Expected an invalid cardinality, found {:?}", other)
+        }
+    }
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 #![cfg(test)]
 
 use std::any::TypeId;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
 use std::ops::Range;
 use std::fmt::Debug;
 
@@ -13,6 +15,37 @@ use num_traits::ops::checked::CheckedAdd;
 
 use crate::container::*;
 
+/// A global allocator counting the allocations made by each thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // Ignore allocations made while the thread is being torn down
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Get the number of allocations made by the current thread so far
+/// 
+/// # Remarks
+/// Counts are per thread so they're unaffected by tests running concurrently
+pub(crate) fn allocation_count() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
 /// An internal trait for automating test setup
 pub(crate) trait TestShim<T> {
     fn from_data(data: &[T]) -> Self;
@@ -23,6 +56,7 @@ pub(crate) trait TestShim<T> {
 }
 
 /// The type of operation to perform
+#[derive(Clone, Copy, Debug)]
 pub(crate) enum OpType {
     /// Performs a union of the two input sets
     Or,